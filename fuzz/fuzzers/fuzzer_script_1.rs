@@ -8,8 +8,11 @@ fuzz_target!(|data: &[u8]| {
 
     if let Ok(ref s) = str::from_utf8(data) {
         if let Ok(exprs) = compilib::parse_expressions(&s) {
-            if let Err(err) = compilib::do_the_thing(exprs, &mut compilib::env::Environment::new()) {
-                compilib::error::print_error("<fuzzer>", &s, &err);
+            if let Err(errs) = compilib::do_the_thing(exprs,
+                                                      &mut compilib::env::Environment::new(),
+                                                      "<fuzzer>",
+                                                      s) {
+                compilib::error::print_errors("<fuzzer>", &s, &errs);
             }
         }
     }
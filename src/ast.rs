@@ -2,6 +2,7 @@
 
 use type_sys;
 
+use std::collections::HashMap;
 use std::fmt;
 
 /// Represents a span in the user code
@@ -14,7 +15,25 @@ pub struct Span(pub usize, pub usize);
 /// An ordered aggregation of expressions.
 #[derive(Debug,Clone,PartialEq)]
 pub struct Exprs {
-    pub exprs: Vec<Box<Expr>>,
+    /// The expressions, with their locations
+    pub exprs: Vec<(Box<Expr>, Span)>,
+    /// The comments written among the expressions, in the order of the source code (see
+    /// [`attach_comments`])
+    ///
+    /// [`attach_comments`]: ../parser/fn.attach_comments.html
+    pub comments: Vec<Comment>,
+    /// The types of the expressions, written by the type checker (the lint pass uses them to flag
+    /// the ignored values)
+    pub types: Vec<type_sys::Type>,
+}
+
+/// A comment of the user code (`// ...` up to the end of the line, or `/* ... */`, nestable)
+#[derive(Debug,Clone,PartialEq)]
+pub struct Comment {
+    /// The comment, delimiters included
+    pub text: String,
+    /// The location of the comment
+    pub span: Span,
 }
 
 /// A single expression
@@ -25,9 +44,9 @@ pub enum Expr {
 
     /// A new scope.
     ///
-    /// Composed of a list of variable declarations, a list of function declarations, and a list of
-    /// expressions to execute in that new scope.
-    Let(Vec<VariableDecl>, Vec<FunctionDecl>, Exprs),
+    /// Composed of a list of type declarations, a list of variable declarations, a list of
+    /// function declarations, and a list of expressions to execute in that new scope.
+    Let(Vec<TypeDecl>, Vec<VariableDecl>, Vec<FunctionDecl>, Exprs),
 
     /// A single expression representing multiple chained expressions
     Assign {
@@ -41,11 +60,34 @@ pub enum Expr {
         value_span: Span,
     },
 
+    /// An assignment to an element of a variable
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// matrix[1][2] := 42
+    /// ```
+    ElementAssign {
+        /// The assigned element
+        ///
+        /// Always an `Index` or a `Projection` expression whose innermost expression is a variable
+        /// (see [`Expr::is_place`](enum.Expr.html#method.is_place)).
+        target: Box<Expr>,
+        /// The location of the assigned element
+        target_span: Span,
+        /// The value to assign
+        value: Box<Expr>,
+        /// The location of the value
+        value_span: Span,
+    },
+
     /// A pattern match expression
     ///
+    /// The variables of the pattern are declared in the current scope.
+    ///
     /// # Examples
     ///
-    /// Provided x was defined and is of type Integer, this will return 1
+    /// This will return 1
     ///
     /// ```text
     /// match [x, 2, 3] := [1, 2, 3],
@@ -60,9 +102,17 @@ pub enum Expr {
         rhs: Box<Expr>,
         /// The location of the right hand side
         rhs_span: Span,
+        /// The variables bound by the pattern
+        ///
+        /// Filled by the type checker: if the pattern does not match, these variables are still
+        /// declared, with a default value.
+        bindings: Vec<PatternBinding>,
     },
 
     /// A function call
+    ///
+    /// Also used for the construction of a variant of a sum type (see
+    /// [`VariantDecl`](struct.VariantDecl.html)).
     Function {
         /// The name of the function
         name: String,
@@ -82,10 +132,25 @@ pub enum Expr {
         true_branch: Box<Expr>,
         /// The location of the true branch
         true_branch_span: Span,
-        /// What to do if the condition is false
-        false_branch: Box<Expr>,
-        /// The location of the false branch
-        false_branch_span: Span,
+        /// What to do if the condition is false, with its location (without an `else`, nothing is
+        /// done and the whole expression is Void)
+        false_branch: Option<(Box<Expr>, Span)>,
+        /// The type of the true branch, written by the type checker (without an `else`, the lint
+        /// pass uses it to flag an ignored value)
+        true_branch_type: Option<type_sys::Type>,
+    },
+
+    /// A case expression: evaluates the first arm whose pattern matches the value (and whose
+    /// guard is truthy)
+    Case {
+        /// The value to match against the patterns
+        expr: Box<Expr>,
+        /// The location of the value
+        expr_span: Span,
+        /// The arms, in order
+        arms: Vec<CaseArm>,
+        /// The location of the whole expression
+        span: Span,
     },
 
     /// A While loop (obviously)
@@ -99,17 +164,54 @@ pub enum Expr {
     },
 
     /// A For loop (obviously)
+    ///
+    /// The iterated variable goes up (`to`) or down (`downto`) by the step (1 by default), and the
+    /// loop stops as soon as it reaches or passes the goal.
     For {
         /// The name and initial value of the variable iterated over
         binding: Box<VariableDecl>,
-        /// The upper bound for the iterated variable
+        /// Whether the iterated variable goes up or down
+        direction: ForDirection,
+        /// The bound for the iterated variable (never reached)
         goal: Box<Expr>,
-        /// The location of the upper bound
+        /// The location of the bound
         goal_span: Span,
+        /// The positive step of the iterated variable, with its location
+        step: Option<(Box<Expr>, Span)>,
         /// The body of the For loop
         expr: Box<Expr>,
     },
 
+    /// A For loop over the elements of an Array, or the characters of a Str
+    ForIn {
+        /// The name of the variable bound to each element
+        name: String,
+        /// The location of the name
+        name_span: Span,
+        /// The iterated Array or Str
+        iterable: Box<Expr>,
+        /// The location of the iterated Array or Str
+        iterable_span: Span,
+        /// The body of the For loop
+        expr: Box<Expr>,
+    },
+
+    /// Stops the innermost loop (only inside the body of a loop)
+    Break(Span),
+
+    /// Goes to the next iteration of the innermost loop (only inside the body of a loop)
+    Continue(Span),
+
+    /// Returns a value from the enclosing function (only inside the body of a function)
+    Return {
+        /// The returned value
+        value: Box<Expr>,
+        /// The location of the returned value
+        value_span: Span,
+        /// The location of the whole expression
+        span: Span,
+    },
+
     /// A binary operator
     BinaryOp {
         /// The left hand side of the operator
@@ -122,6 +224,22 @@ pub enum Expr {
         span: Span,
     },
 
+    /// A short-circuit boolean operator
+    ///
+    /// The right hand side is only evaluated if the left hand side does not decide the result.
+    LogicalOp {
+        /// The left hand side of the operator
+        lhs: Box<Expr>,
+        /// The location of the left hand side
+        lhs_span: Span,
+        /// The right hand side of the operator
+        rhs: Box<Expr>,
+        /// The location of the right hand side
+        rhs_span: Span,
+        /// The operator
+        op: LogicalOpCode,
+    },
+
     /// An unary operator
     UnaryOp {
         /// The body
@@ -142,6 +260,67 @@ pub enum Expr {
         dest: type_sys::Type,
     },
 
+    /// A fallible cast operation
+    ///
+    /// Returns a `{ok, value}` tuple instead of failing at runtime. If the conversion failed,
+    /// `ok` is `false` and `value` is the default value of the destination type.
+    TryCast {
+        /// The expression to cast
+        expr: Box<Expr>,
+        /// The location of the expression
+        expr_span: Span,
+        /// The type to cast to
+        dest: type_sys::Type,
+    },
+
+    /// A read of an element of an Array or a Str
+    Index {
+        /// The indexed expression
+        expr: Box<Expr>,
+        /// The location of the indexed expression
+        expr_span: Span,
+        /// The index
+        index: Box<Expr>,
+        /// The location of the index
+        index_span: Span,
+    },
+
+    /// A read of an element of a Tuple
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// {1, true}.1
+    /// ```
+    Projection {
+        /// The projected expression
+        expr: Box<Expr>,
+        /// The location of the projected expression
+        expr_span: Span,
+        /// The index of the element
+        index: usize,
+        /// The location of the index
+        index_span: Span,
+    },
+
+    /// A read of a field of a Record
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// point.x
+    /// ```
+    Field {
+        /// The expression whose field is read
+        expr: Box<Expr>,
+        /// The location of the expression whose field is read
+        expr_span: Span,
+        /// The name of the field
+        name: String,
+        /// The location of the name of the field
+        name_span: Span,
+    },
+
     /// A read of a variable
     Variable {
         /// The name of the variable
@@ -170,10 +349,73 @@ pub enum Expr {
     /// Composed of a list of expressions
     Tuple(Vec<Box<Expr>>),
 
+    /// A Record construction
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// Point {x = 1., y = 2.}
+    /// ```
+    Record {
+        /// The name of the record type
+        name: String,
+        /// The initial values of the fields (in any order)
+        fields: Vec<FieldInit>,
+        /// The location of the whole expression
+        span: Span,
+    },
+
+    /// An anonymous function
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// function(x: Integer): Integer := x * 2
+    /// ```
+    Lambda(FunctionDecl),
+
+    /// An interpolated string
+    ///
+    /// The embedded expressions are converted to Str (like with `as Str`), and concatenated with
+    /// the text around them.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// "x = ${x}, y = ${y + 1}"
+    /// ```
+    Interpolation(Vec<InterpolationPart>),
+
+    /// A wildcard (`_`, only in patterns): matches anything
+    Wildcard(Span),
+
+    /// The rest of an array (`..rest`, only in array patterns): matches any number of elements
+    Rest {
+        /// The name of the variable bound to the matched elements (if any)
+        name: Option<String>,
+        /// The location of the rest pattern
+        span: Span,
+    },
+
     /// A literal value
     Value(type_sys::Value),
 }
 
+impl Expr {
+    /// Returns true if the expression designates something that can be assigned to
+    ///
+    /// That is either a variable, or an element of something that can be assigned to.
+    pub fn is_place(&self) -> bool {
+        match *self {
+            Expr::Variable { .. } => true,
+            Expr::Index { ref expr, .. } |
+            Expr::Projection { ref expr, .. } |
+            Expr::Field { ref expr, .. } => expr.is_place(),
+            _ => false,
+        }
+    }
+}
+
 /// Represents a binary operator
 #[derive(Debug,Clone,Copy,PartialEq,Eq)]
 pub enum BinaryOpCode {
@@ -181,6 +423,19 @@ pub enum BinaryOpCode {
     Sub,
     Mul,
     Div,
+    /// The floored modulo (of the sign of the right hand side)
+    Mod,
+    Pow,
+    /// The floored division of Integers
+    IntDiv,
+
+    BitAnd,
+    BitOr,
+    BitXor,
+    /// The left shift of an Integer
+    Shl,
+    /// The arithmetic right shift of an Integer
+    Shr,
 
     Lt,
     Le,
@@ -201,6 +456,15 @@ impl fmt::Display for BinaryOpCode {
                    Sub => "-",
                    Mul => "*",
                    Div => "/",
+                   Mod => "mod",
+                   Pow => "**",
+                   IntDiv => "div",
+
+                   BitAnd => "&",
+                   BitOr => "|",
+                   BitXor => "^",
+                   Shl => "<<",
+                   Shr => ">>",
 
                    Lt => "<",
                    Le => "<=",
@@ -212,11 +476,33 @@ impl fmt::Display for BinaryOpCode {
     }
 }
 
+/// Represents a short-circuit boolean operator
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum LogicalOpCode {
+    And,
+    Or,
+}
+
+impl fmt::Display for LogicalOpCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::LogicalOpCode::*;
+
+        write!(f,
+               "{}",
+               match *self {
+                   And => "and",
+                   Or => "or",
+               })
+    }
+}
+
 /// Represents an unary operator
 #[derive(Debug,Clone,Copy,PartialEq,Eq)]
 pub enum UnaryOpCode {
     Plus,
     Minus,
+    /// The boolean negation (not a builtin: its operand is a condition)
+    Not,
 }
 
 impl fmt::Display for UnaryOpCode {
@@ -228,6 +514,27 @@ impl fmt::Display for UnaryOpCode {
                match *self {
                    Plus => "+",
                    Minus => "-",
+                   Not => "not",
+               })
+    }
+}
+
+/// Represents the direction of a For loop
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum ForDirection {
+    Up,
+    Down,
+}
+
+impl fmt::Display for ForDirection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::ForDirection::*;
+
+        write!(f,
+               "{}",
+               match *self {
+                   Up => "to",
+                   Down => "downto",
                })
     }
 }
@@ -242,6 +549,10 @@ pub enum Declaration {
     Function(FunctionDecl),
     /// A argument declaration (in a function declaration)
     Argument(ArgumentDecl),
+    /// A type declaration (in a Let expression)
+    Type(TypeDecl),
+    /// A variable bound by a pattern
+    Pattern(PatternBinding),
 }
 
 impl Declaration {
@@ -252,7 +563,9 @@ impl Declaration {
         match *self {
             Variable(VariableDecl { ref name, .. }) |
             Function(FunctionDecl { ref name, .. }) |
-            Argument(ArgumentDecl { ref name, .. }) => name,
+            Argument(ArgumentDecl { ref name, .. }) |
+            Type(TypeDecl { ref name, .. }) |
+            Pattern(PatternBinding { ref name, .. }) => name,
         }
     }
 
@@ -263,7 +576,9 @@ impl Declaration {
         match *self {
             Variable(VariableDecl { span, .. }) |
             Function(FunctionDecl { signature_span: span, .. }) |
-            Argument(ArgumentDecl { span, .. }) => span,
+            Argument(ArgumentDecl { span, .. }) |
+            Type(TypeDecl { span, .. }) |
+            Pattern(PatternBinding { span, .. }) => span,
         }
     }
 }
@@ -282,14 +597,27 @@ pub struct VariableDecl {
 }
 
 /// Represents a function declaration
+///
+/// # Examples
+///
+/// ```text
+/// function add(x: Integer, y: Integer): Integer := x + y
+/// function id<T>(x: T): T := x
+/// function fact(n) := if n = 0 then 1 else n * fact(n - 1)
+/// ```
+///
+/// The types of the arguments and the return type may be omitted: they are then inferred by the
+/// type checker, which fills them in.
 #[derive(Debug,Clone,PartialEq)]
 pub struct FunctionDecl {
     /// The name of the function
     pub name: String,
+    /// The type parameters of the function (if generic), with their locations
+    pub type_params: Vec<(String, Span)>,
     /// The arguments of the function
     pub args: Vec<ArgumentDecl>,
-    /// The return type
-    pub return_type: type_sys::Type,
+    /// The return type (None if omitted and not inferred yet)
+    pub return_type: Option<type_sys::Type>,
     /// The location of the whole signature
     pub signature_span: Span,
     /// The body of the function
@@ -299,19 +627,86 @@ pub struct FunctionDecl {
 }
 
 impl FunctionDecl {
-    /// Get the return type of the function provided a list of arguments
+    /// Deduce the types of the type parameters of the function from the types of the arguments
     ///
     /// Returns None if the given arguments are not valid for this function
-    pub fn return_type(&self, arg_types: &[type_sys::Type]) -> Option<&type_sys::Type> {
-        if self.args.len() == arg_types.len() &&
-           arg_types
-               .iter()
-               .zip(&self.args)
-               .all(|(type_got, &ArgumentDecl { ref type_, .. })| type_got == type_) {
-            Some(&self.return_type)
-        } else {
-            None
+    pub fn instantiate(&self,
+                       arg_types: &[type_sys::Type])
+                       -> Option<HashMap<String, type_sys::Type>> {
+        if self.args.len() != arg_types.len() {
+            return None;
+        }
+
+        let mut substitution = type_sys::Substitution::new();
+
+        let params = self.type_params
+            .iter()
+            .map(|&(ref name, _)| (name.clone(), substitution.fresh()))
+            .collect::<HashMap<_, _>>();
+
+        // The arguments may be generic closures, whose own type parameters stand for any type
+        let mut names = Vec::new();
+        for type_ in arg_types {
+            collect_type_params(type_, &mut names);
         }
+        let closure_params = names
+            .into_iter()
+            .map(|name| (name, substitution.fresh()))
+            .collect::<HashMap<_, _>>();
+
+        for (type_got, arg) in arg_types.iter().zip(&self.args) {
+            let expected = arg.get_type()
+                .substitute(&|name| params.get(name).cloned());
+            let type_got = type_got.substitute(&|name| closure_params.get(name).cloned());
+
+            if !substitution.unify(&expected, &type_got) {
+                return None;
+            }
+        }
+
+        Some(params
+                 .into_iter()
+                 .map(|(name, var)| (name, substitution.apply(&var)))
+                 .collect())
+    }
+
+    /// Get the type of the function (when used as a value)
+    ///
+    /// Panics if some omitted types have not been inferred yet.
+    pub fn get_type(&self) -> type_sys::Type {
+        type_sys::Type::Function(self.args.iter().map(ArgumentDecl::get_type).collect(),
+                                 Box::new(self.get_return_type()))
+    }
+
+    /// Get the return type of the function
+    ///
+    /// Panics if it was omitted and has not been inferred yet.
+    pub fn get_return_type(&self) -> type_sys::Type {
+        self.return_type
+            .clone()
+            .unwrap_or_else(|| panic!("Return type of `{}` not inferred", self.name))
+    }
+}
+
+// Collect the names of the type parameters used inside a type
+fn collect_type_params(type_: &type_sys::Type, names: &mut Vec<String>) {
+    use type_sys::Type::*;
+
+    match *type_ {
+        Param(ref name) if !names.contains(name) => names.push(name.clone()),
+        Array(ref type_) => collect_type_params(type_, names),
+        Tuple(ref types) => {
+            for type_ in types {
+                collect_type_params(type_, names);
+            }
+        }
+        Function(ref args, ref return_type) => {
+            for type_ in args {
+                collect_type_params(type_, names);
+            }
+            collect_type_params(return_type, names);
+        }
+        Void | Integer | Float | Bool | Str | Named(_) | Param(_) | Var(_) => {}
     }
 }
 
@@ -320,8 +715,146 @@ impl FunctionDecl {
 pub struct ArgumentDecl {
     /// The argument name
     pub name: String,
-    /// The argument type
-    pub type_: type_sys::Type,
+    /// The argument type (None if omitted and not inferred yet)
+    pub type_: Option<type_sys::Type>,
     /// The location of the argument declaration
     pub span: Span,
 }
+
+impl ArgumentDecl {
+    /// Get the type of the argument
+    ///
+    /// Panics if it was omitted and has not been inferred yet.
+    pub fn get_type(&self) -> type_sys::Type {
+        self.type_
+            .clone()
+            .unwrap_or_else(|| panic!("Type of argument `{}` not inferred", self.name))
+    }
+}
+
+/// Represents a variable bound by a pattern
+#[derive(Debug,Clone,PartialEq)]
+pub struct PatternBinding {
+    /// The variable name
+    pub name: String,
+    /// The variable type (deduced from the matched value)
+    pub type_: type_sys::Type,
+    /// The location of the variable in the pattern
+    pub span: Span,
+}
+
+/// Represents a type declaration
+///
+/// # Examples
+///
+/// ```text
+/// type Point = {x: Float, y: Float}
+/// type Shape = Circle(Float) | Rect(Float, Float)
+/// ```
+#[derive(Debug,Clone,PartialEq)]
+pub struct TypeDecl {
+    /// The name of the type
+    pub name: String,
+    /// The definition of the type
+    pub definition: TypeDefinition,
+    /// The location of the declaration
+    pub span: Span,
+}
+
+impl TypeDecl {
+    /// Returns the declaration of the field of the given name (if this is a record type)
+    pub fn get_field(&self, name: &str) -> Option<&FieldDecl> {
+        match self.definition {
+            TypeDefinition::Record(ref fields) => fields.iter().find(|field| field.name == name),
+            TypeDefinition::Sum(_) => None,
+        }
+    }
+
+    /// Returns the declaration of the variant of the given name (if this is a sum type)
+    pub fn get_variant(&self, name: &str) -> Option<&VariantDecl> {
+        match self.definition {
+            TypeDefinition::Record(_) => None,
+            TypeDefinition::Sum(ref variants) => {
+                variants.iter().find(|variant| variant.name == name)
+            }
+        }
+    }
+}
+
+/// Represents the definition of a declared type
+#[derive(Debug,Clone,PartialEq)]
+pub enum TypeDefinition {
+    /// A record type, composed of named fields
+    Record(Vec<FieldDecl>),
+    /// A sum type (also called tagged union), composed of variants
+    Sum(Vec<VariantDecl>),
+}
+
+/// Represents a field declaration (in a record type declaration)
+#[derive(Debug,Clone,PartialEq)]
+pub struct FieldDecl {
+    /// The field name
+    pub name: String,
+    /// The field type
+    pub type_: type_sys::Type,
+    /// The location of the field declaration
+    pub span: Span,
+}
+
+/// Represents a variant declaration (in a sum type declaration)
+///
+/// Each variant defines a constructor of the same name, called like a function.
+#[derive(Debug,Clone,PartialEq)]
+pub struct VariantDecl {
+    /// The variant name
+    pub name: String,
+    /// The types of the values carried by the variant
+    pub types: Vec<type_sys::Type>,
+    /// The location of the variant declaration
+    pub span: Span,
+}
+
+/// Represents an arm of a case expression
+///
+/// # Examples
+///
+/// ```text
+/// Circle(r) when r > 0. => r * r
+/// ```
+#[derive(Debug,Clone,PartialEq)]
+pub struct CaseArm {
+    /// The pattern to match
+    pub pattern: Box<Expr>,
+    /// The location of the pattern
+    pub pattern_span: Span,
+    /// The guard (and its location), which must be truthy for the arm to be taken
+    pub guard: Option<(Box<Expr>, Span)>,
+    /// What to evaluate if the arm is taken
+    pub body: Box<Expr>,
+    /// The location of the body
+    pub body_span: Span,
+    /// The variables bound by the pattern (filled by the type checker)
+    pub bindings: Vec<PatternBinding>,
+}
+
+/// Represents the initialization of a field (in a record construction)
+#[derive(Debug,Clone,PartialEq)]
+pub struct FieldInit {
+    /// The field name
+    pub name: String,
+    /// The location of the field name
+    pub name_span: Span,
+    /// The initial value of the field
+    pub value: Box<Expr>,
+    /// The location of the initial value
+    pub value_span: Span,
+}
+
+/// Represents a part of an interpolated string
+#[derive(Debug,Clone,PartialEq)]
+pub enum InterpolationPart {
+    /// Some text (with its escape sequences already replaced)
+    Text(String),
+    /// An embedded expression (`${...}`), with its location
+    Expr(Box<Expr>, Span),
+}
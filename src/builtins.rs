@@ -1,17 +1,26 @@
 //! Where all the builtins are defined (Rust code-wise)
 //!
 //! This includes print / println functions, binary / unary operators...
+//!
+//! Builtins do not know where they were called from: errors are returned with an empty location,
+//! which is then set by the evaluator.
 
+use ast::Span;
+use error::{DivisionByZeroError, NegativeOperandError, OverflowError, RuntimeError};
 use type_sys::Value;
 use type_sys::Value::*;
 
 macro_rules! define_arit_operator {
-    ( $symbol:tt, $func_name:ident) => {
+    ( $symbol:tt, $func_name:ident, $checked_func:ident) => {
 
-        pub fn $func_name(args: &[Value]) -> Value {
+        pub fn $func_name(args: &[Value]) -> Result<Value, RuntimeError> {
             match (&args[0], &args[1]) {
-                (&Integer(lhs), &Integer(rhs)) => Integer(lhs $symbol rhs),
-                (&Float(lhs), &Float(rhs)) => Float(lhs $symbol rhs),
+                (&Integer(lhs), &Integer(rhs)) => {
+                    lhs.$checked_func(rhs)
+                        .map(Integer)
+                        .ok_or_else(|| overflow(format!("{} {} {}", lhs, stringify!($symbol), rhs)))
+                }
+                (&Float(lhs), &Float(rhs)) => Ok(Float(lhs $symbol rhs)),
                 (lhs, rhs) => unreachable!("Wrong type of arguments in `{}`: {:?}, {:?}",
                                            stringify!($func_name),
                                            lhs,
@@ -22,62 +31,213 @@ macro_rules! define_arit_operator {
     }
 }
 
+macro_rules! define_bit_operator {
+    ( $symbol:tt, $func_name:ident) => {
+        pub fn $func_name(args: &[Value]) -> Result<Value, RuntimeError> {
+            match (&args[0], &args[1]) {
+                (&Integer(lhs), &Integer(rhs)) => Ok(Integer(lhs $symbol rhs)),
+                (lhs, rhs) => unreachable!("Wrong type of arguments in `{}`: {:?}, {:?}",
+                                           stringify!($func_name),
+                                           lhs,
+                                           rhs)
+            }
+        }
+    }
+}
+
 macro_rules! define_cmp_operator {
     ( $symbol:tt, $func_name:ident) => {
-        pub fn $func_name(args: &[Value]) -> Value {
-            Bool(args[0] $symbol args[1])
+        pub fn $func_name(args: &[Value]) -> Result<Value, RuntimeError> {
+            Ok(Bool(args[0] $symbol args[1]))
         }
     }
 }
 
+fn overflow(operation: String) -> RuntimeError {
+    OverflowError::new(operation, Span(0, 0)).into()
+}
+
+fn negative_operand(operation: String) -> RuntimeError {
+    NegativeOperandError::new(operation, Span(0, 0)).into()
+}
+
 //===================
 //== Builtin funcs ==
 //===================
 
-pub fn print(args: &[Value]) -> Value {
+pub fn print(args: &[Value]) -> Result<Value, RuntimeError> {
     print!("{}", args[0]);
-    Void
+    Ok(Void)
 }
 
-pub fn println(args: &[Value]) -> Value {
+pub fn println(args: &[Value]) -> Result<Value, RuntimeError> {
     println!("{}", args[0]);
-    Void
+    Ok(Void)
 }
 
 //===========================
 //== Arithmetic operations ==
 //===========================
 
-pub fn un_plus(args: &[Value]) -> Value {
+pub fn un_plus(args: &[Value]) -> Result<Value, RuntimeError> {
     match &args[0] {
-        &Integer(val) => Integer(val),
-        &Float(val) => Float(val),
+        &Integer(val) => Ok(Integer(val)),
+        &Float(val) => Ok(Float(val)),
         val => unreachable!("Wrong type of arguments in `un+`: {:?}", val),
     }
 }
 
-pub fn un_minus(args: &[Value]) -> Value {
+pub fn un_minus(args: &[Value]) -> Result<Value, RuntimeError> {
     match &args[0] {
-        &Integer(val) => Integer(-val),
-        &Float(val) => Float(-val),
+        &Integer(val) => {
+            val.checked_neg()
+                .map(Integer)
+                .ok_or_else(|| overflow(format!("-{}", val)))
+        }
+        &Float(val) => Ok(Float(-val)),
         val => unreachable!("Wrong type of arguments in `un-`: {:?}", val),
     }
 }
 
-pub fn plus(args: &[Value]) -> Value {
+pub fn plus(args: &[Value]) -> Result<Value, RuntimeError> {
     match (&args[0], &args[1]) {
-        (&Integer(lhs), &Integer(rhs)) => Integer(lhs + rhs),
-        (&Float(lhs), &Float(rhs)) => Float(lhs + rhs),
-        (&Str(ref lhs), &Str(ref rhs)) => Str(lhs.clone() + rhs.as_str()),
+        (&Integer(lhs), &Integer(rhs)) => {
+            lhs.checked_add(rhs)
+                .map(Integer)
+                .ok_or_else(|| overflow(format!("{} + {}", lhs, rhs)))
+        }
+        (&Float(lhs), &Float(rhs)) => Ok(Float(lhs + rhs)),
+        (&Str(ref lhs), &Str(ref rhs)) => Ok(Str(lhs.clone() + rhs.as_str())),
         (lhs, rhs) => unreachable!("Wrong type of arguments in `plus`: {:?}, {:?}",
                                    lhs,
                                    rhs)
     }
 }
 
-define_arit_operator!(-, minus);
-define_arit_operator!(*, mul);
-define_arit_operator!(/, div);
+define_arit_operator!(-, minus, checked_sub);
+define_arit_operator!(*, mul, checked_mul);
+
+pub fn div(args: &[Value]) -> Result<Value, RuntimeError> {
+    match (&args[0], &args[1]) {
+        (&Integer(_), &Integer(0)) => Err(DivisionByZeroError::new(Span(0, 0)).into()),
+        (&Integer(lhs), &Integer(rhs)) => {
+            lhs.checked_div(rhs)
+                .map(Integer)
+                .ok_or_else(|| overflow(format!("{} / {}", lhs, rhs)))
+        }
+        (&Float(lhs), &Float(rhs)) => Ok(Float(lhs / rhs)),
+        (lhs, rhs) => unreachable!("Wrong type of arguments in `div`: {:?}, {:?}",
+                                   lhs,
+                                   rhs)
+    }
+}
+
+/// The result is of the sign of the right hand side (`-7 mod 2 = 1`), to go with `div`
+pub fn modulo(args: &[Value]) -> Result<Value, RuntimeError> {
+    match (&args[0], &args[1]) {
+        (&Integer(_), &Integer(0)) => Err(DivisionByZeroError::new(Span(0, 0)).into()),
+        (&Integer(lhs), &Integer(rhs)) => {
+            // Only `i64::MIN % -1` wraps, and gives the right result: 0
+            let rem = lhs.wrapping_rem(rhs);
+            if rem != 0 && (rem < 0) != (rhs < 0) {
+                Ok(Integer(rem + rhs))
+            } else {
+                Ok(Integer(rem))
+            }
+        }
+        (&Float(lhs), &Float(rhs)) => Ok(Float(lhs - rhs * (lhs / rhs).floor())),
+        (lhs, rhs) => unreachable!("Wrong type of arguments in `modulo`: {:?}, {:?}",
+                                   lhs,
+                                   rhs)
+    }
+}
+
+/// Rounds towards negative infinity (`-7 div 2 = -4`), unlike `/`
+pub fn floor_div(args: &[Value]) -> Result<Value, RuntimeError> {
+    match (&args[0], &args[1]) {
+        (&Integer(_), &Integer(0)) => Err(DivisionByZeroError::new(Span(0, 0)).into()),
+        (&Integer(lhs), &Integer(rhs)) => {
+            let quot = lhs.checked_div(rhs)
+                .ok_or_else(|| overflow(format!("{} div {}", lhs, rhs)))?;
+            if lhs % rhs != 0 && (lhs < 0) != (rhs < 0) {
+                Ok(Integer(quot - 1))
+            } else {
+                Ok(Integer(quot))
+            }
+        }
+        (lhs, rhs) => unreachable!("Wrong type of arguments in `floor_div`: {:?}, {:?}",
+                                   lhs,
+                                   rhs)
+    }
+}
+
+pub fn power(args: &[Value]) -> Result<Value, RuntimeError> {
+    use std::u32;
+
+    match (&args[0], &args[1]) {
+        (&Integer(lhs), &Integer(rhs)) if rhs < 0 => {
+            Err(negative_operand(format!("{} ** {}", lhs, rhs)))
+        }
+        (&Integer(lhs), &Integer(rhs)) => {
+            let result = if rhs > u32::MAX as i64 {
+                // Only a few numbers do not overflow with such an exponent
+                match lhs {
+                    0 | 1 => Some(lhs),
+                    -1 => Some(if rhs % 2 == 0 { 1 } else { -1 }),
+                    _ => None,
+                }
+            } else {
+                lhs.checked_pow(rhs as u32)
+            };
+
+            result
+                .map(Integer)
+                .ok_or_else(|| overflow(format!("{} ** {}", lhs, rhs)))
+        }
+        (&Float(lhs), &Float(rhs)) => Ok(Float(lhs.powf(rhs))),
+        (lhs, rhs) => unreachable!("Wrong type of arguments in `power`: {:?}, {:?}",
+                                   lhs,
+                                   rhs)
+    }
+}
+
+//========================
+//== Bitwise operations ==
+//========================
+
+define_bit_operator!(&, bit_and);
+define_bit_operator!(|, bit_or);
+define_bit_operator!(^, bit_xor);
+
+/// Bits shifted out of an Integer (or changing its sign) are an overflow
+pub fn shift_left(args: &[Value]) -> Result<Value, RuntimeError> {
+    match (&args[0], &args[1]) {
+        (&Integer(lhs), &Integer(rhs)) if rhs < 0 => {
+            Err(negative_operand(format!("{} << {}", lhs, rhs)))
+        }
+        (&Integer(0), &Integer(_)) => Ok(Integer(0)),
+        (&Integer(lhs), &Integer(rhs)) if rhs < 64 && (lhs << rhs) >> rhs == lhs => {
+            Ok(Integer(lhs << rhs))
+        }
+        (&Integer(lhs), &Integer(rhs)) => Err(overflow(format!("{} << {}", lhs, rhs))),
+        (lhs, rhs) => unreachable!("Wrong type of arguments in `shift_left`: {:?}, {:?}",
+                                   lhs,
+                                   rhs)
+    }
+}
+
+/// Keeps the sign of the Integer: shifting enough gives 0 or -1
+pub fn shift_right(args: &[Value]) -> Result<Value, RuntimeError> {
+    match (&args[0], &args[1]) {
+        (&Integer(lhs), &Integer(rhs)) if rhs < 0 => {
+            Err(negative_operand(format!("{} >> {}", lhs, rhs)))
+        }
+        (&Integer(lhs), &Integer(rhs)) => Ok(Integer(lhs >> rhs.min(63))),
+        (lhs, rhs) => unreachable!("Wrong type of arguments in `shift_right`: {:?}, {:?}",
+                                   lhs,
+                                   rhs)
+    }
+}
 
 //========================
 //== Logical Operations ==
@@ -88,15 +248,15 @@ define_cmp_operator!(<=, lower_eq);
 define_cmp_operator!(>,  greater);
 define_cmp_operator!(>=, greater_eq);
 
-pub fn equal(args: &[Value]) -> Value {
+pub fn equal(args: &[Value]) -> Result<Value, RuntimeError> {
     use std::f64::EPSILON;
 
     match (&args[0], &args[1]) {
-        (&Float(lhs), &Float(rhs)) => Bool((lhs - rhs).abs() < EPSILON),
-        (lhs, rhs) => Bool(lhs == rhs),
+        (&Float(lhs), &Float(rhs)) => Ok(Bool((lhs - rhs).abs() < EPSILON)),
+        (lhs, rhs) => Ok(Bool(lhs == rhs)),
     }
 }
 
-pub fn not_equal(args: &[Value]) -> Value {
-    Bool(args[0] != args[1])
+pub fn not_equal(args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Bool(args[0] != args[1]))
 }
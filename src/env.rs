@@ -1,17 +1,22 @@
 //! Where the environment is managed (hopefully in an ecological manner)
 //!
-//! The environment comprise the scopes (variables and functions), builtins and defined types.
-//! There is currently no way of defining custom types, but the generic type system is implemented
-//! (see the [`type_sys::Generic`] enum)
+//! The environment comprise the scopes (variables, functions, user-declared types and type
+//! parameters), builtins and defined generic types. Generic types cannot be defined by the user,
+//! and are only used for the signatures of builtins (see the [`type_sys::Generic`] enum). Users
+//! write generic functions with type parameters instead (see the [`type_sys::Type::Param`]
+//! variant).
 //!
 //! [`type_sys::Generic`]: ../type_sys/enum.Generic.html
+//! [`type_sys::Type::Param`]: ../type_sys/enum.Type.html#variant.Param
 
-use ast::{Declaration, FunctionDecl, ArgumentDecl, VariableDecl, Span};
+use ast::{Declaration, FunctionDecl, ArgumentDecl, PatternBinding, TypeDecl, TypeDefinition,
+          VariableDecl, VariantDecl, Span};
 use builtins;
-use error::AlreadyDeclaredError;
-use type_sys::{Value, Type, Generic, AbstractType, SumType, Match};
+use error::{AlreadyDeclaredError, RuntimeError, TypeCheckError, UnboundedVarError,
+            UndefinedFunctionError};
+use type_sys::{Value, Type, Generic, AbstractType, SumType, Match, Substitution};
 
-use std::collections::{LinkedList, HashMap};
+use std::collections::{LinkedList, HashMap, HashSet};
 use std::collections::hash_map::Entry;
 
 /// The main struct containing the whole environment
@@ -28,9 +33,27 @@ pub struct Environment<T> {
     pub builtins: HashMap<&'static str, BuiltinInfo>,
     /// The defined generic types (defined globally)
     pub types: HashMap<&'static str, Generic>,
+    /// The types deduced for the type variables (only used by the type checker, to infer the
+    /// omitted types of the functions)
+    pub substitution: Substitution,
+    /// The errors found so far (only used by the type checker, which goes on after an error to
+    /// report all of them)
+    pub errors: Vec<TypeCheckError>,
+    /// The number of loops whose body is being checked (only used by the type checker, which
+    /// rejects a `break` or a `continue` outside of a loop)
+    pub loop_depth: usize,
+    /// The function whose body is being checked (only used by the type checker, which checks the
+    /// value of a `return` against its return type)
+    pub function: Option<FunctionDecl>,
+    /// The user types declared so far in the whole program (only used by the type checker: a type
+    /// is identified by its name, which must be unique since its values may outlive its scope)
+    pub declared_types: HashMap<String, TypeDecl>,
+    /// The names of the type parameters declared so far in the whole program (only used by the
+    /// type checker, to name the type parameters it deduces without shadowing them)
+    pub declared_type_params: HashSet<String>,
 }
 
-/// A scope. Contains functions and variables
+/// A scope. Contains functions, variables, user-declared types and type parameters
 ///
 /// The `T` generic parameter corresponds to what will be stored as a binding info (type, value,
 /// etc.)
@@ -40,6 +63,13 @@ pub struct Scope<T> {
     pub variables: HashMap<String, BindingInfo<T>>,
     /// The functions in the current scope
     pub functions: HashMap<String, FunctionDecl>,
+    /// The user-declared types in the current scope
+    pub type_decls: HashMap<String, TypeDecl>,
+    /// The type parameters of the generic function called in the current scope, with the type
+    /// they stand for
+    ///
+    /// When type checking the body of a generic function, a type parameter stands for itself.
+    pub type_params: HashMap<String, Type>,
 }
 
 impl<T> Scope<T> {
@@ -48,10 +78,24 @@ impl<T> Scope<T> {
         Scope {
             variables: HashMap::new(),
             functions: HashMap::new(),
+            type_decls: HashMap::new(),
+            type_params: HashMap::new(),
         }
     }
 }
 
+/// Something that can be called by name: either a function, or a variable (which may hold a
+/// closure)
+///
+/// Returned by [`Environment::get_callable`](struct.Environment.html#method.get_callable).
+#[derive(Debug, PartialEq)]
+pub enum Callable<'a, T: 'a> {
+    /// The function variant
+    Function(&'a FunctionDecl),
+    /// The variable variant
+    Variable(&'a BindingInfo<T>),
+}
+
 /// A struct used to store some info about a builtin function
 pub struct BuiltinInfo {
     /// The name of the builtin
@@ -66,14 +110,14 @@ pub struct BuiltinInfo {
     /// A `Box` is needed because `FnMut` is a trait, not a type, and so does not have a compile
     /// time known size. Wrapping it inside a box is equivalent to store it as a pointer /
     /// reference.
-    pub call: Box<FnMut(&[Value]) -> Value + 'static>,
+    pub call: Box<FnMut(&[Value]) -> Result<Value, RuntimeError> + 'static>,
 }
 
 impl BuiltinInfo {
     /// Create a new builtin info struct
     pub fn new(name: String,
                signatures: HashMap<Vec<Generic>, Type>,
-               call: Box<FnMut(&[Value]) -> Value>)
+               call: Box<FnMut(&[Value]) -> Result<Value, RuntimeError>>)
                -> Self {
         BuiltinInfo {
             name,
@@ -110,6 +154,8 @@ pub enum BindingInfo<T> {
     Variable { declaration: VariableDecl, info: T },
     /// The argument variant
     Argument { declaration: ArgumentDecl, info: T },
+    /// The pattern variant (a variable bound by a pattern)
+    Pattern { declaration: PatternBinding, info: T },
 }
 
 impl<T> BindingInfo<T> {
@@ -120,6 +166,7 @@ impl<T> BindingInfo<T> {
         match *self {
             Variable { ref declaration, .. } => Declaration::Variable(declaration.clone()),
             Argument { ref declaration, .. } => Declaration::Argument(declaration.clone()),
+            Pattern { ref declaration, .. } => Declaration::Pattern(declaration.clone()),
         }
     }
 }
@@ -131,7 +178,8 @@ impl BindingInfo<TypeInfo> {
 
         match *self {
             Variable { ref info, .. } |
-            Argument { ref info, .. } => &info.0,
+            Argument { ref info, .. } |
+            Pattern { ref info, .. } => &info.0,
         }
     }
 }
@@ -143,7 +191,8 @@ impl BindingInfo<ValueInfo> {
 
         match *self {
             Variable { ref info, .. } |
-            Argument { ref info, .. } => &info.0,
+            Argument { ref info, .. } |
+            Pattern { ref info, .. } => &info.0,
         }
     }
 
@@ -153,13 +202,40 @@ impl BindingInfo<ValueInfo> {
 
         match *self {
             Variable { ref mut info, .. } |
-            Argument { ref mut info, .. } => {
+            Argument { ref mut info, .. } |
+            Pattern { ref mut info, .. } => {
                 info.0 = value;
             }
         }
     }
 }
 
+impl BindingInfo<UsageInfo> {
+    /// Tells if this binding was read
+    pub fn is_used(&self) -> bool {
+        use self::BindingInfo::*;
+
+        match *self {
+            Variable { ref info, .. } |
+            Argument { ref info, .. } |
+            Pattern { ref info, .. } => info.0,
+        }
+    }
+
+    /// Remember that this binding was read
+    pub fn set_used(&mut self) {
+        use self::BindingInfo::*;
+
+        match *self {
+            Variable { ref mut info, .. } |
+            Argument { ref mut info, .. } |
+            Pattern { ref mut info, .. } => {
+                info.0 = true;
+            }
+        }
+    }
+}
+
 /// Stores a Type
 ///
 /// Used in a [`BindingInfo`](enum.BindingInfo.html).
@@ -176,11 +252,17 @@ pub struct TypeInfo(pub Type);
 #[derive(Debug, Clone, PartialEq)]
 pub struct ValueInfo(pub Value);
 
+/// Stores whether a binding was read
+///
+/// Used in a [`BindingInfo`](enum.BindingInfo.html), by the lint pass.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageInfo(pub bool);
+
 impl<T> Environment<T> {
     /// Create a new environment
     ///
-    /// This will create an environment with no current scope, the default builtins, and the
-    /// default generic types.
+    /// This will create an environment with a single global scope (where the variables bound by
+    /// top-level patterns live), the default builtins, and the default generic types.
     pub fn new() -> Self {
         use self::Type::*;
 
@@ -205,10 +287,12 @@ impl<T> Environment<T> {
                     vec![Float.into(), Float.into()] => Float
                     );
 
-        // Not exactly correct: the two parameters are not guaranteed to be of the same type.
-        //
-        // This will result in that any comparison with objects of different types will always
-        // return `false`. I would prefer a type error.
+        let int_sig = quick_hashmap!(
+                    vec![Integer.into(), Integer.into()] => Integer
+                    );
+
+        // Not the whole story: the two parameters must also be of the same type, which is checked
+        // by the type checker (after promoting an Integer compared to a Float).
         let cmp_sig = quick_hashmap!(
                     vec![Generic::Named("Comparable".to_string()), Generic::Named("Comparable".to_string())] => Bool
                     );
@@ -249,13 +333,32 @@ impl<T> Environment<T> {
             ]
         });
 
+        let indexable_type = Generic::Sum(SumType {
+            possibilities: vec![
+                Generic::Abstract(AbstractType::Array(Box::new(Generic::Any))),
+                Str.into(),
+            ]
+        });
+
+        let mut scopes = LinkedList::new();
+        scopes.push_back(Scope::new());
+
         Self {
-            scopes: LinkedList::new(),
+            scopes,
             builtins: quick_hashmap!(
                 "+" => BuiltinInfo::new("+".to_string(), plus_sig.clone(), Box::new(builtins::plus)),
                 "-" => BuiltinInfo::new("-".to_string(), arit_sig.clone(), Box::new(builtins::minus)),
                 "*" => BuiltinInfo::new("*".to_string(), arit_sig.clone(), Box::new(builtins::mul)),
-                "/" => BuiltinInfo::new("/".to_string(), arit_sig,         Box::new(builtins::div)),
+                "/" => BuiltinInfo::new("/".to_string(), arit_sig.clone(), Box::new(builtins::div)),
+                "mod" => BuiltinInfo::new("mod".to_string(), arit_sig.clone(), Box::new(builtins::modulo)),
+                "**"  => BuiltinInfo::new("**".to_string(),  arit_sig,         Box::new(builtins::power)),
+                "div" => BuiltinInfo::new("div".to_string(), int_sig.clone(),  Box::new(builtins::floor_div)),
+
+                "&"   => BuiltinInfo::new("&".to_string(),   int_sig.clone(), Box::new(builtins::bit_and)),
+                "|"   => BuiltinInfo::new("|".to_string(),   int_sig.clone(), Box::new(builtins::bit_or)),
+                "^"   => BuiltinInfo::new("^".to_string(),   int_sig.clone(), Box::new(builtins::bit_xor)),
+                "<<"  => BuiltinInfo::new("<<".to_string(),  int_sig.clone(), Box::new(builtins::shift_left)),
+                ">>"  => BuiltinInfo::new(">>".to_string(),  int_sig,         Box::new(builtins::shift_right)),
 
                 "<"  => BuiltinInfo::new("<".to_string(),  cmp_sig.clone(), Box::new(builtins::lower)),
                 "<=" => BuiltinInfo::new("<=".to_string(), cmp_sig.clone(), Box::new(builtins::lower_eq)),
@@ -274,8 +377,15 @@ impl<T> Environment<T> {
             types: quick_hashmap!(
                 "Printable" => printable_type,
                 "Comparable" => comparable_type,
+                "Indexable" => indexable_type,
                 "Number" => number_type
                 ),
+            substitution: Substitution::new(),
+            errors: Vec::new(),
+            loop_depth: 0,
+            function: None,
+            declared_types: HashMap::new(),
+            declared_type_params: HashSet::new(),
         }
     }
 
@@ -324,6 +434,18 @@ impl<T> Environment<T> {
         }
     }
 
+    /// Declare a variable in the current scope, shadowing any variable of the same name already
+    /// declared in this scope
+    ///
+    /// Used for the variables bound by patterns.
+    pub fn bind_var(&mut self, name: String, info: BindingInfo<T>) {
+        self.scopes
+            .front_mut()
+            .expect("Trying to bind a variable out of scope")
+            .variables
+            .insert(name, info);
+    }
+
     /// Lookup a variable by name
     ///
     /// This will look for the variable in all the scopes, starting with the innermost one.
@@ -365,6 +487,17 @@ impl<T> Environment<T> {
         }
     }
 
+    /// Replace the declaration of a function already declared in the current scope
+    ///
+    /// Used by the type checker once the omitted types of the function are inferred.
+    pub fn update_func(&mut self, decl: FunctionDecl) {
+        self.scopes
+            .front_mut()
+            .expect("Trying to update a function out of scope")
+            .functions
+            .insert(decl.name.clone(), decl);
+    }
+
     /// Lookup a function declaration by name
     ///
     /// This will look for the variable in all the scopes, starting with the innermost one.
@@ -375,6 +508,132 @@ impl<T> Environment<T> {
             .map(|scope| &scope.functions[name])
     }
 
+    /// Lookup a function or a variable by name
+    ///
+    /// This will look for the name in all the scopes, starting with the innermost one. If a scope
+    /// defines both a variable and a function of the same name, the variable is returned.
+    pub fn get_callable(&self, name: &str) -> Option<Callable<T>> {
+        self.scopes
+            .iter()
+            .filter_map(|scope| if let Some(var) = scope.variables.get(name) {
+                            Some(Callable::Variable(var))
+                        } else {
+                            scope.functions.get(name).map(Callable::Function)
+                        })
+            .next()
+    }
+
+    /// Get the depth of the scope where a function or a variable is bound (0 being the innermost
+    /// scope)
+    ///
+    /// This will look for the name in all the scopes, starting with the innermost one.
+    pub fn get_depth(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .position(|scope| {
+                          scope.variables.contains_key(name) || scope.functions.contains_key(name)
+                      })
+    }
+
+    /// Declare a new user type in the current scope
+    ///
+    /// The variants of a sum type cannot share their name with the variants of another type of
+    /// the same scope.
+    pub fn declare_type(&mut self, decl: TypeDecl) -> Result<(), AlreadyDeclaredError> {
+        let scope = &mut self.scopes
+                             .front_mut()
+                             .expect("Trying to declare a type out of scope")
+                             .type_decls;
+
+        if let TypeDefinition::Sum(ref variants) = decl.definition {
+            for variant in variants {
+                if let Some(other) = scope
+                       .values()
+                       .find(|other| other.get_variant(&variant.name).is_some()) {
+                    return Err(AlreadyDeclaredError::new(variant.name.clone(),
+                                                         Declaration::Type(other.clone()),
+                                                         variant.span));
+                }
+            }
+        }
+
+        match scope.entry(decl.name.clone()) {
+            Entry::Occupied(entry) => {
+                Err(AlreadyDeclaredError::new(decl.name,
+                                              Declaration::Type(entry.get().clone()),
+                                              decl.span))
+            }
+
+            Entry::Vacant(vacant_entry) => {
+                vacant_entry.insert(decl);
+                Ok(())
+            }
+        }
+    }
+
+    /// Lookup a user type declaration by name
+    ///
+    /// This will look for the type in all the scopes, starting with the innermost one.
+    pub fn get_type_decl(&self, name: &str) -> Option<&TypeDecl> {
+        self.scopes
+            .iter()
+            .find(|scope| scope.type_decls.contains_key(name))
+            .map(|scope| &scope.type_decls[name])
+    }
+
+    /// Lookup a variant of a user sum type by name
+    ///
+    /// This will look for the variant in all the scopes, starting with the innermost one. Returns
+    /// the declaration of the sum type along with the declaration of the variant.
+    pub fn get_variant(&self, name: &str) -> Option<(&TypeDecl, &VariantDecl)> {
+        self.scopes
+            .iter()
+            .flat_map(|scope| scope.type_decls.values())
+            .filter_map(|decl| decl.get_variant(name).map(|variant| (decl, variant)))
+            .next()
+    }
+
+    /// Tells if a name used as a pattern names a variant rather than a new variable
+    pub fn is_variant_pattern(&self, name: &str) -> bool {
+        self.get_variant(name).is_some()
+    }
+
+    /// Declare a type parameter in the current scope, standing for the given type
+    pub fn declare_type_param(&mut self, name: String, type_: Type) {
+        self.scopes
+            .front_mut()
+            .expect("Trying to declare a type parameter out of scope")
+            .type_params
+            .insert(name, type_);
+    }
+
+    /// Lookup the type a type parameter stands for
+    ///
+    /// This will look for the type parameter in all the scopes, starting with the innermost one.
+    pub fn get_type_param(&self, name: &str) -> Option<&Type> {
+        self.scopes
+            .iter()
+            .find(|scope| scope.type_params.contains_key(name))
+            .map(|scope| &scope.type_params[name])
+    }
+
+    /// Tells if a type name designates a type parameter rather than a user-declared type
+    ///
+    /// The innermost declaration wins.
+    pub fn is_type_param(&self, name: &str) -> bool {
+        self.scopes
+            .iter()
+            .find(|scope| {
+                      scope.type_decls.contains_key(name) || scope.type_params.contains_key(name)
+                  })
+            .map_or(false, |scope| scope.type_params.contains_key(name))
+    }
+
+    /// Replace the type parameters inside a given type by the types they stand for
+    pub fn resolve_type(&self, type_: &Type) -> Type {
+        type_.substitute(&|name| self.get_type_param(name).cloned())
+    }
+
     /// Lookup a builtin info by name
     pub fn get_builtin(&self, name: &str) -> Option<&BuiltinInfo> {
         self.builtins.get(name)
@@ -387,12 +646,16 @@ impl<T> Environment<T> {
 
     /// Call a given builtin from its name
     ///
-    /// Panics if the builtin is not defined
-    pub fn call_builtin(&mut self, name: &str, args: &[Value]) -> Value {
-        (self.builtins
-             .get_mut(name)
-             .expect("No such function")
-             .call)(args)
+    /// Returns an [`UndefinedFunctionError`] if the builtin is not defined, or the error returned
+    /// by the builtin itself. In both cases, the location of the error must be set by the caller.
+    ///
+    /// [`UndefinedFunctionError`]: ../error/struct.UndefinedFunctionError.html
+    pub fn call_builtin(&mut self, name: &str, args: &[Value]) -> Result<Value, RuntimeError> {
+        let builtin = self.builtins
+            .get_mut(name)
+            .ok_or_else(|| UndefinedFunctionError::new(name.to_string(), Span(0, 0)))?;
+
+        (builtin.call)(args)
     }
 
     /// Get a given generic type by its name
@@ -404,10 +667,14 @@ impl<T> Environment<T> {
 impl Environment<ValueInfo> {
     /// Assign a variable given a name and a value
     ///
-    /// Panics if the variable is not defined
-    pub fn assign(&mut self, name: &str, value: Value) {
+    /// Returns an [`UnboundedVarError`] if the variable is not defined. The location of the error
+    /// must be set by the caller.
+    ///
+    /// [`UnboundedVarError`]: ../error/struct.UnboundedVarError.html
+    pub fn assign(&mut self, name: &str, value: Value) -> Result<(), RuntimeError> {
         self.get_var_mut(name)
-            .expect(format!("Could not find variable {} in current scope", name).as_str())
+            .ok_or_else(|| UnboundedVarError::new(name.to_string(), Span(0, 0)))?
             .set_value(value);
+        Ok(())
     }
 }
@@ -1,6 +1,7 @@
 //! Where all the errors are defined
 
 use ast::{Declaration, Span};
+use parser::Tok;
 use type_sys::{Generic, Type};
 
 use itertools::Itertools;
@@ -18,26 +19,64 @@ use std::io::{stderr, Write};
 /// traits.
 pub fn print_error<T>(filename: &str, input: &str, err: &T)
     where T: Error + Hint
+{
+    print_diagnostic(filename, input, err, "While processing", term::color::BRIGHT_RED);
+}
+
+/// Print a warning given a filename, the original input and the warning (see the `print_error`
+/// function).
+pub fn print_warning<T>(filename: &str, input: &str, warning: &T)
+    where T: Error + Hint
+{
+    print_diagnostic(filename,
+                     input,
+                     warning,
+                     "Warning while processing",
+                     term::color::BRIGHT_YELLOW);
+}
+
+// Print an error or a warning, with a colored header
+fn print_diagnostic<T>(filename: &str,
+                       input: &str,
+                       diagnostic: &T,
+                       header: &str,
+                       color: term::color::Color)
+    where T: Error + Hint
 {
     let mut t = term::stderr().unwrap();
 
-    t.fg(term::color::BRIGHT_RED).unwrap();
+    t.fg(color).unwrap();
     t.attr(term::Attr::Bold).unwrap();
 
     writeln!(&mut stderr(),
-             "While processing \"{}\"\n{}: {}\n",
+             "{} \"{}\"\n{}: {}\n",
+             header,
              filename,
-             err.description(),
-             err)
+             diagnostic.description(),
+             diagnostic)
             .unwrap();
 
     t.reset().unwrap();
 
-    for hint in &err.hints() {
+    for hint in &diagnostic.hints() {
         print_hints(input, hint);
     }
 }
 
+/// Print several errors found in the same input, in the given order (see the `print_error`
+/// function).
+pub fn print_errors<T>(filename: &str, input: &str, errs: &[T])
+    where T: Error + Hint
+{
+    for err in errs {
+        print_error(filename, input, err);
+    }
+
+    if errs.len() > 1 {
+        writeln!(&mut stderr(), "{} errors found in \"{}\"", errs.len(), filename).unwrap();
+    }
+}
+
 /// Print a list of hints (see the [`Hint`](trait.Hint.html) trait).
 pub fn print_hints(input: &str, hint: &Hinter) {
     let mut t = term::stderr().unwrap();
@@ -204,11 +243,7 @@ impl Hint for AlreadyDeclaredError {
 impl fmt::Display for AlreadyDeclaredError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{} `{}` was already declared",
-               match self.orig_declaration {
-                   Declaration::Variable(_) => "variable",
-                   Declaration::Function(_) => "function",
-                   Declaration::Argument(_) => "argument",
-               },
+               declaration_kind(&self.orig_declaration),
                self.name)
     }
 }
@@ -227,6 +262,9 @@ impl Error for AlreadyDeclaredError {
 pub struct NoSuchSignatureError {
     pub func_name: String,
     pub arg_types: Vec<Type>,
+    // The type of the called user function (declared or inferred), with the location of its
+    // signature
+    pub function_type: Option<(Type, Span)>,
     pub span: Span,
 }
 
@@ -235,6 +273,21 @@ impl NoSuchSignatureError {
         NoSuchSignatureError {
             func_name,
             arg_types,
+            function_type: None,
+            span,
+        }
+    }
+
+    pub fn from_function(func_name: String,
+                         function_type: Type,
+                         signature_span: Span,
+                         arg_types: Vec<Type>,
+                         span: Span)
+                         -> Self {
+        NoSuchSignatureError {
+            func_name,
+            arg_types,
+            function_type: Some((function_type, signature_span)),
             span,
         }
     }
@@ -242,11 +295,24 @@ impl NoSuchSignatureError {
 
 impl Hint for NoSuchSignatureError {
     fn hints(&self) -> Vec<Hinter> {
-        vec![Hinter {
-                 type_: HinterType::Error,
-                 span: self.span,
-                 message: "Used here".to_string(),
-             }]
+        let mut res = vec![Hinter {
+                               type_: HinterType::Error,
+                               span: self.span,
+                               message: "Used here".to_string(),
+                           }];
+
+        if let Some((ref function_type, signature_span)) = self.function_type {
+            res.push(Hinter {
+                         type_: HinterType::Info,
+                         span: signature_span,
+                         message: format!("Function `{}` is of type `{:?}` as deduced by this \
+                                           declaration",
+                                          self.func_name,
+                                          function_type),
+                     });
+        }
+
+        res
     }
 }
 
@@ -355,6 +421,22 @@ pub enum TypeCheckError {
     UndefinedFunction(UndefinedFunctionError),
     UntypedEmptyArray(UntypedEmptyArrayError),
     InconsistentArrayTyping(InconsistentArrayTypingError),
+    TupleIndexOutOfRange(TupleIndexOutOfRangeError),
+    NotCallable(NotCallableError),
+    UndefinedType(UndefinedTypeError),
+    NoSuchField(NoSuchFieldError),
+    MissingField(MissingFieldError),
+    NotARecord(NotARecordError),
+    NonExhaustiveMatch(NonExhaustiveMatchError),
+    UnreachableArm(UnreachableArmError),
+    VariantArity(VariantArityError),
+    UninferableTypeParam(UninferableTypeParamError),
+    TypeAnnotationNeeded(TypeAnnotationNeededError),
+    OutsideLoop(OutsideLoopError),
+    OutsideFunction(OutsideFunctionError),
+    UninhabitedType(UninhabitedTypeError),
+    IncompatibleCaseArmTypes(IncompatibleCaseArmTypesError),
+    InvalidPattern(InvalidPatternError),
 }
 
 impl Hint for TypeCheckError {
@@ -372,6 +454,22 @@ impl Hint for TypeCheckError {
             UndefinedFunction(ref err) => err.hints(),
             UntypedEmptyArray(ref err) => err.hints(),
             InconsistentArrayTyping(ref err) => err.hints(),
+            TupleIndexOutOfRange(ref err) => err.hints(),
+            NotCallable(ref err) => err.hints(),
+            UndefinedType(ref err) => err.hints(),
+            NoSuchField(ref err) => err.hints(),
+            MissingField(ref err) => err.hints(),
+            NotARecord(ref err) => err.hints(),
+            NonExhaustiveMatch(ref err) => err.hints(),
+            UnreachableArm(ref err) => err.hints(),
+            VariantArity(ref err) => err.hints(),
+            UninferableTypeParam(ref err) => err.hints(),
+            TypeAnnotationNeeded(ref err) => err.hints(),
+            OutsideLoop(ref err) => err.hints(),
+            OutsideFunction(ref err) => err.hints(),
+            UninhabitedType(ref err) => err.hints(),
+            IncompatibleCaseArmTypes(ref err) => err.hints(),
+            InvalidPattern(ref err) => err.hints(),
         }
     }
 }
@@ -391,6 +489,22 @@ impl fmt::Display for TypeCheckError {
             UndefinedFunction(ref err) => write!(f, "{}", err),
             UntypedEmptyArray(ref err) => write!(f, "{}", err),
             InconsistentArrayTyping(ref err) => write!(f, "{}", err),
+            TupleIndexOutOfRange(ref err) => write!(f, "{}", err),
+            NotCallable(ref err) => write!(f, "{}", err),
+            UndefinedType(ref err) => write!(f, "{}", err),
+            NoSuchField(ref err) => write!(f, "{}", err),
+            MissingField(ref err) => write!(f, "{}", err),
+            NotARecord(ref err) => write!(f, "{}", err),
+            NonExhaustiveMatch(ref err) => write!(f, "{}", err),
+            UnreachableArm(ref err) => write!(f, "{}", err),
+            VariantArity(ref err) => write!(f, "{}", err),
+            UninferableTypeParam(ref err) => write!(f, "{}", err),
+            TypeAnnotationNeeded(ref err) => write!(f, "{}", err),
+            OutsideLoop(ref err) => write!(f, "{}", err),
+            OutsideFunction(ref err) => write!(f, "{}", err),
+            UninhabitedType(ref err) => write!(f, "{}", err),
+            IncompatibleCaseArmTypes(ref err) => write!(f, "{}", err),
+            InvalidPattern(ref err) => write!(f, "{}", err),
         }
     }
 }
@@ -410,6 +524,22 @@ impl Error for TypeCheckError {
             UndefinedFunction(ref err) => err.description(),
             UntypedEmptyArray(ref err) => err.description(),
             InconsistentArrayTyping(ref err) => err.description(),
+            TupleIndexOutOfRange(ref err) => err.description(),
+            NotCallable(ref err) => err.description(),
+            UndefinedType(ref err) => err.description(),
+            NoSuchField(ref err) => err.description(),
+            MissingField(ref err) => err.description(),
+            NotARecord(ref err) => err.description(),
+            NonExhaustiveMatch(ref err) => err.description(),
+            UnreachableArm(ref err) => err.description(),
+            VariantArity(ref err) => err.description(),
+            UninferableTypeParam(ref err) => err.description(),
+            TypeAnnotationNeeded(ref err) => err.description(),
+            OutsideLoop(ref err) => err.description(),
+            OutsideFunction(ref err) => err.description(),
+            UninhabitedType(ref err) => err.description(),
+            IncompatibleCaseArmTypes(ref err) => err.description(),
+            InvalidPattern(ref err) => err.description(),
         }
     }
 
@@ -427,6 +557,22 @@ impl Error for TypeCheckError {
             UndefinedFunction(ref err) => Some(err),
             UntypedEmptyArray(ref err) => Some(err),
             InconsistentArrayTyping(ref err) => Some(err),
+            TupleIndexOutOfRange(ref err) => Some(err),
+            NotCallable(ref err) => Some(err),
+            UndefinedType(ref err) => Some(err),
+            NoSuchField(ref err) => Some(err),
+            MissingField(ref err) => Some(err),
+            NotARecord(ref err) => Some(err),
+            NonExhaustiveMatch(ref err) => Some(err),
+            UnreachableArm(ref err) => Some(err),
+            VariantArity(ref err) => Some(err),
+            UninferableTypeParam(ref err) => Some(err),
+            TypeAnnotationNeeded(ref err) => Some(err),
+            OutsideLoop(ref err) => Some(err),
+            OutsideFunction(ref err) => Some(err),
+            UninhabitedType(ref err) => Some(err),
+            IncompatibleCaseArmTypes(ref err) => Some(err),
+            InvalidPattern(ref err) => Some(err),
         }
     }
 }
@@ -491,6 +637,102 @@ impl From<InconsistentArrayTypingError> for TypeCheckError {
     }
 }
 
+impl From<TupleIndexOutOfRangeError> for TypeCheckError {
+    fn from(err: TupleIndexOutOfRangeError) -> Self {
+        TypeCheckError::TupleIndexOutOfRange(err)
+    }
+}
+
+impl From<NotCallableError> for TypeCheckError {
+    fn from(err: NotCallableError) -> Self {
+        TypeCheckError::NotCallable(err)
+    }
+}
+
+impl From<UndefinedTypeError> for TypeCheckError {
+    fn from(err: UndefinedTypeError) -> Self {
+        TypeCheckError::UndefinedType(err)
+    }
+}
+
+impl From<NoSuchFieldError> for TypeCheckError {
+    fn from(err: NoSuchFieldError) -> Self {
+        TypeCheckError::NoSuchField(err)
+    }
+}
+
+impl From<MissingFieldError> for TypeCheckError {
+    fn from(err: MissingFieldError) -> Self {
+        TypeCheckError::MissingField(err)
+    }
+}
+
+impl From<NotARecordError> for TypeCheckError {
+    fn from(err: NotARecordError) -> Self {
+        TypeCheckError::NotARecord(err)
+    }
+}
+
+impl From<NonExhaustiveMatchError> for TypeCheckError {
+    fn from(err: NonExhaustiveMatchError) -> Self {
+        TypeCheckError::NonExhaustiveMatch(err)
+    }
+}
+
+impl From<UnreachableArmError> for TypeCheckError {
+    fn from(err: UnreachableArmError) -> Self {
+        TypeCheckError::UnreachableArm(err)
+    }
+}
+
+impl From<VariantArityError> for TypeCheckError {
+    fn from(err: VariantArityError) -> Self {
+        TypeCheckError::VariantArity(err)
+    }
+}
+
+impl From<UninferableTypeParamError> for TypeCheckError {
+    fn from(err: UninferableTypeParamError) -> Self {
+        TypeCheckError::UninferableTypeParam(err)
+    }
+}
+
+impl From<TypeAnnotationNeededError> for TypeCheckError {
+    fn from(err: TypeAnnotationNeededError) -> Self {
+        TypeCheckError::TypeAnnotationNeeded(err)
+    }
+}
+
+impl From<OutsideLoopError> for TypeCheckError {
+    fn from(err: OutsideLoopError) -> Self {
+        TypeCheckError::OutsideLoop(err)
+    }
+}
+
+impl From<OutsideFunctionError> for TypeCheckError {
+    fn from(err: OutsideFunctionError) -> Self {
+        TypeCheckError::OutsideFunction(err)
+    }
+}
+
+impl From<UninhabitedTypeError> for TypeCheckError {
+    fn from(err: UninhabitedTypeError) -> Self {
+        TypeCheckError::UninhabitedType(err)
+    }
+}
+
+impl From<IncompatibleCaseArmTypesError> for TypeCheckError {
+    fn from(err: IncompatibleCaseArmTypesError) -> Self {
+        TypeCheckError::IncompatibleCaseArmTypes(err)
+    }
+}
+
+impl From<InvalidPatternError> for TypeCheckError {
+    fn from(err: InvalidPatternError) -> Self {
+        TypeCheckError::InvalidPattern(err)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct MismatchedTypesError {
     pub expected: Generic,
@@ -533,6 +775,8 @@ impl Hint for MismatchedTypesError {
                 Declaration::Variable(_) => "Variable",
                 Declaration::Argument(_) => "Argument",
                 Declaration::Function(_) => "Function",
+                Declaration::Type(_) => "Type",
+                Declaration::Pattern(_) => "Pattern variable",
             };
 
             res.push(Hinter {
@@ -674,6 +918,59 @@ impl Error for IncompatibleArmTypesError {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncompatibleCaseArmTypesError {
+    pub expected: Type,
+    pub got: Type,
+    pub first_arm_span: Span,
+    pub arm_span: Span,
+}
+
+impl IncompatibleCaseArmTypesError {
+    pub fn new(expected: Type, got: Type, first_arm_span: Span, arm_span: Span) -> Self {
+        IncompatibleCaseArmTypesError {
+            expected,
+            got,
+            first_arm_span,
+            arm_span,
+        }
+    }
+}
+
+impl Hint for IncompatibleCaseArmTypesError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Warning,
+                 span: self.arm_span,
+                 message: format!("Resolved as a `{:?}`", self.got),
+             },
+             Hinter {
+                 type_: HinterType::Warning,
+                 span: self.first_arm_span,
+                 message: format!("First arm resolved as `{:?}`", self.expected),
+             }]
+    }
+}
+
+impl fmt::Display for IncompatibleCaseArmTypesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "case arms have incomptible types: first arm `{:?}`, this arm `{:?}`",
+               self.expected,
+               self.got)
+    }
+}
+
+impl Error for IncompatibleCaseArmTypesError {
+    fn description(&self) -> &str {
+        "incompatible case arm types"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct UntypedEmptyArrayError {
     span: Span,
@@ -773,135 +1070,1689 @@ impl Error for InconsistentArrayTypingError {
     }
 }
 
-pub type OrigPopParseError<'a> = lalrpop_util::ParseError<usize, (usize, &'a str), UserParseError>;
-
 #[derive(Debug, Clone, PartialEq)]
-pub enum ParseError<'a> {
-    InvalidToken { location: usize },
-
-    UnrecognizedToken {
-        token: Option<(usize, (usize, &'a str), usize)>,
-        expected: Vec<String>,
-    },
+pub struct TupleIndexOutOfRangeError {
+    pub index: usize,
+    pub tuple_type: Type,
+    pub span: Span,
+    pub tuple_span: Span,
+}
 
-    ExtraToken { token: (usize, (usize, &'a str), usize), },
+impl TupleIndexOutOfRangeError {
+    pub fn new(index: usize, tuple_type: Type, span: Span, tuple_span: Span) -> Self {
+        TupleIndexOutOfRangeError {
+            index,
+            tuple_type,
+            span,
+            tuple_span,
+        }
+    }
 
-    User { error: UserParseError },
+    fn arity(&self) -> usize {
+        match self.tuple_type {
+            Type::Tuple(ref types) => types.len(),
+            _ => unreachable!("Projection out of a non-tuple type"),
+        }
+    }
 }
 
-impl<'a> Hint for ParseError<'a> {
+impl Hint for TupleIndexOutOfRangeError {
     fn hints(&self) -> Vec<Hinter> {
-        use self::ParseError::*;
-
         vec![Hinter {
                  type_: HinterType::Error,
-                 span: match *self {
-                     InvalidToken { location } => Span(location, location + 1),
-                     UnrecognizedToken { token: Some((start, _, end)), .. } |
-                     ExtraToken { token: (start, _, end) } => Span(start, end),
-                     UnrecognizedToken { token: None, .. } => return vec![],
-                     User { ref error } => return error.hints(),
-                 },
-                 message: "Encountered here".to_string(),
+                 span: self.span,
+                 message: format!("Expected an index lower than {}", self.arity()),
+             },
+             Hinter {
+                 type_: HinterType::Info,
+                 span: self.tuple_span,
+                 message: format!("This is a `{:?}`", self.tuple_type),
              }]
     }
 }
 
-impl<'a> From<OrigPopParseError<'a>> for ParseError<'a> {
-    fn from(err: OrigPopParseError<'a>) -> Self {
-        match err {
-            lalrpop_util::ParseError::InvalidToken { location } => {
-                ParseError::InvalidToken { location }
-            }
-            lalrpop_util::ParseError::UnrecognizedToken { token, expected } => {
-                ParseError::UnrecognizedToken { token, expected }
-            }
-            lalrpop_util::ParseError::ExtraToken { token } => ParseError::ExtraToken { token },
-            lalrpop_util::ParseError::User { error } => ParseError::User { error },
-        }
+impl fmt::Display for TupleIndexOutOfRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "no element {} in a tuple of {} elements",
+               self.index,
+               self.arity())
     }
 }
 
-impl<'a> fmt::Display for ParseError<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use self::ParseError::*;
+impl Error for TupleIndexOutOfRangeError {
+    fn description(&self) -> &str {
+        "tuple index out of range"
+    }
 
-        match *self {
-            InvalidToken { .. } => write!(f, "Invalid token"),
-            UnrecognizedToken {
-                ref token,
-                ref expected,
-            } => {
-                match *token {
-                    None => write!(f, "Unexpected EOF")?,
-                    Some((_, (_, token), _)) => write!(f, "Unexpected \"{}\"", token)?,
-                }
-                if !expected.is_empty() {
-                    write!(f,
-                           ", expected one of: {}",
-                           expected
-                               .iter()
-                               .map(|x| match x.as_str() {
-                                        r##"r#"\"(?:[^\"\\\\]|\\\\.)*\""#"## => "string literal",
-                                        r##"r#"[0-9]+"#"## => "integer literal",
-                                        r##"r#"[0-9]+\\.[0-9]*"#"## => "float literal",
-                                        r##"r#"[[:alpha:]][[:alnum:]_]*"#"## => "identifier",
-                                        _ => x,
-                                    })
-                               .join(", "))?;
-                }
-                Ok(())
-            }
-            ExtraToken { token: (_, (_, token), _) } => write!(f, "Extra token `{}`", token),
-            User { ref error } => write!(f, "{}", error),
-        }
+    fn cause(&self) -> Option<&Error> {
+        None
     }
 }
 
-impl<'a> Error for ParseError<'a> {
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotCallableError {
+    pub name: String,
+    pub type_: Type,
+    pub span: Span,
+}
+
+impl NotCallableError {
+    pub fn new(name: String, type_: Type, span: Span) -> Self {
+        NotCallableError { name, type_, span }
+    }
+}
+
+impl Hint for NotCallableError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: format!("This is a `{:?}`", self.type_),
+             }]
+    }
+}
+
+impl fmt::Display for NotCallableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\" is not a function", self.name)
+    }
+}
+
+impl Error for NotCallableError {
     fn description(&self) -> &str {
-        "parse error"
+        "not callable"
     }
 
     fn cause(&self) -> Option<&Error> {
-        use self::ParseError::*;
-
-        match *self {
-            User { ref error } => Some(error),
-            _ => None,
-        }
+        None
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum UserParseError {
-    IntegerOverflow { span: Span },
-    InvalidStringEscapeSequence { sequence_id: char, span: Span },
+pub struct UndefinedTypeError {
+    pub name: String,
+    pub span: Span,
 }
 
-impl Hint for UserParseError {
+impl UndefinedTypeError {
+    pub fn new(name: String, span: Span) -> Self {
+        UndefinedTypeError { name, span }
+    }
+}
+
+impl Hint for UndefinedTypeError {
     fn hints(&self) -> Vec<Hinter> {
-        use self::UserParseError::*;
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: "Used here".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for UndefinedTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\"", self.name)
+    }
+}
+
+impl Error for UndefinedTypeError {
+    fn description(&self) -> &str {
+        "undefined type"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoSuchFieldError {
+    pub name: String,
+    pub type_: Type,
+    pub span: Span,
+}
+
+impl NoSuchFieldError {
+    pub fn new(name: String, type_: Type, span: Span) -> Self {
+        NoSuchFieldError { name, type_, span }
+    }
+}
 
+impl Hint for NoSuchFieldError {
+    fn hints(&self) -> Vec<Hinter> {
         vec![Hinter {
                  type_: HinterType::Error,
-                 span: match *self {
-                     IntegerOverflow { span } | InvalidStringEscapeSequence { span, .. } => span,
-                 },
-                 message: "inputted here".to_string(),
+                 span: self.span,
+                 message: "Used here".to_string(),
              }]
     }
 }
 
-impl fmt::Display for UserParseError {
+impl fmt::Display for NoSuchFieldError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use self::UserParseError::*;
+        write!(f, "no field `{}` in type `{:?}`", self.name, self.type_)
+    }
+}
 
-        match *self {
-            IntegerOverflow { .. } => write!(f, "Integer overflow"),
-            InvalidStringEscapeSequence { sequence_id, .. } => {
-                write!(f, "Invalid escape sequence usage for `\\{}`", sequence_id)
+impl Error for NoSuchFieldError {
+    fn description(&self) -> &str {
+        "no such field"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingFieldError {
+    pub name: String,
+    pub type_: Type,
+    pub span: Span,
+}
+
+impl MissingFieldError {
+    pub fn new(name: String, type_: Type, span: Span) -> Self {
+        MissingFieldError { name, type_, span }
+    }
+}
+
+impl Hint for MissingFieldError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: format!("Missing a value for `{}`", self.name),
+             }]
+    }
+}
+
+impl fmt::Display for MissingFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "field `{}` of type `{:?}` is not initialized", self.name, self.type_)
+    }
+}
+
+impl Error for MissingFieldError {
+    fn description(&self) -> &str {
+        "missing field"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotARecordError {
+    pub name: String,
+    pub span: Span,
+}
+
+impl NotARecordError {
+    pub fn new(name: String, span: Span) -> Self {
+        NotARecordError { name, span }
+    }
+}
+
+impl Hint for NotARecordError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: "Constructed here".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for NotARecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\" is not a record type", self.name)
+    }
+}
+
+impl Error for NotARecordError {
+    fn description(&self) -> &str {
+        "not a record type"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NonExhaustiveMatchError {
+    pub missing: String,
+    pub span: Span,
+}
+
+impl NonExhaustiveMatchError {
+    pub fn new(missing: String, span: Span) -> Self {
+        NonExhaustiveMatchError { missing, span }
+    }
+}
+
+impl Hint for NonExhaustiveMatchError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: format!("`{}` not covered", self.missing),
+             }]
+    }
+}
+
+impl fmt::Display for NonExhaustiveMatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "pattern `{}` not covered", self.missing)
+    }
+}
+
+impl Error for NonExhaustiveMatchError {
+    fn description(&self) -> &str {
+        "non-exhaustive patterns"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnreachableArmError {
+    pub span: Span,
+}
+
+impl UnreachableArmError {
+    pub fn new(span: Span) -> Self {
+        UnreachableArmError { span }
+    }
+}
+
+impl Hint for UnreachableArmError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: "Covered by the previous arms".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for UnreachableArmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "this arm can never be taken")
+    }
+}
+
+impl Error for UnreachableArmError {
+    fn description(&self) -> &str {
+        "unreachable arm"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidPatternError {
+    pub span: Span,
+}
+
+impl InvalidPatternError {
+    pub fn new(span: Span) -> Self {
+        InvalidPatternError { span }
+    }
+}
+
+impl Hint for InvalidPatternError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: "Not a pattern".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for InvalidPatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "this expression cannot be used as a pattern")
+    }
+}
+
+impl Error for InvalidPatternError {
+    fn description(&self) -> &str {
+        "invalid pattern"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantArityError {
+    pub name: String,
+    pub expected: usize,
+    pub got: usize,
+    pub span: Span,
+}
+
+impl VariantArityError {
+    pub fn new(name: String, expected: usize, got: usize, span: Span) -> Self {
+        VariantArityError {
+            name,
+            expected,
+            got,
+            span,
+        }
+    }
+}
+
+impl Hint for VariantArityError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: format!("Used with {} value(s) here", self.got),
+             }]
+    }
+}
+
+impl fmt::Display for VariantArityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "variant `{}` carries {} value(s), got {}",
+               self.name,
+               self.expected,
+               self.got)
+    }
+}
+
+impl Error for VariantArityError {
+    fn description(&self) -> &str {
+        "wrong number of values for a variant"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UninferableTypeParamError {
+    pub name: String,
+    pub func_name: String,
+    pub span: Span,
+}
+
+impl UninferableTypeParamError {
+    pub fn new(name: String, func_name: String, span: Span) -> Self {
+        UninferableTypeParamError {
+            name,
+            func_name,
+            span,
+        }
+    }
+}
+
+impl Hint for UninferableTypeParamError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: "Not used by the arguments".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for UninferableTypeParamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "type parameter `{}` of `{}` cannot be deduced from the arguments",
+               self.name,
+               self.func_name)
+    }
+}
+
+impl Error for UninferableTypeParamError {
+    fn description(&self) -> &str {
+        "type parameter cannot be deduced"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeAnnotationNeededError {
+    pub type_: Type,
+    pub span: Span,
+}
+
+impl TypeAnnotationNeededError {
+    pub fn new(type_: Type, span: Span) -> Self {
+        TypeAnnotationNeededError { type_, span }
+    }
+}
+
+impl Hint for TypeAnnotationNeededError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: "Add type annotations to the functions used here".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for TypeAnnotationNeededError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the type `{:?}` cannot be inferred", self.type_)
+    }
+}
+
+impl Error for TypeAnnotationNeededError {
+    fn description(&self) -> &str {
+        "type annotations needed"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutsideLoopError {
+    pub keyword: String,
+    pub span: Span,
+}
+
+impl OutsideLoopError {
+    pub fn new(keyword: String, span: Span) -> Self {
+        OutsideLoopError { keyword, span }
+    }
+}
+
+impl Hint for OutsideLoopError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: "Not inside the body of a while or a for loop".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for OutsideLoopError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}` outside of a loop", self.keyword)
+    }
+}
+
+impl Error for OutsideLoopError {
+    fn description(&self) -> &str {
+        "outside of a loop"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutsideFunctionError {
+    pub span: Span,
+}
+
+impl OutsideFunctionError {
+    pub fn new(span: Span) -> Self {
+        OutsideFunctionError { span }
+    }
+}
+
+impl Hint for OutsideFunctionError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: "Not inside the body of a function".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for OutsideFunctionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`return` outside of a function")
+    }
+}
+
+impl Error for OutsideFunctionError {
+    fn description(&self) -> &str {
+        "outside of a function"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UninhabitedTypeError {
+    pub name: String,
+    pub span: Span,
+}
+
+impl UninhabitedTypeError {
+    pub fn new(name: String, span: Span) -> Self {
+        UninhabitedTypeError { name, span }
+    }
+}
+
+impl Hint for UninhabitedTypeError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: "Every value of this type would contain another value of this type"
+                     .to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for UninhabitedTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "type `{}` has no finite value", self.name)
+    }
+}
+
+impl Error for UninhabitedTypeError {
+    fn description(&self) -> &str {
+        "uninhabited type"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeError {
+    DivisionByZero(DivisionByZeroError),
+    Overflow(OverflowError),
+    Conversion(ConversionError),
+    UnboundedVar(UnboundedVarError),
+    UndefinedFunction(UndefinedFunctionError),
+    UnparsableStr(UnparsableStrError),
+    IndexOutOfBounds(IndexOutOfBoundsError),
+    NonPositiveStep(NonPositiveStepError),
+    NegativeOperand(NegativeOperandError),
+    InvalidCharacter(InvalidCharacterError),
+    InvalidArguments(InvalidArgumentsError),
+}
+
+impl RuntimeError {
+    /// Set the location of the error
+    ///
+    /// Errors coming from builtins or from value conversions do not know where they were
+    /// triggered: the evaluator uses this function to point them at the faulty expression.
+    pub fn set_span(&mut self, span: Span) {
+        use self::RuntimeError::*;
+
+        match *self {
+            DivisionByZero(ref mut err) => err.span = span,
+            Overflow(ref mut err) => err.span = span,
+            Conversion(ref mut err) => err.span = span,
+            UnboundedVar(ref mut err) => err.span = span,
+            UndefinedFunction(ref mut err) => err.span = span,
+            UnparsableStr(ref mut err) => err.span = span,
+            IndexOutOfBounds(ref mut err) => err.span = span,
+            NonPositiveStep(ref mut err) => err.span = span,
+            NegativeOperand(ref mut err) => err.span = span,
+            InvalidCharacter(ref mut err) => err.span = span,
+            InvalidArguments(ref mut err) => err.span = span,
+        }
+    }
+}
+
+impl Hint for RuntimeError {
+    fn hints(&self) -> Vec<Hinter> {
+        use self::RuntimeError::*;
+
+        match *self {
+            DivisionByZero(ref err) => err.hints(),
+            Overflow(ref err) => err.hints(),
+            Conversion(ref err) => err.hints(),
+            UnboundedVar(ref err) => err.hints(),
+            UndefinedFunction(ref err) => err.hints(),
+            UnparsableStr(ref err) => err.hints(),
+            IndexOutOfBounds(ref err) => err.hints(),
+            NonPositiveStep(ref err) => err.hints(),
+            NegativeOperand(ref err) => err.hints(),
+            InvalidCharacter(ref err) => err.hints(),
+            InvalidArguments(ref err) => err.hints(),
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::RuntimeError::*;
+
+        match *self {
+            DivisionByZero(ref err) => write!(f, "{}", err),
+            Overflow(ref err) => write!(f, "{}", err),
+            Conversion(ref err) => write!(f, "{}", err),
+            UnboundedVar(ref err) => write!(f, "{}", err),
+            UndefinedFunction(ref err) => write!(f, "{}", err),
+            UnparsableStr(ref err) => write!(f, "{}", err),
+            IndexOutOfBounds(ref err) => write!(f, "{}", err),
+            NonPositiveStep(ref err) => write!(f, "{}", err),
+            NegativeOperand(ref err) => write!(f, "{}", err),
+            InvalidCharacter(ref err) => write!(f, "{}", err),
+            InvalidArguments(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for RuntimeError {
+    fn description(&self) -> &str {
+        use self::RuntimeError::*;
+
+        match *self {
+            DivisionByZero(ref err) => err.description(),
+            Overflow(ref err) => err.description(),
+            Conversion(ref err) => err.description(),
+            UnboundedVar(ref err) => err.description(),
+            UndefinedFunction(ref err) => err.description(),
+            UnparsableStr(ref err) => err.description(),
+            IndexOutOfBounds(ref err) => err.description(),
+            NonPositiveStep(ref err) => err.description(),
+            NegativeOperand(ref err) => err.description(),
+            InvalidCharacter(ref err) => err.description(),
+            InvalidArguments(ref err) => err.description(),
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        use self::RuntimeError::*;
+
+        match *self {
+            DivisionByZero(ref err) => Some(err),
+            Overflow(ref err) => Some(err),
+            Conversion(ref err) => Some(err),
+            UnboundedVar(ref err) => Some(err),
+            UndefinedFunction(ref err) => Some(err),
+            UnparsableStr(ref err) => Some(err),
+            IndexOutOfBounds(ref err) => Some(err),
+            NonPositiveStep(ref err) => Some(err),
+            NegativeOperand(ref err) => Some(err),
+            InvalidCharacter(ref err) => Some(err),
+            InvalidArguments(ref err) => Some(err),
+        }
+    }
+}
+
+impl From<DivisionByZeroError> for RuntimeError {
+    fn from(err: DivisionByZeroError) -> Self {
+        RuntimeError::DivisionByZero(err)
+    }
+}
+
+impl From<OverflowError> for RuntimeError {
+    fn from(err: OverflowError) -> Self {
+        RuntimeError::Overflow(err)
+    }
+}
+
+impl From<ConversionError> for RuntimeError {
+    fn from(err: ConversionError) -> Self {
+        RuntimeError::Conversion(err)
+    }
+}
+
+impl From<UnboundedVarError> for RuntimeError {
+    fn from(err: UnboundedVarError) -> Self {
+        RuntimeError::UnboundedVar(err)
+    }
+}
+
+impl From<UndefinedFunctionError> for RuntimeError {
+    fn from(err: UndefinedFunctionError) -> Self {
+        RuntimeError::UndefinedFunction(err)
+    }
+}
+
+impl From<UnparsableStrError> for RuntimeError {
+    fn from(err: UnparsableStrError) -> Self {
+        RuntimeError::UnparsableStr(err)
+    }
+}
+
+impl From<IndexOutOfBoundsError> for RuntimeError {
+    fn from(err: IndexOutOfBoundsError) -> Self {
+        RuntimeError::IndexOutOfBounds(err)
+    }
+}
+
+impl From<NonPositiveStepError> for RuntimeError {
+    fn from(err: NonPositiveStepError) -> Self {
+        RuntimeError::NonPositiveStep(err)
+    }
+}
+
+impl From<NegativeOperandError> for RuntimeError {
+    fn from(err: NegativeOperandError) -> Self {
+        RuntimeError::NegativeOperand(err)
+    }
+}
+
+impl From<InvalidCharacterError> for RuntimeError {
+    fn from(err: InvalidCharacterError) -> Self {
+        RuntimeError::InvalidCharacter(err)
+    }
+}
+
+impl From<InvalidArgumentsError> for RuntimeError {
+    fn from(err: InvalidArgumentsError) -> Self {
+        RuntimeError::InvalidArguments(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DivisionByZeroError {
+    pub span: Span,
+}
+
+impl DivisionByZeroError {
+    pub fn new(span: Span) -> Self {
+        DivisionByZeroError { span }
+    }
+}
+
+impl Hint for DivisionByZeroError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: "Divided by zero here".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for DivisionByZeroError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "attempted to divide by zero")
+    }
+}
+
+impl Error for DivisionByZeroError {
+    fn description(&self) -> &str {
+        "division by zero"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverflowError {
+    pub operation: String,
+    pub span: Span,
+}
+
+impl OverflowError {
+    pub fn new(operation: String, span: Span) -> Self {
+        OverflowError { operation, span }
+    }
+}
+
+impl Hint for OverflowError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: "Overflowed here".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "attempted to compute `{}` with overflow", self.operation)
+    }
+}
+
+impl Error for OverflowError {
+    fn description(&self) -> &str {
+        "integer overflow"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NegativeOperandError {
+    pub operation: String,
+    pub span: Span,
+}
+
+impl NegativeOperandError {
+    pub fn new(operation: String, span: Span) -> Self {
+        NegativeOperandError { operation, span }
+    }
+}
+
+impl Hint for NegativeOperandError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: "Negative right hand side here".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for NegativeOperandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "attempted to compute `{}` with a negative right hand side",
+               self.operation)
+    }
+}
+
+impl Error for NegativeOperandError {
+    fn description(&self) -> &str {
+        "negative operand"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnparsableStrError {
+    pub value: String,
+    pub to: Type,
+    pub span: Span,
+}
+
+impl UnparsableStrError {
+    pub fn new(value: String, to: Type, span: Span) -> Self {
+        UnparsableStrError { value, to, span }
+    }
+}
+
+impl Hint for UnparsableStrError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: format!("Got {:?} here", self.value),
+             }]
+    }
+}
+
+impl fmt::Display for UnparsableStrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "could not convert {:?} to `{:?}`", self.value, self.to)
+    }
+}
+
+impl Error for UnparsableStrError {
+    fn description(&self) -> &str {
+        "unparsable string"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexOutOfBoundsError {
+    pub index: i64,
+    pub length: usize,
+    pub span: Span,
+}
+
+impl IndexOutOfBoundsError {
+    pub fn new(index: i64, length: usize, span: Span) -> Self {
+        IndexOutOfBoundsError {
+            index,
+            length,
+            span,
+        }
+    }
+}
+
+impl Hint for IndexOutOfBoundsError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: format!("Evaluated to {} here", self.index),
+             }]
+    }
+}
+
+impl fmt::Display for IndexOutOfBoundsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "index out of bounds: the length is {} but the index is {}",
+               self.length,
+               self.index)
+    }
+}
+
+impl Error for IndexOutOfBoundsError {
+    fn description(&self) -> &str {
+        "index out of bounds"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidCharacterError {
+    pub value: String,
+    pub span: Span,
+}
+
+impl InvalidCharacterError {
+    pub fn new(value: String, span: Span) -> Self {
+        InvalidCharacterError { value, span }
+    }
+}
+
+impl Hint for InvalidCharacterError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: format!("Evaluated to {:?} here", self.value),
+             }]
+    }
+}
+
+impl fmt::Display for InvalidCharacterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "a character of a string can only be replaced by a single character, not {} \
+                characters",
+               self.value.chars().count())
+    }
+}
+
+impl Error for InvalidCharacterError {
+    fn description(&self) -> &str {
+        "invalid character"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidArgumentsError {
+    pub func_name: String,
+    pub arg_types: Vec<Type>,
+    pub span: Span,
+}
+
+impl InvalidArgumentsError {
+    pub fn new(func_name: String, arg_types: Vec<Type>, span: Span) -> Self {
+        InvalidArgumentsError {
+            func_name,
+            arg_types,
+            span,
+        }
+    }
+}
+
+impl Hint for InvalidArgumentsError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: "Called here".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for InvalidArgumentsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{:?}", self.func_name, self.arg_types)
+    }
+}
+
+impl Error for InvalidArgumentsError {
+    fn description(&self) -> &str {
+        "invalid arguments"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NonPositiveStepError {
+    pub step: i64,
+    pub span: Span,
+}
+
+impl NonPositiveStepError {
+    pub fn new(step: i64, span: Span) -> Self {
+        NonPositiveStepError { step, span }
+    }
+}
+
+impl Hint for NonPositiveStepError {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: self.span,
+                 message: format!("Evaluated to {} here", self.step),
+             }]
+    }
+}
+
+impl fmt::Display for NonPositiveStepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the step of a for loop must be positive, but it is {}", self.step)
+    }
+}
+
+impl Error for NonPositiveStepError {
+    fn description(&self) -> &str {
+        "non-positive step"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+/// Any error that can happen after parsing: either while type checking, or while evaluating
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessingError {
+    TypeCheck(TypeCheckError),
+    Runtime(RuntimeError),
+}
+
+impl Hint for ProcessingError {
+    fn hints(&self) -> Vec<Hinter> {
+        use self::ProcessingError::*;
+
+        match *self {
+            TypeCheck(ref err) => err.hints(),
+            Runtime(ref err) => err.hints(),
+        }
+    }
+}
+
+impl fmt::Display for ProcessingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::ProcessingError::*;
+
+        match *self {
+            TypeCheck(ref err) => write!(f, "{}", err),
+            Runtime(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ProcessingError {
+    fn description(&self) -> &str {
+        use self::ProcessingError::*;
+
+        match *self {
+            TypeCheck(ref err) => err.description(),
+            Runtime(ref err) => err.description(),
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        use self::ProcessingError::*;
+
+        match *self {
+            TypeCheck(ref err) => Some(err),
+            Runtime(ref err) => Some(err),
+        }
+    }
+}
+
+impl From<TypeCheckError> for ProcessingError {
+    fn from(err: TypeCheckError) -> Self {
+        ProcessingError::TypeCheck(err)
+    }
+}
+
+impl From<RuntimeError> for ProcessingError {
+    fn from(err: RuntimeError) -> Self {
+        ProcessingError::Runtime(err)
+    }
+}
+
+// Returns what is declared by a declaration, for the messages
+fn declaration_kind(declaration: &Declaration) -> &'static str {
+    match *declaration {
+        Declaration::Variable(_) => "variable",
+        Declaration::Function(_) => "function",
+        Declaration::Argument(_) => "argument",
+        Declaration::Type(_) => "type",
+        Declaration::Pattern(_) => "pattern variable",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnusedBindingWarning {
+    pub declaration: Declaration,
+}
+
+impl UnusedBindingWarning {
+    pub fn new(declaration: Declaration) -> Self {
+        UnusedBindingWarning { declaration }
+    }
+}
+
+impl Hint for UnusedBindingWarning {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Warning,
+                 span: self.declaration.span(),
+                 message: "Declared here, but never read".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for UnusedBindingWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{} `{}` is never read",
+               declaration_kind(&self.declaration),
+               self.declaration.name())
+    }
+}
+
+impl Error for UnusedBindingWarning {
+    fn description(&self) -> &str {
+        "unused binding"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowedBindingWarning {
+    pub declaration: Declaration,
+    pub shadowed: Declaration,
+}
+
+impl ShadowedBindingWarning {
+    pub fn new(declaration: Declaration, shadowed: Declaration) -> Self {
+        ShadowedBindingWarning {
+            declaration,
+            shadowed,
+        }
+    }
+}
+
+impl Hint for ShadowedBindingWarning {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Warning,
+                 span: self.declaration.span(),
+                 message: "Shadows an outer binding".to_string(),
+             },
+             Hinter {
+                 type_: HinterType::Info,
+                 span: self.shadowed.span(),
+                 message: "Previously declared here".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for ShadowedBindingWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{} `{}` shadows an outer {}",
+               declaration_kind(&self.declaration),
+               self.declaration.name(),
+               declaration_kind(&self.shadowed))
+    }
+}
+
+impl Error for ShadowedBindingWarning {
+    fn description(&self) -> &str {
+        "shadowed binding"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstantConditionWarning {
+    pub value: bool,
+    pub span: Span,
+}
+
+impl ConstantConditionWarning {
+    pub fn new(value: bool, span: Span) -> Self {
+        ConstantConditionWarning { value, span }
+    }
+}
+
+impl Hint for ConstantConditionWarning {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Warning,
+                 span: self.span,
+                 message: format!("This condition is always {}", self.value),
+             }]
+    }
+}
+
+impl fmt::Display for ConstantConditionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "only the {} branch can be taken",
+               if self.value { "true" } else { "false" })
+    }
+}
+
+impl Error for ConstantConditionWarning {
+    fn description(&self) -> &str {
+        "constant condition"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeadLoopWarning {
+    pub span: Span,
+}
+
+impl DeadLoopWarning {
+    pub fn new(span: Span) -> Self {
+        DeadLoopWarning { span }
+    }
+}
+
+impl Hint for DeadLoopWarning {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Warning,
+                 span: self.span,
+                 message: "This condition is always false".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for DeadLoopWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the body of the loop is never run")
+    }
+}
+
+impl Error for DeadLoopWarning {
+    fn description(&self) -> &str {
+        "dead loop"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IgnoredValueWarning {
+    pub span: Span,
+}
+
+impl IgnoredValueWarning {
+    pub fn new(span: Span) -> Self {
+        IgnoredValueWarning { span }
+    }
+}
+
+impl Hint for IgnoredValueWarning {
+    fn hints(&self) -> Vec<Hinter> {
+        vec![Hinter {
+                 type_: HinterType::Warning,
+                 span: self.span,
+                 message: "Computed, then thrown away".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for IgnoredValueWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the value of this expression is not used")
+    }
+}
+
+impl Error for IgnoredValueWarning {
+    fn description(&self) -> &str {
+        "ignored value"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
+/// A suspicious, but valid, piece of code (see the [`Lint`] trait)
+///
+/// [`Lint`]: ../processing/trait.Lint.html
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    UnusedBinding(UnusedBindingWarning),
+    ShadowedBinding(ShadowedBindingWarning),
+    ConstantCondition(ConstantConditionWarning),
+    DeadLoop(DeadLoopWarning),
+    IgnoredValue(IgnoredValueWarning),
+}
+
+impl Hint for Warning {
+    fn hints(&self) -> Vec<Hinter> {
+        use self::Warning::*;
+
+        match *self {
+            UnusedBinding(ref warning) => warning.hints(),
+            ShadowedBinding(ref warning) => warning.hints(),
+            ConstantCondition(ref warning) => warning.hints(),
+            DeadLoop(ref warning) => warning.hints(),
+            IgnoredValue(ref warning) => warning.hints(),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Warning::*;
+
+        match *self {
+            UnusedBinding(ref warning) => write!(f, "{}", warning),
+            ShadowedBinding(ref warning) => write!(f, "{}", warning),
+            ConstantCondition(ref warning) => write!(f, "{}", warning),
+            DeadLoop(ref warning) => write!(f, "{}", warning),
+            IgnoredValue(ref warning) => write!(f, "{}", warning),
+        }
+    }
+}
+
+impl Error for Warning {
+    fn description(&self) -> &str {
+        use self::Warning::*;
+
+        match *self {
+            UnusedBinding(ref warning) => warning.description(),
+            ShadowedBinding(ref warning) => warning.description(),
+            ConstantCondition(ref warning) => warning.description(),
+            DeadLoop(ref warning) => warning.description(),
+            IgnoredValue(ref warning) => warning.description(),
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        use self::Warning::*;
+
+        match *self {
+            UnusedBinding(ref warning) => Some(warning),
+            ShadowedBinding(ref warning) => Some(warning),
+            ConstantCondition(ref warning) => Some(warning),
+            DeadLoop(ref warning) => Some(warning),
+            IgnoredValue(ref warning) => Some(warning),
+        }
+    }
+}
+
+impl From<UnusedBindingWarning> for Warning {
+    fn from(warning: UnusedBindingWarning) -> Self {
+        Warning::UnusedBinding(warning)
+    }
+}
+
+impl From<ShadowedBindingWarning> for Warning {
+    fn from(warning: ShadowedBindingWarning) -> Self {
+        Warning::ShadowedBinding(warning)
+    }
+}
+
+impl From<ConstantConditionWarning> for Warning {
+    fn from(warning: ConstantConditionWarning) -> Self {
+        Warning::ConstantCondition(warning)
+    }
+}
+
+impl From<DeadLoopWarning> for Warning {
+    fn from(warning: DeadLoopWarning) -> Self {
+        Warning::DeadLoop(warning)
+    }
+}
+
+impl From<IgnoredValueWarning> for Warning {
+    fn from(warning: IgnoredValueWarning) -> Self {
+        Warning::IgnoredValue(warning)
+    }
+}
+
+pub type OrigPopParseError<'a> = lalrpop_util::ParseError<usize, Tok<'a>, UserParseError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError<'a> {
+    InvalidToken { location: usize },
+
+    UnrecognizedToken {
+        token: (usize, Tok<'a>, usize),
+        expected: Vec<String>,
+    },
+
+    /// The input ended too early (`location` is the location of its last character, since its end
+    /// may be on a line of its own)
+    UnrecognizedEof {
+        location: usize,
+        expected: Vec<String>,
+    },
+
+    ExtraToken { token: (usize, Tok<'a>, usize), },
+
+    User { error: UserParseError },
+}
+
+impl<'a> Hint for ParseError<'a> {
+    fn hints(&self) -> Vec<Hinter> {
+        use self::ParseError::*;
+
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: match *self {
+                     InvalidToken { location } |
+                     UnrecognizedEof { location, .. } => Span(location, location + 1),
+                     UnrecognizedToken { token: (start, _, end), .. } |
+                     ExtraToken { token: (start, _, end) } => Span(start, end),
+                     User { ref error } => return error.hints(),
+                 },
+                 message: "Encountered here".to_string(),
+             }]
+    }
+}
+
+impl<'a> ParseError<'a> {
+    /// Converts an error of the parser, given its input (to locate its end)
+    pub fn new(err: OrigPopParseError<'a>, input: &str) -> Self {
+        match err {
+            lalrpop_util::ParseError::InvalidToken { location } => {
+                ParseError::InvalidToken { location }
+            }
+            lalrpop_util::ParseError::UnrecognizedToken {
+                token: Some(token),
+                expected,
+            } => ParseError::UnrecognizedToken { token, expected },
+            lalrpop_util::ParseError::UnrecognizedToken {
+                token: None,
+                expected,
+            } => {
+                let location = input
+                    .char_indices()
+                    .filter(|&(_, ch)| !ch.is_whitespace())
+                    .last()
+                    .map_or(0, |(location, _)| location);
+
+                ParseError::UnrecognizedEof { location, expected }
+            }
+            lalrpop_util::ParseError::ExtraToken { token } => ParseError::ExtraToken { token },
+            lalrpop_util::ParseError::User { error } => ParseError::User { error },
+        }
+    }
+}
+
+impl<'a> fmt::Display for ParseError<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::ParseError::*;
+
+        match *self {
+            InvalidToken { .. } => write!(f, "Invalid token"),
+            UnrecognizedToken {
+                token: (_, ref token, _),
+                ref expected,
+            } => {
+                write!(f, "Unexpected `{}`", token)?;
+                write_expected(f, expected)
+            }
+            UnrecognizedEof { ref expected, .. } => {
+                write!(f, "Unexpected EOF")?;
+                write_expected(f, expected)
+            }
+            ExtraToken { token: (_, ref token, _) } => write!(f, "Extra token `{}`", token),
+            User { ref error } => write!(f, "{}", error),
+        }
+    }
+}
+
+// The tokens the parser would have accepted instead
+fn write_expected(f: &mut fmt::Formatter, expected: &[String]) -> fmt::Result {
+    if expected.is_empty() {
+        return Ok(());
+    }
+
+    write!(f,
+           ", expected one of: {}",
+           expected
+               .iter()
+               .map(|x| match x.as_str() {
+                        "StringLiteral" | "InterpolationStart" => "string literal",
+                        "InterpolationMiddle" | "InterpolationEnd" => "\"}\"",
+                        "IntegerLiteral" => "integer literal",
+                        "FloatLiteral" => "float literal",
+                        "Ident" => "identifier",
+                        _ => x,
+                    })
+               .unique()
+               .join(", "))
+}
+
+impl<'a> Error for ParseError<'a> {
+    fn description(&self) -> &str {
+        "parse error"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        use self::ParseError::*;
+
+        match *self {
+            User { ref error } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UserParseError {
+    IntegerOverflow { span: Span },
+    InvalidStringEscapeSequence { sequence_id: char, span: Span },
+    InvalidCodePoint { value: u32, span: Span },
+    UnterminatedString { span: Span },
+    InvalidNumberLiteral { span: Span },
+    UnexpectedCharacter { character: char, span: Span },
+    InvalidAssignTarget { span: Span },
+    DuplicateField { name: String, span: Span },
+    DuplicateVariant { name: String, span: Span },
+    DuplicateRest { span: Span },
+    DuplicateTypeParam { name: String, span: Span },
+    UnterminatedComment { span: Span },
+}
+
+impl Hint for UserParseError {
+    fn hints(&self) -> Vec<Hinter> {
+        use self::UserParseError::*;
+
+        vec![Hinter {
+                 type_: HinterType::Error,
+                 span: match *self {
+                     IntegerOverflow { span } |
+                     InvalidStringEscapeSequence { span, .. } |
+                     InvalidCodePoint { span, .. } |
+                     UnterminatedString { span } |
+                     InvalidNumberLiteral { span } |
+                     UnexpectedCharacter { span, .. } |
+                     InvalidAssignTarget { span } |
+                     DuplicateField { span, .. } |
+                     DuplicateVariant { span, .. } |
+                     DuplicateRest { span } |
+                     DuplicateTypeParam { span, .. } |
+                     UnterminatedComment { span } => span,
+                 },
+                 message: "inputted here".to_string(),
+             }]
+    }
+}
+
+impl fmt::Display for UserParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::UserParseError::*;
+
+        match *self {
+            IntegerOverflow { .. } => write!(f, "Integer overflow"),
+            InvalidStringEscapeSequence { sequence_id, .. } => {
+                write!(f, "Invalid escape sequence usage for `\\{}`", sequence_id)
+            }
+            InvalidCodePoint { value, .. } => write!(f, "Invalid code point U+{:X}", value),
+            UnterminatedString { .. } => write!(f, "Unterminated string literal"),
+            InvalidNumberLiteral { .. } => write!(f, "Invalid number literal"),
+            UnexpectedCharacter { character, .. } => {
+                write!(f, "Unexpected character `{}`", character)
+            }
+            InvalidAssignTarget { .. } => write!(f, "Invalid left-hand side of assignment"),
+            DuplicateField { ref name, .. } => write!(f, "Field `{}` initialized twice", name),
+            DuplicateVariant { ref name, .. } => write!(f, "Variant `{}` declared twice", name),
+            DuplicateRest { .. } => write!(f, "Multiple rest patterns in an array pattern"),
+            DuplicateTypeParam { ref name, .. } => {
+                write!(f, "Type parameter `{}` declared twice", name)
             }
+            UnterminatedComment { .. } => write!(f, "Unterminated comment"),
         }
     }
 }
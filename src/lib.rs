@@ -12,9 +12,9 @@ pub mod processing;
 pub mod repl;
 pub mod type_sys;
 
-use processing::{Evaluate, Print, TypeCheck};
+use processing::{Evaluate, Lint, Print, TypeCheck};
 use env::{Environment, ValueInfo};
-use error::{print_error, ParseError, TypeCheckError};
+use error::{print_errors, print_warning, ParseError, ProcessingError};
 
 extern crate itertools;
 extern crate lalrpop_util;
@@ -34,17 +34,23 @@ pub fn evaluate_file(filename: &str) {
 
     match parse_expressions(content.as_str()) {
         Ok(exprs) => {
-            if let Err(err) = do_the_thing(exprs, &mut Environment::new()) {
-                print_error(filename, &content, &err);
+            if let Err(errs) = do_the_thing(exprs, &mut Environment::new(), filename, &content) {
+                print_errors(filename, &content, &errs);
             }
         }
-        Err(err) => {
-            print_error(filename, &content, &err);
+        Err(errs) => {
+            print_errors(filename, &content, &errs);
         }
     }
 }
 
-/// Evaluate the given AST (going through the type checker, pretty printing, printing the AST, ...)
+/// Evaluate the given AST (going through the type checker, the lint pass, pretty printing,
+/// printing the AST, ...)
+///
+/// The warnings are printed right away, with the given filename and input (from which the AST was
+/// parsed).
+/// Returns the errors if the type checker rejects the AST (it reports all of them), or the error
+/// if the evaluation fails (division by zero, overflow, ...).
 ///
 /// # Examples
 ///
@@ -55,33 +61,87 @@ pub fn evaluate_file(filename: &str) {
 /// use compilib::type_sys::Value::*;
 /// use compilib::env::Environment;
 ///
-/// let res = do_the_thing(Exprs { exprs: vec![Box::new(Value(Integer(42)))] },
-///                        &mut Environment::new());
+/// let res = do_the_thing(Exprs { exprs: vec![(Box::new(Value(Integer(42))), Span(0, 2))],
+///                                comments: vec![],
+///                                types: vec![] },
+///                        &mut Environment::new(),
+///                        "<doc>",
+///                        "42");
 /// assert!(res.is_ok());
 ///
-/// let res = do_the_thing(Exprs { exprs: vec![Box::new(BinaryOp {
+/// let res = do_the_thing(Exprs { exprs: vec![(Box::new(BinaryOp {
 ///                            lhs: Box::new(Value(Integer(42))),
-///                            rhs: Box::new(Value(Float(69f64))),
+///                            rhs: Box::new(Value(Str("69".to_string()))),
 ///                            op: BinaryOpCode::Add,
-///                            span: Span(0, 5),
-///                        })] },
-///                        &mut Environment::new());
+///                            span: Span(0, 9),
+///                        }), Span(0, 9))],
+///                                comments: vec![],
+///                                types: vec![] },
+///                        &mut Environment::new(),
+///                        "<doc>",
+///                        "42 + \"69\"");
+/// assert!(res.is_err());
+///
+/// let res = do_the_thing(Exprs { exprs: vec![(Box::new(BinaryOp {
+///                            lhs: Box::new(Value(Integer(42))),
+///                            rhs: Box::new(Value(Integer(0))),
+///                            op: BinaryOpCode::Div,
+///                            span: Span(0, 4),
+///                        }), Span(0, 4))],
+///                                comments: vec![],
+///                                types: vec![] },
+///                        &mut Environment::new(),
+///                        "<doc>",
+///                        "42/0");
 /// assert!(res.is_err());
 /// ```
-pub fn do_the_thing(mut exprs: ast::Exprs, mut bindings: &mut Environment<ValueInfo>) -> Result<(), TypeCheckError> {
+pub fn do_the_thing(mut exprs: ast::Exprs,
+                    mut bindings: &mut Environment<ValueInfo>,
+                    filename: &str,
+                    input: &str)
+                    -> Result<(), Vec<ProcessingError>> {
     println!("Result: {:?}", exprs);
+    let final_type = exprs.type_check(&mut Environment::new())
+        .map_err(|errs| errs.into_iter().map(ProcessingError::from).collect::<Vec<_>>())?;
+    for warning in exprs.lint(&mut Environment::new()) {
+        print_warning(filename, input, &warning);
+    }
+    // Printed once type checked, with the inferred types of the functions
     println!("===== Pretty printing =====\n{}===========================", &exprs.pretty_print(0));
-    println!("Final type (type checker): {:?}", &mut exprs.type_check(&mut Environment::new())?);
-    println!("Final value: {:?}", &exprs.evaluate(&mut bindings));
+    println!("Final type (type checker): {:?}", final_type);
+    let final_value = exprs.evaluate(&mut bindings).map_err(|err| vec![err.into()])?;
+    println!("Final value: {:?}", &final_value);
     Ok(())
 }
 
 /// Parse several expresssions (comma separated) given a corpus.
 ///
-/// Mainly used to convert a lalrpop error to a `ParseError`
-pub fn parse_expressions(partial_input: &str) -> Result<ast::Exprs, ParseError> {
-    match parser::parse_Expressions(partial_input) {
-        Ok(exprs) => Ok(exprs),
-        Err(err) => Err(From::from(err.clone())),
+/// Mainly used to convert the lalrpop errors to `ParseError`s (all the syntax errors are returned,
+/// see [`parser::parse_Expressions`])
+///
+/// [`parser::parse_Expressions`]: parser/fn.parse_Expressions.html
+pub fn parse_expressions(partial_input: &str) -> Result<ast::Exprs, Vec<ParseError>> {
+    parser::parse_Expressions(partial_input).map_err(|errs| {
+        errs.into_iter().map(|err| ParseError::new(err, partial_input)).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{do_the_thing, parse_expressions};
+
+    use env::Environment;
+
+    #[test]
+    fn untyped_array_patterns() {
+        // The types of the array patterns are inferred before the pretty printing
+        let input = "let
+                       var xs := [1, 2]
+                     in
+                       match [a, ..r] := xs,
+                       case xs of [] => 0 | [x, ..rest] => x end
+                     end";
+        let exprs = parse_expressions(input).unwrap();
+        assert!(do_the_thing(exprs, &mut Environment::new(), "<test>", input).is_ok());
     }
 }
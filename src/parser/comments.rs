@@ -0,0 +1,210 @@
+//! The comments of the user code
+//!
+//! The comments are skipped by the lexer, then attached to the AST so that the pretty printer can
+//! keep them.
+
+use ast::{Comment, Expr, Exprs, InterpolationPart, Span};
+
+/// Gives the comments to the innermost sequences of expressions containing them
+///
+/// A comment in a declaration of a `let` (and not in a sequence inside of it) is kept with the
+/// sequence of the `let`, and will be printed before the declaration containing or following it.
+/// A comment that cannot be located more precisely (between the operands of an operator, ...) is
+/// kept with the sequence of the enclosing expression, and will be printed before it.
+pub fn attach_comments(exprs: &mut Exprs, comments: Vec<Comment>) {
+    for comment in comments {
+        attach_comment(exprs, comment);
+    }
+}
+
+fn attach_comment(exprs: &mut Exprs, comment: Comment) {
+    let comment = match exprs.exprs
+              .iter_mut()
+              .find(|&&mut (_, span)| contains(span, &comment)) {
+        Some(&mut (ref mut expr, _)) => attach_in_expr(expr, comment),
+        None => Some(comment),
+    };
+
+    if let Some(comment) = comment {
+        exprs.comments.push(comment);
+    }
+}
+
+// Gives a comment to the innermost sequence of expressions containing it inside an expression, or
+// gives it back if there is none
+fn attach_in_expr(expr: &mut Expr, comment: Comment) -> Option<Comment> {
+    use ast::Expr::*;
+
+    // The bodies of the loops are not located, but they follow everything else
+    let after = |span: Span| Span(span.1, usize::max_value());
+
+    let children: Vec<(&mut Expr, Span)> = match *expr {
+        Grouping(ref mut exprs) => {
+            attach_comment(exprs, comment);
+            return None;
+        }
+        Let(_, ref mut bindings, ref mut function_decls, ref mut exprs) => {
+            let comment = match bindings
+                      .iter_mut()
+                      .map(|binding| (&mut binding.value, binding.value_span))
+                      .chain(function_decls
+                                 .iter_mut()
+                                 .map(|decl| (&mut *decl.body, decl.body_span)))
+                      .find(|&(_, span)| contains(span, &comment)) {
+                Some((value, _)) => attach_in_expr(value, comment),
+                None => Some(comment),
+            };
+
+            if let Some(comment) = comment {
+                attach_comment(exprs, comment);
+            }
+            return None;
+        }
+        Assign {
+            ref mut value,
+            value_span,
+            ..
+        } |
+        Return {
+            ref mut value,
+            value_span,
+            ..
+        } => vec![(&mut **value, value_span)],
+        ElementAssign {
+            ref mut target,
+            target_span,
+            ref mut value,
+            value_span,
+        } => vec![(&mut **target, target_span), (&mut **value, value_span)],
+        PatternMatch {
+            ref mut rhs,
+            rhs_span,
+            ..
+        } => vec![(&mut **rhs, rhs_span)],
+        Function { ref mut args, .. } => {
+            args.iter_mut().map(|&mut (ref mut arg, span)| (&mut **arg, span)).collect()
+        }
+        If {
+            ref mut cond,
+            cond_span,
+            ref mut true_branch,
+            true_branch_span,
+            ref mut false_branch,
+            ..
+        } => {
+            let mut children = vec![(&mut **cond, cond_span),
+                                    (&mut **true_branch, true_branch_span)];
+            if let Some((ref mut false_branch, span)) = *false_branch {
+                children.push((&mut **false_branch, span));
+            }
+            children
+        }
+        Case {
+            expr: ref mut matched,
+            expr_span,
+            ref mut arms,
+            ..
+        } => {
+            let mut children = vec![(&mut **matched, expr_span)];
+            for arm in arms {
+                if let Some((ref mut guard, span)) = arm.guard {
+                    children.push((&mut **guard, span));
+                }
+                children.push((&mut *arm.body, arm.body_span));
+            }
+            children
+        }
+        While {
+            ref mut cond,
+            cond_span,
+            expr: ref mut body,
+        } => vec![(&mut **cond, cond_span), (&mut **body, after(cond_span))],
+        For {
+            ref mut binding,
+            ref mut goal,
+            goal_span,
+            ref mut step,
+            expr: ref mut body,
+            ..
+        } => {
+            let mut children = vec![(&mut binding.value, binding.value_span),
+                                    (&mut **goal, goal_span)];
+            let body_span = match *step {
+                Some((ref mut step, span)) => {
+                    children.push((&mut **step, span));
+                    after(span)
+                }
+                None => after(goal_span),
+            };
+            children.push((&mut **body, body_span));
+            children
+        }
+        ForIn {
+            ref mut iterable,
+            iterable_span,
+            expr: ref mut body,
+            ..
+        } => vec![(&mut **iterable, iterable_span), (&mut **body, after(iterable_span))],
+        LogicalOp {
+            ref mut lhs,
+            lhs_span,
+            ref mut rhs,
+            rhs_span,
+            ..
+        } => vec![(&mut **lhs, lhs_span), (&mut **rhs, rhs_span)],
+        Cast {
+            expr: ref mut operand,
+            expr_span,
+            ..
+        } |
+        TryCast {
+            expr: ref mut operand,
+            expr_span,
+            ..
+        } |
+        Projection {
+            expr: ref mut operand,
+            expr_span,
+            ..
+        } |
+        Field {
+            expr: ref mut operand,
+            expr_span,
+            ..
+        } => vec![(&mut **operand, expr_span)],
+        Index {
+            expr: ref mut indexed,
+            expr_span,
+            ref mut index,
+            index_span,
+        } => vec![(&mut **indexed, expr_span), (&mut **index, index_span)],
+        Array { ref mut values, .. } => {
+            values.iter_mut().map(|&mut (ref mut value, span)| (&mut **value, span)).collect()
+        }
+        Record { ref mut fields, .. } => {
+            fields.iter_mut().map(|field| (&mut *field.value, field.value_span)).collect()
+        }
+        Lambda(ref mut decl) => vec![(&mut *decl.body, decl.body_span)],
+        Interpolation(ref mut parts) => {
+            parts
+                .iter_mut()
+                .filter_map(|part| match *part {
+                                InterpolationPart::Expr(ref mut expr, span) => {
+                                    Some((&mut **expr, span))
+                                }
+                                InterpolationPart::Text(_) => None,
+                            })
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    match children.into_iter().find(|&(_, span)| contains(span, &comment)) {
+        Some((child, _)) => attach_in_expr(child, comment),
+        None => Some(comment),
+    }
+}
+
+fn contains(span: Span, comment: &Comment) -> bool {
+    span.0 <= comment.span.0 && comment.span.1 <= span.1
+}
@@ -0,0 +1,430 @@
+//! The lexer
+//!
+//! Splits the user input into the tokens given to the parser, skipping the whitespace and the
+//! comments (which are kept aside, see [`attach_comments`]).
+//!
+//! [`attach_comments`]: fn.attach_comments.html
+
+use ast::{Comment, Span};
+use error::UserParseError;
+
+use std::char;
+use std::fmt;
+use std::mem;
+use std::str::FromStr;
+
+/// A token of the user code
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tok<'input> {
+    /// A keyword (`if`, `Integer`, ...)
+    Keyword(&'input str),
+    /// An operator or a punctuation sign
+    Symbol(&'input str),
+    Identifier(&'input str),
+    /// An integer literal, in decimal, hexadecimal (`0x`) or binary (`0b`) notation
+    Integer(i64),
+    /// A float literal, possibly in scientific notation
+    Float(f64),
+    /// A string literal, with its escape sequences already replaced
+    Str(String),
+    /// The text of an interpolated string up to its first embedded expression (`"...${`)
+    InterpolationStart(String),
+    /// The text of an interpolated string between two embedded expressions (`}...${`)
+    InterpolationMiddle(String),
+    /// The text of an interpolated string after its last embedded expression (`}..."`)
+    InterpolationEnd(String),
+}
+
+impl<'input> fmt::Display for Tok<'input> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Tok::*;
+
+        match *self {
+            Keyword(text) | Symbol(text) | Identifier(text) => write!(f, "{}", text),
+            Integer(value) => write!(f, "{}", value),
+            Float(value) => write!(f, "{:?}", value),
+            Str(ref value) => write!(f, "{:?}", value),
+            InterpolationStart(ref value) => write!(f, "\"{}${{", unquoted(value)),
+            InterpolationMiddle(ref value) => write!(f, "}}{}${{", unquoted(value)),
+            InterpolationEnd(ref value) => write!(f, "}}{}\"", unquoted(value)),
+        }
+    }
+}
+
+// The escaped text of a string, without its quotes
+fn unquoted(value: &str) -> String {
+    let quoted = format!("{:?}", value);
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+const KEYWORDS: &[&str] = &["and", "as", "break", "case", "continue", "div", "do", "downto",
+                            "else", "end", "false", "for", "function", "if", "in", "let",
+                            "match", "mod", "not", "of", "or", "return", "step", "then", "to",
+                            "true", "type", "var", "when", "while", "Array", "Bool", "Float",
+                            "Function", "Integer", "Str", "Tuple", "Void"];
+
+// The longest symbols first, so that they are not split
+const SYMBOLS: &[&str] = &["**", "..", ":=", "<<", "<=", "<>", "=>", ">=", ">>", "&", "(", ")",
+                           "*", "+", ",", "-", ".", "/", ":", "<", "=", ">", "[", "]", "^", "_",
+                           "{", "|", "}"];
+
+/// The iterator of the tokens of an input, with their locations
+pub struct Lexer<'input> {
+    input: &'input str,
+    pos: usize,
+    // The previous token is a `.`, so a number is a tuple index (`t.0.1` is not a float literal)
+    after_dot: bool,
+    // The number of unclosed `{` in each embedded expression of the interpolated strings (the `}`
+    // closing an embedded expression resumes its string)
+    interpolations: Vec<usize>,
+    comments: Vec<Comment>,
+}
+
+impl<'input> Lexer<'input> {
+    pub fn new(input: &'input str) -> Self {
+        Lexer {
+            input,
+            pos: 0,
+            after_dot: false,
+            interpolations: Vec::new(),
+            comments: Vec::new(),
+        }
+    }
+
+    /// Returns the comments skipped so far, in the order of the source code
+    pub fn into_comments(self) -> Vec<Comment> {
+        self.comments
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.input[self.pos..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek();
+        self.pos += ch.map_or(0, char::len_utf8);
+        ch
+    }
+
+    fn eat_while<F: Fn(char) -> bool>(&mut self, pred: F) {
+        while self.peek().map_or(false, &pred) {
+            self.bump();
+        }
+    }
+
+    // The location from `start` up to the next character, included
+    fn span_with_next(&self, start: usize) -> Span {
+        Span(start, self.pos + self.peek().map_or(0, char::len_utf8))
+    }
+
+    fn push_comment(&mut self, start: usize) {
+        self.comments.push(Comment {
+                               text: self.input[start..self.pos].to_string(),
+                               span: Span(start, self.pos),
+                           });
+    }
+
+    // Block comments nest, like in Tiger
+    fn block_comment(&mut self, start: usize) -> Result<(), UserParseError> {
+        let mut depth = 0;
+
+        loop {
+            match (self.peek(), self.peek_nth(1)) {
+                (None, _) => {
+                    return Err(UserParseError::UnterminatedComment { span: Span(start, self.pos) })
+                }
+                (Some('/'), Some('*')) => {
+                    depth += 1;
+                    self.pos += 2;
+                }
+                (Some('*'), Some('/')) => {
+                    depth -= 1;
+                    self.pos += 2;
+
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {
+                    self.bump();
+                }
+            }
+        }
+
+        self.push_comment(start);
+        Ok(())
+    }
+
+    fn word(&mut self, start: usize) -> Tok<'input> {
+        self.eat_while(|ch| ch.is_ascii_alphanumeric() || ch == '_');
+
+        if &self.input[start..self.pos] == "as" && self.peek() == Some('?') {
+            self.bump();
+        }
+
+        let text = &self.input[start..self.pos];
+
+        if text == "as?" || KEYWORDS.contains(&text) {
+            Tok::Keyword(text)
+        } else {
+            Tok::Identifier(text)
+        }
+    }
+
+    fn symbol(&mut self, start: usize) -> Result<Tok<'input>, UserParseError> {
+        let rest = &self.input[start..];
+
+        match SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol)) {
+            Some(symbol) => {
+                self.pos += symbol.len();
+                self.after_dot = *symbol == ".";
+
+                if let Some(depth) = self.interpolations.last_mut() {
+                    match *symbol {
+                        "{" => *depth += 1,
+                        "}" => *depth -= 1,
+                        _ => (),
+                    }
+                }
+
+                Ok(Tok::Symbol(&rest[..symbol.len()]))
+            }
+            None => {
+                Err(UserParseError::UnexpectedCharacter {
+                        character: self.peek().unwrap(),
+                        span: self.span_with_next(start),
+                    })
+            }
+        }
+    }
+
+    // A number directly followed by a letter (`0b102`, `12abc`, ...) is a single invalid literal
+    fn end_number(&mut self, start: usize) -> Result<(), UserParseError> {
+        if self.peek().map_or(false, |ch| ch.is_ascii_alphanumeric() || ch == '_') {
+            self.eat_while(|ch| ch.is_ascii_alphanumeric() || ch == '_');
+            return Err(UserParseError::InvalidNumberLiteral { span: Span(start, self.pos) });
+        }
+
+        Ok(())
+    }
+
+    fn tuple_index(&mut self, start: usize) -> Result<Tok<'input>, UserParseError> {
+        self.eat_while(|ch| ch.is_digit(10));
+
+        i64::from_str(&self.input[start..self.pos])
+            .map(Tok::Integer)
+            .map_err(|_| UserParseError::IntegerOverflow { span: Span(start, self.pos) })
+    }
+
+    fn number(&mut self, start: usize) -> Result<Tok<'input>, UserParseError> {
+        let radix = match (self.peek(), self.peek_nth(1)) {
+            (Some('0'), Some('x')) => 16,
+            (Some('0'), Some('b')) => 2,
+            _ => 10,
+        };
+
+        if radix != 10 {
+            self.pos += 2;
+            let digits_start = self.pos;
+            self.eat_while(|ch| ch.is_digit(radix) || ch == '_');
+            let digits = self.input[digits_start..self.pos].replace('_', "");
+            self.end_number(start)?;
+
+            if digits.is_empty() {
+                return Err(UserParseError::InvalidNumberLiteral { span: Span(start, self.pos) });
+            }
+
+            let span = Span(start, self.pos);
+
+            return i64::from_str_radix(&digits, radix)
+                       .map(Tok::Integer)
+                       .map_err(|_| UserParseError::IntegerOverflow { span });
+        }
+
+        let mut float = false;
+        self.eat_while(|ch| ch.is_digit(10) || ch == '_');
+
+        if self.peek() == Some('.') {
+            float = true;
+            self.bump();
+            self.eat_while(|ch| ch.is_digit(10) || ch == '_');
+        }
+
+        if self.peek() == Some('e') || self.peek() == Some('E') {
+            let sign = match self.peek_nth(1) {
+                Some('+') | Some('-') => 1,
+                _ => 0,
+            };
+
+            if self.peek_nth(1 + sign).map_or(false, |ch| ch.is_digit(10)) {
+                float = true;
+                self.pos += 1 + sign;
+                self.eat_while(|ch| ch.is_digit(10) || ch == '_');
+            }
+        }
+
+        self.end_number(start)?;
+        let text = self.input[start..self.pos].replace('_', "");
+
+        if float {
+            Ok(Tok::Float(f64::from_str(&text).unwrap()))
+        } else {
+            i64::from_str(&text)
+                .map(Tok::Integer)
+                .map_err(|_| UserParseError::IntegerOverflow { span: Span(start, self.pos) })
+        }
+    }
+
+    // Starts after the opening quote, or after the `}` of an embedded expression (`resumed`)
+    fn string(&mut self, start: usize, resumed: bool) -> Result<Tok<'input>, UserParseError> {
+        self.bump();
+        let mut value = String::new();
+
+        loop {
+            let escape_start = self.pos;
+
+            match self.bump() {
+                None => {
+                    return Err(UserParseError::UnterminatedString { span: Span(start, self.pos) })
+                }
+                Some('"') if resumed => return Ok(Tok::InterpolationEnd(value)),
+                Some('"') => return Ok(Tok::Str(value)),
+                Some('$') if self.peek() == Some('{') => {
+                    self.bump();
+                    self.interpolations.push(0);
+
+                    return Ok(if resumed {
+                                  Tok::InterpolationMiddle(value)
+                              } else {
+                                  Tok::InterpolationStart(value)
+                              });
+                }
+                Some('\\') => value.push(self.escape(start, escape_start)?),
+                Some(ch) => value.push(ch),
+            }
+        }
+    }
+
+    fn escape(&mut self, start: usize, escape_start: usize) -> Result<char, UserParseError> {
+        match self.bump() {
+            None => Err(UserParseError::UnterminatedString { span: Span(start, self.pos) }),
+            Some('x') => Ok(self.hex_digits(2, 'x', escape_start)? as u8 as char),
+            Some('u') if self.peek() == Some('{') => {
+                self.bump();
+                let digits_start = self.pos;
+                self.eat_while(|ch| ch.is_digit(16));
+                let digits_end = self.pos;
+
+                // At most 6 digits: the highest code point is 10FFFF
+                if self.peek() != Some('}') || digits_start == digits_end ||
+                   digits_end - digits_start > 6 {
+                    return Err(UserParseError::InvalidStringEscapeSequence {
+                                   sequence_id: 'u',
+                                   span: self.span_with_next(escape_start),
+                               });
+                }
+
+                self.bump();
+                let value = u32::from_str_radix(&self.input[digits_start..digits_end], 16)
+                    .unwrap();
+                self.code_point(value, escape_start)
+            }
+            Some('u') => {
+                let value = self.hex_digits(4, 'u', escape_start)?;
+                self.code_point(value, escape_start)
+            }
+            Some('n') => Ok('\n'),
+            Some('r') => Ok('\r'),
+            Some('t') => Ok('\t'),
+            Some(ch @ '\\') | Some(ch @ '"') | Some(ch @ '$') => Ok(ch),
+            Some(ch) => {
+                Err(UserParseError::InvalidStringEscapeSequence {
+                        sequence_id: ch,
+                        span: Span(escape_start, self.pos),
+                    })
+            }
+        }
+    }
+
+    fn hex_digits(&mut self,
+                  count: usize,
+                  sequence_id: char,
+                  escape_start: usize)
+                  -> Result<u32, UserParseError> {
+        let mut value = 0;
+
+        for _ in 0..count {
+            match self.peek().and_then(|ch| ch.to_digit(16)) {
+                Some(digit) => {
+                    self.bump();
+                    value = value * 16 + digit;
+                }
+                None => {
+                    return Err(UserParseError::InvalidStringEscapeSequence {
+                                   sequence_id,
+                                   span: self.span_with_next(escape_start),
+                               })
+                }
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn code_point(&self, value: u32, escape_start: usize) -> Result<char, UserParseError> {
+        char::from_u32(value).ok_or(UserParseError::InvalidCodePoint {
+                                        value,
+                                        span: Span(escape_start, self.pos),
+                                    })
+    }
+}
+
+impl<'input> Iterator for Lexer<'input> {
+    type Item = Result<(usize, Tok<'input>, usize), UserParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.eat_while(char::is_whitespace);
+            let start = self.pos;
+
+            let tok = match (self.peek(), self.peek_nth(1)) {
+                (None, _) => return None,
+                (Some('/'), Some('/')) => {
+                    self.eat_while(|ch| ch != '\n');
+                    self.push_comment(start);
+                    continue;
+                }
+                (Some('/'), Some('*')) => {
+                    match self.block_comment(start) {
+                        Ok(()) => continue,
+                        Err(err) => return Some(Err(err)),
+                    }
+                }
+                (Some(ch), _) => {
+                    let after_dot = mem::replace(&mut self.after_dot, false);
+
+                    if ch == '"' {
+                        self.string(start, false)
+                    } else if ch == '}' && self.interpolations.last() == Some(&0) {
+                        self.interpolations.pop();
+                        self.string(start, true)
+                    } else if ch.is_digit(10) && after_dot {
+                        self.tuple_index(start)
+                    } else if ch.is_digit(10) {
+                        self.number(start)
+                    } else if ch.is_ascii_alphabetic() {
+                        Ok(self.word(start))
+                    } else {
+                        self.symbol(start)
+                    }
+                }
+            };
+
+            return Some(tok.map(|tok| (start, tok, self.pos)));
+        }
+    }
+}
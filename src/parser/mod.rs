@@ -3,14 +3,66 @@
 //! This is a module generated by the [`lalrpop`] library that parses the user input and returns an
 //! [`ast`].
 //!
-//! The file from which this generated module came from is `src/parser/parser.lalrpop`.
+//! The file from which this generated module came from is `src/parser/parser.lalrpop`. Its tokens
+//! come from the hand-written [`Lexer`].
 //!
 //! [`lalrpop`]: https://github.com/nikomatsakis/lalrpop/
 //! [`ast`]: ../ast/index.html
+//! [`Lexer`]: struct.Lexer.html
 
+mod comments;
+mod lexer;
 #[allow(clippy)]
 mod parser;
 #[cfg(test)]
 mod tests;
 
-pub use self::parser::*;
+pub use self::comments::attach_comments;
+pub use self::lexer::{Lexer, Tok};
+
+use ast::{Expr, Exprs};
+use error::OrigPopParseError;
+
+// The functions keep the names of the generated ones
+
+/// Parses a single expression (the comments are dropped)
+///
+/// Returns the first syntax error, if any.
+#[allow(non_snake_case)]
+pub fn parse_Expression(input: &str) -> Result<Box<Expr>, OrigPopParseError> {
+    let mut errors = Vec::new();
+    let expr = parser::parse_Expression(&mut errors, Lexer::new(input))?;
+
+    match errors.into_iter().next() {
+        Some(recovery) => Err(recovery.error),
+        None => Ok(expr),
+    }
+}
+
+/// Parses several expressions (comma separated), with their comments
+///
+/// The parser recovers from the syntax errors in a sequence of expressions or of declarations, so
+/// that all of them are returned, in the order of the source code.
+#[allow(non_snake_case)]
+pub fn parse_Expressions(input: &str) -> Result<Exprs, Vec<OrigPopParseError>> {
+    let mut errors = Vec::new();
+    let mut lexer = Lexer::new(input);
+    let result = parser::parse_Expressions(&mut errors, &mut lexer);
+
+    let mut errors = errors.into_iter().map(|recovery| recovery.error).collect::<Vec<_>>();
+
+    match result {
+        Ok(mut exprs) => {
+            if !errors.is_empty() {
+                return Err(errors);
+            }
+
+            attach_comments(&mut exprs, lexer.into_comments());
+            Ok(exprs)
+        }
+        Err(err) => {
+            errors.push(err);
+            Err(errors)
+        }
+    }
+}
@@ -1,20 +1,27 @@
 use ast::*;
 use ast::Expr::*;
-use ast::Expr::{Array, Tuple};
-use parser::parse_Expression;
+use ast::Expr::{Array, Record, Tuple};
+use error::{Hint, UserParseError};
+use parser::{parse_Expression, parse_Expressions, Lexer, Tok};
 use type_sys::Type;
 use type_sys::Value::*;
 
 #[test]
 fn grouping() {
     let ast = Box::new(Grouping(Exprs {
-                                    exprs: vec![Box::new(Value(Integer(1))),
-                                                Box::new(Value(Integer(2)))],
+                                    exprs: vec![(Box::new(Value(Integer(1))), Span(1, 2)),
+                                                (Box::new(Value(Integer(2))), Span(4, 5))],
+                                    comments: vec![],
+                                    types: vec![],
                                 }));
 
     assert_eq!(parse_Expression("(1, 2)").unwrap(), ast);
 
-    let ast = Box::new(Grouping(Exprs { exprs: vec![Box::new(Value(Integer(1)))] }));
+    let ast = Box::new(Grouping(Exprs {
+                                    exprs: vec![(Box::new(Value(Integer(1))), Span(1, 2))],
+                                    comments: vec![],
+                                    types: vec![],
+                                }));
 
     // Single parenthesis is NOT a grouping
     assert_ne!(parse_Expression("(1)").unwrap(), ast);
@@ -25,9 +32,14 @@ fn grouping() {
     assert_eq!(parse_Expression("(((((((((1)))))))))").unwrap(), ast);
 
     let ast = Box::new(Grouping(Exprs {
-        exprs: vec![Box::new(Grouping(Exprs {
-            exprs: vec![Box::new(Value(Integer(1))), Box::new(Value(Integer(2)))]
-        })), Box::new(Value(Integer(3)))],
+        exprs: vec![(Box::new(Grouping(Exprs {
+            exprs: vec![(Box::new(Value(Integer(1))), Span(2, 3)),
+                        (Box::new(Value(Integer(2))), Span(5, 6))],
+            comments: vec![],
+            types: vec![],
+        })), Span(1, 7)), (Box::new(Value(Integer(3))), Span(9, 10))],
+        comments: vec![],
+        types: vec![],
     }));
 
     assert_eq!(parse_Expression("((1, 2), 3)").unwrap(), ast);
@@ -35,22 +47,24 @@ fn grouping() {
 
 #[test]
 fn let_block() {
-    let ast = Box::new(Let(vec![], vec![], Exprs { exprs: vec![] }));
+    let ast = Box::new(Let(vec![], vec![], vec![], Exprs { exprs: vec![], comments: vec![], types: vec![] }));
 
     assert_eq!(parse_Expression("let in end").unwrap(), ast);
 
-    let ast = Box::new(Let(vec![VariableDecl {
+    let ast = Box::new(Let(vec![],
+                           vec![VariableDecl {
                                     name: "x".to_string(),
                                     value: Value(Integer(2)),
                                     span: Span(4, 14),
                                     value_span: Span(13, 14),
                                 }],
                            vec![],
-                           Exprs { exprs: vec![] }));
+                           Exprs { exprs: vec![], comments: vec![], types: vec![] }));
 
     assert_eq!(parse_Expression("let var x := 2 in end").unwrap(), ast);
 
-    let ast = Box::new(Let(vec![VariableDecl {
+    let ast = Box::new(Let(vec![],
+                           vec![VariableDecl {
                                     name: "x".to_string(),
                                     value: Value(Integer(2)),
                                     span: Span(4, 14),
@@ -63,20 +77,22 @@ fn let_block() {
                                     value_span: Span(24, 26),
                                 }],
                            vec![],
-                           Exprs { exprs: vec![] }));
+                           Exprs { exprs: vec![], comments: vec![], types: vec![] }));
 
     assert_eq!(parse_Expression("let var x := 2 var y := 42 in end").unwrap(),
                ast);
 
     let ast = Box::new(Let(vec![],
+                           vec![],
                            vec![FunctionDecl {
                                     name: "f".to_string(),
+                                    type_params: vec![],
                                     args: vec![ArgumentDecl {
                                                    name: "x".to_string(),
-                                                   type_: Type::Integer,
+                                                   type_: Some(Type::Integer),
                                                    span: Span(15, 25),
                                                }],
-                                    return_type: Type::Integer,
+                                    return_type: Some(Type::Integer),
                                     signature_span: Span(4, 35),
                                     body: Box::new(Variable {
                                                        name: "x".to_string(),
@@ -84,12 +100,68 @@ fn let_block() {
                                                    }),
                                     body_span: Span(39, 40),
                                 }],
-                           Exprs { exprs: vec![] }));
+                           Exprs { exprs: vec![], comments: vec![], types: vec![] }));
 
     assert_eq!(parse_Expression("let function f(x: Integer): Integer := x in end").unwrap(),
                ast);
 
-    let ast = Box::new(Let(vec![VariableDecl {
+    // Type parameters are parsed as named types, resolved by the type checker
+    let ast = Box::new(Let(vec![],
+                           vec![],
+                           vec![FunctionDecl {
+                                    name: "id".to_string(),
+                                    type_params: vec![("T".to_string(), Span(16, 17))],
+                                    args: vec![ArgumentDecl {
+                                                   name: "x".to_string(),
+                                                   type_: Some(Type::Named("T".to_string())),
+                                                   span: Span(19, 23),
+                                               }],
+                                    return_type: Some(Type::Named("T".to_string())),
+                                    signature_span: Span(4, 27),
+                                    body: Box::new(Variable {
+                                                       name: "x".to_string(),
+                                                       span: Span(31, 32),
+                                                   }),
+                                    body_span: Span(31, 32),
+                                }],
+                           Exprs { exprs: vec![], comments: vec![], types: vec![] }));
+
+    assert_eq!(parse_Expression("let function id<T>(x: T): T := x in end").unwrap(),
+               ast);
+    assert!(parse_Expression("let function f<T, U>(x: T, y: U): T := x in end").is_ok());
+    assert!(parse_Expression("let function f<T, T>(x: T): T := x in end").is_err());
+    assert!(parse_Expression("function<T>(x: T): T := x").is_err());
+
+    let ast = Box::new(Let(vec![],
+                           vec![],
+                           vec![FunctionDecl {
+                                    name: "f".to_string(),
+                                    type_params: vec![],
+                                    args: vec![ArgumentDecl {
+                                                   name: "x".to_string(),
+                                                   type_: None,
+                                                   span: Span(15, 16),
+                                               },
+                                               ArgumentDecl {
+                                                   name: "y".to_string(),
+                                                   type_: Some(Type::Float),
+                                                   span: Span(18, 26),
+                                               }],
+                                    return_type: None,
+                                    signature_span: Span(4, 27),
+                                    body: Box::new(Variable {
+                                                       name: "x".to_string(),
+                                                       span: Span(31, 32),
+                                                   }),
+                                    body_span: Span(31, 32),
+                                }],
+                           Exprs { exprs: vec![], comments: vec![], types: vec![] }));
+
+    assert_eq!(parse_Expression("let function f(x, y: Float) := x in end").unwrap(),
+               ast);
+
+    let ast = Box::new(Let(vec![],
+                           vec![VariableDecl {
                                     name: "y".to_string(),
                                     value: Value(Integer(2)),
                                     span: Span(4, 14),
@@ -97,12 +169,13 @@ fn let_block() {
                                 }],
                            vec![FunctionDecl {
                                     name: "f".to_string(),
+                                    type_params: vec![],
                                     args: vec![ArgumentDecl {
                                                    name: "x".to_string(),
-                                                   type_: Type::Integer,
+                                                   type_: Some(Type::Integer),
                                                    span: Span(26, 36),
                                                }],
-                                    return_type: Type::Integer,
+                                    return_type: Some(Type::Integer),
                                     signature_span: Span(15, 46),
                                     body: Box::new(BinaryOp {
                                                        lhs: Box::new(Variable {
@@ -119,12 +192,15 @@ fn let_block() {
                                     body_span: Span(50, 55),
                                 }],
                            Exprs {
-                               exprs: vec![Box::new(Function {
+                               exprs: vec![(Box::new(Function {
                                                         name: "f".to_string(),
                                                         args: vec![(Box::new(Value(Integer(42))),
                                                                     Span(61, 63))],
                                                         span: Span(59, 64),
-                                                    })],
+                                                    }),
+                                                    Span(59, 64))],
+                               comments: vec![],
+                               types: vec![],
                            }));
 
     assert_eq!(parse_Expression("let var y := 2 function f(x: Integer): Integer := x + y in f(42) end")
@@ -159,6 +235,27 @@ fn assign() {
 
     assert!(parse_Expression("2 := 2").is_err());
     assert!(parse_Expression("x + 3 := 2").is_err());
+
+    let ast = Box::new(ElementAssign {
+                           target: Box::new(Index {
+                                                expr: Box::new(Variable {
+                                                                   name: "x".to_string(),
+                                                                   span: Span(0, 1),
+                                                               }),
+                                                expr_span: Span(0, 1),
+                                                index: Box::new(Value(Integer(1))),
+                                                index_span: Span(2, 3),
+                                            }),
+                           target_span: Span(0, 4),
+                           value: Box::new(Value(Integer(2))),
+                           value_span: Span(8, 9),
+                       });
+
+    assert_eq!(parse_Expression("x[1] := 2").unwrap(), ast);
+
+    assert!(parse_Expression("x[0][1] := 2").is_ok());
+    assert!(parse_Expression("[1, 2][0] := 2").is_err());
+    assert!(parse_Expression("f()[0] := 2").is_err());
 }
 
 #[test]
@@ -168,6 +265,7 @@ fn pattern_match() {
                            lhs_span: Span(6, 7),
                            rhs: Box::new(Value(Integer(1))),
                            rhs_span: Span(11, 12),
+                           bindings: vec![],
                        });
 
     assert_eq!(parse_Expression("match 1 := 1").unwrap(), ast);
@@ -187,6 +285,7 @@ fn pattern_match() {
                                              span: Span(14, 17),
                                          }),
                            rhs_span: Span(14, 17),
+                           bindings: vec![],
                        });
 
     assert_eq!(parse_Expression("match [{}] := 2+2").unwrap(), ast);
@@ -194,6 +293,47 @@ fn pattern_match() {
     assert!(parse_Expression("match 2+2 := 2").is_err());
     assert!(parse_Expression("match [2+2] := 2").is_err());
     assert!(parse_Expression("match [{let in end}] := 2").is_err());
+
+    let ast = Box::new(PatternMatch {
+                           lhs: Box::new(Array {
+                                             values: vec![(Box::new(Wildcard(Span(7, 8))), Span(7, 8)),
+                                                          (Box::new(Rest {
+                                                                        name: Some("r".to_string()),
+                                                                        span: Span(10, 13),
+                                                                    }),
+                                                           Span(10, 13)),
+                                                          (Box::new(Value(Integer(1))), Span(15, 16))],
+                                             declared_type: None,
+                                             declared_type_span: None,
+                                             span: Span(6, 17),
+                                         }),
+                           lhs_span: Span(6, 17),
+                           rhs: Box::new(Variable {
+                                             name: "a".to_string(),
+                                             span: Span(21, 22),
+                                         }),
+                           rhs_span: Span(21, 22),
+                           bindings: vec![],
+                       });
+
+    assert_eq!(parse_Expression("match [_, ..r, 1] := a").unwrap(), ast);
+    assert!(parse_Expression("match [..] := a").is_ok());
+    // Multiple rest patterns make the match ambiguous
+    assert!(parse_Expression("match [_, ..r, ..] := a").is_err());
+
+    let ast = Box::new(PatternMatch {
+                           lhs: Box::new(Wildcard(Span(6, 7))),
+                           lhs_span: Span(6, 7),
+                           rhs: Box::new(Value(Integer(1))),
+                           rhs_span: Span(11, 12),
+                           bindings: vec![],
+                       });
+
+    assert_eq!(parse_Expression("match _ := 1").unwrap(), ast);
+
+    assert!(parse_Expression("_").is_err());
+    assert!(parse_Expression("[..r]").is_err());
+    assert!(parse_Expression("match {..r} := a").is_err());
 }
 
 #[test]
@@ -236,6 +376,65 @@ fn function() {
     assert_eq!(parse_Expression("f(2+2,2+2)").unwrap(), ast);
 }
 
+#[test]
+fn lambda() {
+    let ast = Box::new(Lambda(FunctionDecl {
+        name: "lambda".to_string(),
+        type_params: vec![],
+        args: vec![ArgumentDecl {
+                       name: "x".to_string(),
+                       type_: Some(Type::Integer),
+                       span: Span(9, 19),
+                   }],
+        return_type: Some(Type::Integer),
+        signature_span: Span(0, 29),
+        body: Box::new(Variable {
+            name: "x".to_string(),
+            span: Span(33, 34),
+        }),
+        body_span: Span(33, 34),
+    }));
+
+    assert_eq!(parse_Expression("function(x: Integer): Integer := x").unwrap(), ast);
+
+    // Omitted types are inferred by the type checker
+    let ast = Box::new(Lambda(FunctionDecl {
+        name: "lambda".to_string(),
+        type_params: vec![],
+        args: vec![ArgumentDecl {
+                       name: "x".to_string(),
+                       type_: None,
+                       span: Span(9, 10),
+                   }],
+        return_type: None,
+        signature_span: Span(0, 11),
+        body: Box::new(Variable {
+            name: "x".to_string(),
+            span: Span(15, 16),
+        }),
+        body_span: Span(15, 16),
+    }));
+
+    assert_eq!(parse_Expression("function(x) := x").unwrap(), ast);
+
+    let ast = Box::new(Function {
+        name: "f".to_string(),
+        args: vec![(Box::new(Lambda(FunctionDecl {
+                        name: "lambda".to_string(),
+                        type_params: vec![],
+                        args: vec![],
+                        return_type: Some(Type::Bool),
+                        signature_span: Span(2, 18),
+                        body: Box::new(Value(Bool(true))),
+                        body_span: Span(22, 26),
+                    })),
+                    Span(2, 26))],
+        span: Span(0, 27),
+    });
+
+    assert_eq!(parse_Expression("f(function(): Bool := true)").unwrap(), ast);
+}
+
 #[test]
 fn if_block() {
     let ast = Box::new(If {
@@ -243,8 +442,8 @@ fn if_block() {
                            cond_span: Span(3, 7),
                            true_branch: Box::new(Value(Bool(true))),
                            true_branch_span: Span(13, 17),
-                           false_branch: Box::new(Value(Bool(false))),
-                           false_branch_span: Span(23, 28),
+                           true_branch_type: None,
+                           false_branch: Some((Box::new(Value(Bool(false))), Span(23, 28))),
                        });
 
     assert_eq!(parse_Expression("if true then true else false").unwrap(),
@@ -256,8 +455,8 @@ fn if_block() {
                                               cond_span: Span(6, 10),
                                               true_branch: Box::new(Value(Bool(true))),
                                               true_branch_span: Span(16, 20),
-                                              false_branch: Box::new(Value(Bool(false))),
-                                              false_branch_span: Span(26, 31),
+                                              true_branch_type: None,
+                                              false_branch: Some((Box::new(Value(Bool(false))), Span(26, 31))),
                                           }),
                            cond_span: Span(3, 31),
                            true_branch: Box::new(If {
@@ -265,22 +464,117 @@ fn if_block() {
                                                      cond_span: Span(40, 44),
                                                      true_branch: Box::new(Value(Bool(true))),
                                                      true_branch_span: Span(50, 54),
-                                                     false_branch: Box::new(Value(Bool(false))),
-                                                     false_branch_span: Span(60, 65),
+                                                     true_branch_type: None,
+                                                     false_branch: Some((Box::new(Value(Bool(false))), Span(60, 65))),
                                                  }),
                            true_branch_span: Span(37, 65),
-                           false_branch: Box::new(If {
+                           true_branch_type: None,
+                           false_branch: Some((Box::new(If {
                                                       cond: Box::new(Value(Bool(true))),
                                                       cond_span: Span(74, 78),
                                                       true_branch: Box::new(Value(Bool(true))),
                                                       true_branch_span: Span(84, 88),
-                                                      false_branch: Box::new(Value(Bool(false))),
-                                                      false_branch_span: Span(94, 99),
-                                                  }),
-                           false_branch_span: Span(71, 99),
+                                                      true_branch_type: None,
+                                                      false_branch: Some((Box::new(Value(Bool(false))), Span(94, 99))),
+                                                  }), Span(71, 99))),
                        });
 
     assert_eq!(parse_Expression("if if true then true else false then if true then true else false else if true then true else false").unwrap(), ast);
+
+    let ast = Box::new(If {
+                           cond: Box::new(Value(Bool(true))),
+                           cond_span: Span(3, 7),
+                           true_branch: Box::new(Value(Integer(1))),
+                           true_branch_span: Span(13, 14),
+                           true_branch_type: None,
+                           false_branch: None,
+                       });
+
+    assert_eq!(parse_Expression("if true then 1").unwrap(), ast);
+
+    // An `else` belongs to the innermost `if`
+    let ast = Box::new(If {
+                           cond: Box::new(Value(Bool(true))),
+                           cond_span: Span(3, 7),
+                           true_branch: Box::new(If {
+                                                     cond: Box::new(Value(Bool(false))),
+                                                     cond_span: Span(16, 21),
+                                                     true_branch: Box::new(Value(Integer(1))),
+                                                     true_branch_span: Span(27, 28),
+                                                     true_branch_type: None,
+                                                     false_branch: Some((Box::new(Value(Integer(2))),
+                                                                         Span(34, 35))),
+                                                 }),
+                           true_branch_span: Span(13, 35),
+                           true_branch_type: None,
+                           false_branch: None,
+                       });
+
+    assert_eq!(parse_Expression("if true then if false then 1 else 2").unwrap(), ast);
+
+    let ast = Box::new(If {
+                           cond: Box::new(Value(Bool(true))),
+                           cond_span: Span(3, 7),
+                           true_branch: Box::new(While {
+                                                     cond: Box::new(Value(Bool(false))),
+                                                     cond_span: Span(19, 24),
+                                                     expr: Box::new(Value(Integer(1))),
+                                                 }),
+                           true_branch_span: Span(13, 29),
+                           true_branch_type: None,
+                           false_branch: Some((Box::new(Value(Integer(2))), Span(35, 36))),
+                       });
+
+    assert_eq!(parse_Expression("if true then while false do 1 else 2").unwrap(), ast);
+}
+
+#[test]
+fn case_block() {
+    let ast = Box::new(Case {
+                           expr: Box::new(Variable {
+                                              name: "x".to_string(),
+                                              span: Span(5, 6),
+                                          }),
+                           expr_span: Span(5, 6),
+                           arms: vec![CaseArm {
+                                          pattern: Box::new(Value(Integer(1))),
+                                          pattern_span: Span(10, 11),
+                                          guard: None,
+                                          body: Box::new(Value(Bool(true))),
+                                          body_span: Span(15, 19),
+                                          bindings: vec![],
+                                      },
+                                      CaseArm {
+                                          pattern: Box::new(Variable {
+                                                                name: "y".to_string(),
+                                                                span: Span(22, 23),
+                                                            }),
+                                          pattern_span: Span(22, 23),
+                                          guard: Some((Box::new(Variable {
+                                                                    name: "y".to_string(),
+                                                                    span: Span(29, 30),
+                                                                }),
+                                                       Span(29, 30))),
+                                          body: Box::new(Value(Bool(false))),
+                                          body_span: Span(34, 39),
+                                          bindings: vec![],
+                                      }],
+                           span: Span(0, 43),
+                       });
+
+    assert_eq!(parse_Expression("case x of 1 => true | y when y => false end").unwrap(),
+               ast);
+
+    // Nested case expressions are delimited by `end`
+    assert!(parse_Expression("case x of 1 => case y of 2 => 3 | 4 => 5 end | 6 => 7 end").is_ok());
+    assert!(parse_Expression("case x of end").is_err());
+
+    // A bitwise `|` ending the body of an arm must be parenthesized, since `|` starts the next arm
+    assert!(parse_Expression("case x of 1 => (2 | 4) | _ => 3 end").is_ok());
+    assert!(parse_Expression("case x of 1 => 2 | 4 | _ => 3 end").is_err());
+    assert!(parse_Expression("case x of 1 => y := 2 | 4 => 3 | _ => 4 end").is_ok());
+    assert!(parse_Expression("case x of 1 => if y then 2 else 3 | _ => 4 end").is_ok());
+    assert!(parse_Expression("case x of 1 => [2 | 4] | _ => 3 end").is_ok());
 }
 
 #[test]
@@ -320,8 +614,10 @@ fn for_block() {
             value: Value(Integer(1)),
             value_span: Span(13, 14),
         }),
+        direction: ForDirection::Up,
         goal: Box::new(Value(Integer(42))),
         goal_span: Span(18, 20),
+        step: None,
         expr: Box::new(Variable {
             name: "x".to_string(),
             span: Span(24, 25),
@@ -342,6 +638,7 @@ fn for_block() {
             },
             value_span: Span(13, 16),
         }),
+        direction: ForDirection::Up,
         goal: Box::new(BinaryOp {
             lhs: Box::new(Value(Integer(6))),
             rhs: Box::new(Value(Integer(7))),
@@ -349,10 +646,98 @@ fn for_block() {
             span: Span(20, 23),
         }),
         goal_span: Span(20, 23),
+        step: None,
         expr: Box::new(Value(Integer(1))),
     });
 
     assert_eq!(parse_Expression("for var x := 3-2 to 6*7 do 1").unwrap(), ast);
+
+    let ast = Box::new(For {
+        binding: Box::new(VariableDecl {
+            name: "x".to_string(),
+            span: Span(4, 15),
+            value: Value(Integer(10)),
+            value_span: Span(13, 15),
+        }),
+        direction: ForDirection::Down,
+        goal: Box::new(Value(Integer(0))),
+        goal_span: Span(23, 24),
+        step: Some((Box::new(Value(Integer(2))), Span(30, 31))),
+        expr: Box::new(Value(Integer(1))),
+    });
+
+    assert_eq!(parse_Expression("for var x := 10 downto 0 step 2 do 1").unwrap(), ast);
+
+    let ast = Box::new(ForIn {
+        name: "x".to_string(),
+        name_span: Span(4, 5),
+        iterable: Box::new(Variable {
+            name: "xs".to_string(),
+            span: Span(9, 11),
+        }),
+        iterable_span: Span(9, 11),
+        expr: Box::new(Variable {
+            name: "x".to_string(),
+            span: Span(15, 16),
+        }),
+    });
+
+    assert_eq!(parse_Expression("for x in xs do x").unwrap(), ast);
+
+    // Keywords
+    assert!(parse_Expression("let var step := 1 in end").is_err());
+    assert!(parse_Expression("let var downto := 1 in end").is_err());
+}
+
+#[test]
+fn loop_jumps() {
+    let ast = Box::new(While {
+        cond: Box::new(Value(Bool(true))),
+        cond_span: Span(6, 10),
+        expr: Box::new(Break(Span(14, 19))),
+    });
+
+    assert_eq!(parse_Expression("while true do break").unwrap(), ast);
+
+    let ast = Box::new(If {
+        cond: Box::new(Variable {
+            name: "x".to_string(),
+            span: Span(3, 4),
+        }),
+        cond_span: Span(3, 4),
+        true_branch: Box::new(Continue(Span(10, 18))),
+        true_branch_span: Span(10, 18),
+        true_branch_type: None,
+        false_branch: Some((Box::new(Value(Integer(1))), Span(24, 25))),
+    });
+
+    assert_eq!(parse_Expression("if x then continue else 1").unwrap(), ast);
+
+    // Keywords
+    assert!(parse_Expression("let var break := 1 in end").is_err());
+}
+
+#[test]
+fn return_expr() {
+    let ast = Box::new(Return {
+        value: Box::new(BinaryOp {
+            lhs: Box::new(Variable {
+                name: "x".to_string(),
+                span: Span(7, 8),
+            }),
+            rhs: Box::new(Value(Integer(1))),
+            op: BinaryOpCode::Add,
+            span: Span(7, 12),
+        }),
+        value_span: Span(7, 12),
+        span: Span(0, 12),
+    });
+
+    assert_eq!(parse_Expression("return x + 1").unwrap(), ast);
+
+    // Keywords
+    assert!(parse_Expression("let var return := 1 in end").is_err());
+    assert!(parse_Expression("return").is_err());
 }
 
 #[test]
@@ -543,6 +928,114 @@ fn unary_operator() {
     assert_eq!(parse_Expression("2+-2+2").unwrap(), ast);
 }
 
+#[test]
+fn integer_operator() {
+    // Right associative
+    let ast = Box::new(BinaryOp {
+        lhs: Box::new(Value(Integer(2))),
+        rhs: Box::new(BinaryOp {
+            lhs: Box::new(Value(Integer(3))),
+            rhs: Box::new(Value(Integer(2))),
+            op: BinaryOpCode::Pow,
+            span: Span(5, 11),
+        }),
+        op: BinaryOpCode::Pow,
+        span: Span(0, 11),
+    });
+
+    assert_eq!(parse_Expression("2 ** 3 ** 2").unwrap(), ast);
+
+    let ast = Box::new(BinaryOp {
+        lhs: Box::new(Value(Integer(1))),
+        rhs: Box::new(BinaryOp {
+            lhs: Box::new(Value(Integer(2))),
+            rhs: Box::new(BinaryOp {
+                lhs: Box::new(Value(Integer(3))),
+                rhs: Box::new(Value(Integer(4))),
+                op: BinaryOpCode::Mod,
+                span: Span(9, 16),
+            }),
+            op: BinaryOpCode::Shl,
+            span: Span(4, 16),
+        }),
+        op: BinaryOpCode::BitOr,
+        span: Span(0, 16),
+    });
+
+    assert_eq!(parse_Expression("1 | 2 << 3 mod 4").unwrap(), ast);
+
+    let ast = Box::new(BinaryOp {
+        lhs: Box::new(BinaryOp {
+            lhs: Box::new(Value(Integer(1))),
+            rhs: Box::new(Value(Integer(2))),
+            op: BinaryOpCode::BitAnd,
+            span: Span(0, 5),
+        }),
+        rhs: Box::new(Value(Integer(3))),
+        op: BinaryOpCode::Lt,
+        span: Span(0, 9),
+    });
+
+    assert_eq!(parse_Expression("1 & 2 < 3").unwrap(), ast);
+
+    // Keywords
+    assert!(parse_Expression("let var mod := 1 in end").is_err());
+    assert!(parse_Expression("let var div := 1 in end").is_err());
+}
+
+#[test]
+fn logical_operator() {
+    let ast = Box::new(LogicalOp {
+        lhs: Box::new(UnaryOp {
+            expr: Box::new(BinaryOp {
+                lhs: Box::new(Variable {
+                    name: "a".to_string(),
+                    span: Span(4, 5),
+                }),
+                rhs: Box::new(Value(Integer(1))),
+                op: BinaryOpCode::Eq,
+                span: Span(4, 9),
+            }),
+            op: UnaryOpCode::Not,
+            span: Span(0, 9),
+        }),
+        lhs_span: Span(0, 9),
+        rhs: Box::new(LogicalOp {
+            lhs: Box::new(Variable {
+                name: "b".to_string(),
+                span: Span(13, 14),
+            }),
+            lhs_span: Span(13, 14),
+            rhs: Box::new(Variable {
+                name: "c".to_string(),
+                span: Span(19, 20),
+            }),
+            rhs_span: Span(19, 20),
+            op: LogicalOpCode::And,
+        }),
+        rhs_span: Span(13, 20),
+        op: LogicalOpCode::Or,
+    });
+
+    assert_eq!(parse_Expression("not a = 1 or b and c").unwrap(), ast);
+
+    let ast = Box::new(UnaryOp {
+        expr: Box::new(UnaryOp {
+            expr: Box::new(Value(Bool(true))),
+            op: UnaryOpCode::Not,
+            span: Span(4, 12),
+        }),
+        op: UnaryOpCode::Not,
+        span: Span(0, 12),
+    });
+
+    assert_eq!(parse_Expression("not not true").unwrap(), ast);
+
+    // Keywords
+    assert!(parse_Expression("let var and := 1 in end").is_err());
+    assert!(parse_Expression("1 + not 2").is_err());
+}
+
 #[test]
 fn cast() {
     let ast = Box::new(Cast {
@@ -594,6 +1087,38 @@ fn cast() {
     });
 
     assert_eq!(parse_Expression("1 as Tuple(Array(Integer), Tuple(Integer, Bool))").unwrap(), ast);
+
+    let ast = Box::new(TryCast {
+        expr: Box::new(Value(Str("42".to_string()))),
+        expr_span: Span(0, 4),
+        dest: Type::Integer,
+    });
+
+    assert_eq!(parse_Expression(r#""42" as? Integer"#).unwrap(), ast);
+
+    let ast = Box::new(Cast {
+        expr: Box::new(TryCast {
+            expr: Box::new(Value(Integer(1))),
+            expr_span: Span(0, 1),
+            dest: Type::Str,
+        }),
+        expr_span: Span(0, 9),
+        dest: Type::Tuple(vec![Type::Bool, Type::Str]),
+    });
+
+    assert_eq!(parse_Expression("1 as? Str as Tuple(Bool, Str)").unwrap(), ast);
+
+    let ast = Box::new(Cast {
+        expr: Box::new(Variable {
+            name: "f".to_string(),
+            span: Span(0, 1),
+        }),
+        expr_span: Span(0, 1),
+        dest: Type::Function(vec![Type::Integer, Type::Function(vec![], Box::new(Type::Bool))],
+                             Box::new(Type::Str)),
+    });
+
+    assert_eq!(parse_Expression("f as Function(Integer, Function(): Bool): Str").unwrap(), ast);
 }
 
 #[test]
@@ -659,6 +1184,226 @@ fn array() {
     assert_eq!(parse_Expression("[[]]").unwrap(), ast);
 }
 
+#[test]
+fn index() {
+    let ast = Box::new(Index {
+        expr: Box::new(Variable {
+            name: "x".to_string(),
+            span: Span(0, 1),
+        }),
+        expr_span: Span(0, 1),
+        index: Box::new(BinaryOp {
+            lhs: Box::new(Value(Integer(1))),
+            rhs: Box::new(Value(Integer(1))),
+            op: BinaryOpCode::Add,
+            span: Span(2, 5),
+        }),
+        index_span: Span(2, 5),
+    });
+
+    assert_eq!(parse_Expression("x[1+1]").unwrap(), ast);
+
+    let ast = Box::new(Index {
+        expr: Box::new(Index {
+            expr: Box::new(Variable {
+                name: "m".to_string(),
+                span: Span(0, 1),
+            }),
+            expr_span: Span(0, 1),
+            index: Box::new(Value(Integer(0))),
+            index_span: Span(2, 3),
+        }),
+        expr_span: Span(0, 4),
+        index: Box::new(Value(Integer(1))),
+        index_span: Span(5, 6),
+    });
+
+    assert_eq!(parse_Expression("m[0][1]").unwrap(), ast);
+
+    let ast = Box::new(UnaryOp {
+        expr: Box::new(Index {
+            expr: Box::new(Array {
+                values: vec![(Box::new(Value(Integer(1))), Span(2, 3))],
+                declared_type: None,
+                declared_type_span: None,
+                span: Span(1, 4),
+            }),
+            expr_span: Span(1, 4),
+            index: Box::new(Value(Integer(0))),
+            index_span: Span(5, 6),
+        }),
+        op: UnaryOpCode::Minus,
+        span: Span(0, 7),
+    });
+
+    assert_eq!(parse_Expression("-[1][0]").unwrap(), ast);
+
+    assert!(parse_Expression("x[]").is_err());
+    assert!(parse_Expression("x[1, 2]").is_err());
+}
+
+#[test]
+fn projection() {
+    let ast = Box::new(Projection {
+        expr: Box::new(Variable {
+            name: "t".to_string(),
+            span: Span(0, 1),
+        }),
+        expr_span: Span(0, 1),
+        index: 1,
+        index_span: Span(2, 3),
+    });
+
+    assert_eq!(parse_Expression("t.1").unwrap(), ast);
+
+    let ast = Box::new(Projection {
+        expr: Box::new(Projection {
+            expr: Box::new(Variable {
+                name: "t".to_string(),
+                span: Span(0, 1),
+            }),
+            expr_span: Span(0, 1),
+            index: 0,
+            index_span: Span(2, 3),
+        }),
+        expr_span: Span(0, 3),
+        index: 12,
+        index_span: Span(4, 6),
+    });
+
+    assert_eq!(parse_Expression("t.0.12").unwrap(), ast);
+
+    let ast = Box::new(Projection {
+        expr: Box::new(Tuple(vec![Box::new(Value(Integer(1)))])),
+        expr_span: Span(0, 3),
+        index: 0,
+        index_span: Span(4, 5),
+    });
+
+    assert_eq!(parse_Expression("{1}.0").unwrap(), ast);
+
+    assert!(parse_Expression("t.0 := 2").is_ok());
+    assert!(parse_Expression("t.0.").is_err());
+    assert!(parse_Expression("t.-1").is_err());
+}
+
+#[test]
+fn record() {
+    let ast = Box::new(Let(vec![TypeDecl {
+                                    name: "P".to_string(),
+                                    definition: TypeDefinition::Record(vec![FieldDecl {
+                                        name: "x".to_string(),
+                                        type_: Type::Integer,
+                                        span: Span(14, 24),
+                                    }]),
+                                    span: Span(4, 25),
+                                }],
+                           vec![],
+                           vec![],
+                           Exprs { exprs: vec![], comments: vec![], types: vec![] }));
+
+    assert_eq!(parse_Expression("let type P = {x: Integer} in end").unwrap(), ast);
+
+    let ast = Box::new(Record {
+        name: "P".to_string(),
+        fields: vec![FieldInit {
+                         name: "x".to_string(),
+                         name_span: Span(3, 4),
+                         value: Box::new(Value(Integer(1))),
+                         value_span: Span(7, 8),
+                     },
+                     FieldInit {
+                         name: "y".to_string(),
+                         name_span: Span(10, 11),
+                         value: Box::new(Value(Bool(true))),
+                         value_span: Span(14, 18),
+                     }],
+        span: Span(0, 19),
+    });
+
+    assert_eq!(parse_Expression("P {x = 1, y = true}").unwrap(), ast);
+
+    let ast = Box::new(Field {
+        expr: Box::new(Variable {
+            name: "p".to_string(),
+            span: Span(0, 1),
+        }),
+        expr_span: Span(0, 1),
+        name: "x".to_string(),
+        name_span: Span(2, 3),
+    });
+
+    assert_eq!(parse_Expression("p.x").unwrap(), ast);
+
+    let ast = Box::new(ElementAssign {
+        target: ast,
+        target_span: Span(0, 3),
+        value: Box::new(Value(Integer(1))),
+        value_span: Span(7, 8),
+    });
+
+    assert_eq!(parse_Expression("p.x := 1").unwrap(), ast);
+
+    let ast = Box::new(Cast {
+        expr: Box::new(Variable {
+            name: "x".to_string(),
+            span: Span(0, 1),
+        }),
+        expr_span: Span(0, 1),
+        dest: Type::Array(Box::new(Type::Named("P".to_string()))),
+    });
+
+    assert_eq!(parse_Expression("x as Array(P)").unwrap(), ast);
+
+    assert!(parse_Expression("P {x = 1, x = 2}").is_err());
+}
+
+#[test]
+fn sum_type() {
+    let ast = Box::new(Let(vec![TypeDecl {
+                                    name: "S".to_string(),
+                                    definition: TypeDefinition::Sum(vec![VariantDecl {
+                                                                             name: "A".to_string(),
+                                                                             types: vec![],
+                                                                             span: Span(13, 14),
+                                                                         },
+                                                                         VariantDecl {
+                                                                             name: "B".to_string(),
+                                                                             types: vec![Type::Integer],
+                                                                             span: Span(17, 27),
+                                                                         }]),
+                                    span: Span(4, 27),
+                                }],
+                           vec![],
+                           vec![],
+                           Exprs { exprs: vec![], comments: vec![], types: vec![] }));
+
+    assert_eq!(parse_Expression("let type S = A | B(Integer) in end").unwrap(), ast);
+
+    let ast = Box::new(PatternMatch {
+        lhs: Box::new(Function {
+            name: "B".to_string(),
+            args: vec![(Box::new(Variable {
+                            name: "x".to_string(),
+                            span: Span(8, 9),
+                        }),
+                        Span(8, 9))],
+            span: Span(6, 10),
+        }),
+        lhs_span: Span(6, 10),
+        rhs: Box::new(Variable {
+            name: "s".to_string(),
+            span: Span(14, 15),
+        }),
+        rhs_span: Span(14, 15),
+        bindings: vec![],
+    });
+
+    assert_eq!(parse_Expression("match B(x) := s").unwrap(), ast);
+
+    assert!(parse_Expression("let type S = A | A in end").is_err());
+}
+
 #[test]
 fn tuple() {
     let ast = Box::new(Tuple(vec![]));
@@ -731,3 +1476,197 @@ fn value() {
 
     assert!(parse_Expression(r#""hel\u""#).is_err());
 }
+
+// The first error of the lexer
+fn lex_error(input: &str) -> UserParseError {
+    Lexer::new(input).filter_map(|token| token.err()).next().unwrap()
+}
+
+#[test]
+fn literals() {
+    let ast = Box::new(Value(Integer(42)));
+    assert_eq!(parse_Expression("0x2A").unwrap(), ast);
+    assert_eq!(parse_Expression("0x2a").unwrap(), ast);
+    assert_eq!(parse_Expression("0b10_1010").unwrap(), ast);
+    assert_eq!(parse_Expression("4_2").unwrap(), ast);
+
+    let ast = Box::new(Value(Float(1500f64)));
+    assert_eq!(parse_Expression("1.5e3").unwrap(), ast);
+    assert_eq!(parse_Expression("15E2").unwrap(), ast);
+    assert_eq!(parse_Expression("150_000e-2").unwrap(), ast);
+
+    assert_eq!(lex_error("0x"),
+               UserParseError::InvalidNumberLiteral { span: Span(0, 2) });
+    assert_eq!(lex_error("1 + 0b102"),
+               UserParseError::InvalidNumberLiteral { span: Span(4, 9) });
+    assert_eq!(lex_error("1e"), UserParseError::InvalidNumberLiteral { span: Span(0, 2) });
+    assert_eq!(lex_error("0x8000000000000000"),
+               UserParseError::IntegerOverflow { span: Span(0, 18) });
+
+    let ast = Box::new(Value(Str("I \u{1F600} Rust".to_string())));
+    assert_eq!(parse_Expression(r#""I \u{1F600} Rust""#).unwrap(), ast);
+
+    assert_eq!(lex_error(r#""\xZZ""#),
+               UserParseError::InvalidStringEscapeSequence {
+                   sequence_id: 'x',
+                   span: Span(1, 4),
+               });
+    assert_eq!(lex_error(r#""\u{12""#),
+               UserParseError::InvalidStringEscapeSequence {
+                   sequence_id: 'u',
+                   span: Span(1, 7),
+               });
+    assert_eq!(lex_error(r#""a\qb""#),
+               UserParseError::InvalidStringEscapeSequence {
+                   sequence_id: 'q',
+                   span: Span(2, 4),
+               });
+    assert_eq!(lex_error(r#""\é""#),
+               UserParseError::InvalidStringEscapeSequence {
+                   sequence_id: 'é',
+                   span: Span(1, 4),
+               });
+    assert_eq!(parse_Expression(r#""\\\"\$""#).unwrap(),
+               Box::new(Value(Str("\\\"$".to_string()))));
+    assert_eq!(lex_error(r#""\u{110000}""#),
+               UserParseError::InvalidCodePoint {
+                   value: 0x110000,
+                   span: Span(1, 11),
+               });
+    assert_eq!(lex_error(r#""\uD800""#),
+               UserParseError::InvalidCodePoint {
+                   value: 0xD800,
+                   span: Span(1, 7),
+               });
+    assert_eq!(lex_error(r#"1, "abc"#),
+               UserParseError::UnterminatedString { span: Span(3, 7) });
+
+    assert_eq!(lex_error("1 @ 2"),
+               UserParseError::UnexpectedCharacter {
+                   character: '@',
+                   span: Span(2, 3),
+               });
+}
+
+#[test]
+fn comments() {
+    let mut lexer = Lexer::new("1 /* a /* b */ c */ + // d\n2");
+    let tokens = lexer.by_ref().map(Result::unwrap).collect::<Vec<_>>();
+
+    assert_eq!(tokens,
+               vec![(0, Tok::Integer(1), 1),
+                    (20, Tok::Symbol("+"), 21),
+                    (27, Tok::Integer(2), 28)]);
+    assert_eq!(lexer.into_comments(),
+               vec![Comment {
+                        text: "/* a /* b */ c */".to_string(),
+                        span: Span(2, 19),
+                    },
+                    Comment {
+                        text: "// d".to_string(),
+                        span: Span(22, 26),
+                    }]);
+
+    let mut lexer = Lexer::new(r#""/* \" // */""#);
+    assert_eq!(lexer.next(),
+               Some(Ok((0, Tok::Str(r#"/* " // */"#.to_string()), 13))));
+    assert!(lexer.into_comments().is_empty());
+
+    assert_eq!(lex_error("1 /* /* */"),
+               UserParseError::UnterminatedComment { span: Span(2, 10) });
+
+    let exprs = parse_Expressions("(// one\n1, /* two */ 2), let in /* three */ 3 end // four")
+        .unwrap();
+
+    let texts = |exprs: &Exprs| {
+        exprs.comments.iter().map(|comment| comment.text.clone()).collect::<Vec<_>>()
+    };
+
+    assert_eq!(texts(&exprs), vec!["// four"]);
+    match *exprs.exprs[0].0 {
+        Grouping(ref exprs) => assert_eq!(texts(exprs), vec!["// one", "/* two */"]),
+        ref expr => panic!("Not a grouping: {:?}", expr),
+    }
+    match *exprs.exprs[1].0 {
+        Let(_, _, _, ref exprs) => assert_eq!(texts(exprs), vec!["/* three */"]),
+        ref expr => panic!("Not a let: {:?}", expr),
+    }
+}
+
+#[test]
+fn error_recovery() {
+    use lalrpop_util::ParseError::*;
+
+    let locations = |input| {
+        parse_Expressions(input)
+            .unwrap_err()
+            .into_iter()
+            .map(|err| match err {
+                     UnrecognizedToken { token: Some((start, _, end)), .. } => Some((start, end)),
+                     UnrecognizedToken { token: None, .. } => None,
+                     err => panic!("Unexpected error: {:?}", err),
+                 })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(locations("1 +, 2 * , 3"), vec![Some((3, 4)), Some((9, 10))]);
+    assert_eq!(locations("let var := 1 function f( := 2 in 1 +, 2 end"),
+               vec![Some((8, 10)), Some((25, 27)), Some((36, 37))]);
+    assert_eq!(locations("(1, 2 +), let in 1"), vec![Some((7, 8)), None]);
+
+    match parse_Expressions("let var := 1 in 1 end").unwrap_err()[0] {
+        UnrecognizedToken { ref expected, .. } => assert_eq!(expected, &["Ident"]),
+        ref err => panic!("Unexpected error: {:?}", err),
+    }
+
+    // A missing end is located on the last character of the input, not after its last line
+    let hints = ::parse_expressions("let in\n  1 +\n").unwrap_err()[0].hints();
+    assert_eq!(hints[0].span, Span(11, 12));
+}
+
+#[test]
+fn interpolation() {
+    let x = Box::new(Variable {
+                         name: "x".to_string(),
+                         span: Span(7, 8),
+                     });
+    let y_plus_1 = Box::new(BinaryOp {
+                                lhs: Box::new(Variable {
+                                                  name: "y".to_string(),
+                                                  span: Span(17, 18),
+                                              }),
+                                rhs: Box::new(Value(Integer(1))),
+                                op: BinaryOpCode::Add,
+                                span: Span(17, 22),
+                            });
+    assert_eq!(parse_Expression(r#""x = ${x}, y = ${y + 1}""#).unwrap(),
+               Box::new(Interpolation(vec![InterpolationPart::Text("x = ".to_string()),
+                                           InterpolationPart::Expr(x, Span(7, 8)),
+                                           InterpolationPart::Text(", y = ".to_string()),
+                                           InterpolationPart::Expr(y_plus_1, Span(17, 22))])));
+
+    // The braces of the embedded expressions are balanced
+    let tokens = Lexer::new(r#""a${ {1}.0 }b${"${c}"}""#)
+        .map(|token| token.unwrap().1)
+        .collect::<Vec<_>>();
+    assert_eq!(tokens,
+               vec![Tok::InterpolationStart("a".to_string()),
+                    Tok::Symbol("{"),
+                    Tok::Integer(1),
+                    Tok::Symbol("}"),
+                    Tok::Symbol("."),
+                    Tok::Integer(0),
+                    Tok::InterpolationMiddle("b".to_string()),
+                    Tok::InterpolationStart("".to_string()),
+                    Tok::Identifier("c"),
+                    Tok::InterpolationEnd("".to_string()),
+                    Tok::InterpolationEnd("".to_string())]);
+
+    assert_eq!(parse_Expression(r#""\${x} $x""#).unwrap(),
+               Box::new(Value(Str("${x} $x".to_string()))));
+
+    assert_eq!(lex_error(r#""${x} a"#),
+               UserParseError::UnterminatedString { span: Span(4, 7) });
+    assert!(parse_Expression(r#""${}""#).is_err());
+    assert!(parse_Expressions(r#""${x""#).is_err());
+}
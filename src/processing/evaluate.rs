@@ -1,49 +1,109 @@
 use ast::*;
-use type_sys::Value;
-use env::{Environment, BindingInfo, ValueInfo};
+use type_sys::{Type, Value};
+use env::{Environment, BindingInfo, Callable, Scope, ValueInfo};
+use error::{InvalidArgumentsError, NonPositiveStepError, RuntimeError, UnboundedVarError};
 use processing::pattern_match::PatternMatch;
 
+use std::collections::{HashMap, LinkedList};
+use std::mem;
+
 /// That trait that must be implemented by part of the AST for evaluation
 pub trait Evaluate {
-    fn evaluate(&self, env: &mut Environment<ValueInfo>) -> Value;
+    fn evaluate(&self, env: &mut Environment<ValueInfo>) -> Result<Value, RuntimeError>;
 }
 
 impl Evaluate for Exprs {
-    fn evaluate(&self, env: &mut Environment<ValueInfo>) -> Value {
+    fn evaluate(&self, env: &mut Environment<ValueInfo>) -> Result<Value, RuntimeError> {
+        self.eval(env).map_err(Unwind::into_error)
+    }
+}
+
+impl Evaluate for Expr {
+    fn evaluate(&self, env: &mut Environment<ValueInfo>) -> Result<Value, RuntimeError> {
+        self.eval(env).map_err(Unwind::into_error)
+    }
+}
+
+// Why the evaluation of an expression stopped before giving a value
+//
+// Going through the error path of the evaluation, `break`, `continue` and `return` leave the scopes
+// entered on the way to the loop or the function they stop.
+enum Unwind {
+    /// A runtime error
+    Error(RuntimeError),
+    /// A `break`, caught by the innermost loop
+    Break,
+    /// A `continue`, caught by the innermost loop
+    Continue,
+    /// A `return` with its value, caught by the innermost function call
+    Return(Value),
+}
+
+impl Unwind {
+    // Get the runtime error which stopped the evaluation, once outside of any loop or function
+    fn into_error(self) -> RuntimeError {
+        match self {
+            Unwind::Error(err) => err,
+            // Rejected by the type checker
+            Unwind::Break | Unwind::Continue => panic!("break or continue outside of a loop"),
+            Unwind::Return(_) => panic!("return outside of a function"),
+        }
+    }
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(err: RuntimeError) -> Self {
+        Unwind::Error(err)
+    }
+}
+
+// Evaluate a part of the AST, possibly stopped by a `break`, a `continue` or a `return`
+trait Eval {
+    fn eval(&self, env: &mut Environment<ValueInfo>) -> Result<Value, Unwind>;
+}
+
+impl Eval for Exprs {
+    fn eval(&self, env: &mut Environment<ValueInfo>) -> Result<Value, Unwind> {
         let mut value = Value::Void;
-        for expr in &self.exprs {
-            value = expr.evaluate(env);
+        for &(ref expr, _) in &self.exprs {
+            value = expr.eval(env)?;
         }
-        value
+        Ok(value)
     }
 }
 
-impl Evaluate for Expr {
-    fn evaluate(&self, env: &mut Environment<ValueInfo>) -> Value {
+impl Eval for Expr {
+    fn eval(&self, env: &mut Environment<ValueInfo>) -> Result<Value, Unwind> {
         use ast::Expr::*;
         use type_sys;
 
         match *self {
-            Grouping(ref exprs) => exprs.evaluate(env),
+            Grouping(ref exprs) => exprs.eval(env),
 
-            Let(ref bindings, ref function_decls, ref exprs) => {
+            Let(ref type_decls, ref bindings, ref function_decls, ref exprs) => {
                 env.enter_scope();
 
-                for binding in bindings.iter() {
-                    let value = binding.value.evaluate(env);
-                    env.declare_var(binding.name.clone(),
-                                     BindingInfo::Variable {
-                                         declaration: binding.clone(),
-                                         info: ValueInfo(value),
-                                     })
-                        .unwrap();
-                }
+                let rv = (|| {
+                    for type_decl in type_decls.iter() {
+                        env.declare_type(type_decl.clone()).unwrap();
+                    }
 
-                for function_decl in function_decls.iter() {
-                    env.declare_func(function_decl.clone()).unwrap();
-                }
+                    for binding in bindings.iter() {
+                        let value = binding.value.eval(env)?;
+                        env.declare_var(binding.name.clone(),
+                                         BindingInfo::Variable {
+                                             declaration: binding.clone(),
+                                             info: ValueInfo(value),
+                                         })
+                            .unwrap();
+                    }
 
-                let rv = exprs.evaluate(env);
+                    for function_decl in function_decls.iter() {
+                        env.declare_func(function_decl.clone()).unwrap();
+                    }
+
+                    exprs.eval(env)
+                })();
 
                 env.leave_scope();
                 rv
@@ -51,182 +111,844 @@ impl Evaluate for Expr {
 
             Assign {
                 ref name,
+                ref name_span,
                 ref value,
                 ..
             } => {
-                let value = value.evaluate(env);
-                env.assign(name, value.clone());
-                value
+                let value = value.eval(env)?;
+                env.assign(name, value.clone())
+                    .map_err(|mut err| {
+                                 err.set_span(*name_span);
+                                 err
+                             })?;
+                Ok(value)
             }
 
-            PatternMatch { ref lhs, ref rhs, .. } => {
-                let var_save = env.scopes.clone();
-
-                let res = lhs.pattern_match(&rhs.evaluate(env), env);
-
-                if !res {
-                    env.scopes = var_save;
-                }
-
-                type_sys::Value::Bool(res)
+            ElementAssign {
+                ref target,
+                ref value,
+                value_span,
+                ..
+            } => {
+                let value = value.eval(env)?;
+                assign_place(target, value.clone(), value_span, env)?;
+                Ok(value)
             }
 
-            Function { ref name, ref args, .. } => {
-                let args = args.iter()
-                    .map(|&(ref expr, _)| expr.evaluate(env))
-                    .collect::<Vec<type_sys::Value>>();
-
-                let mut user_defined = false;
-                let mut user_func = None;
-
-                if let Some(func) = env.get_func(name) {
-                    user_defined = true;
-
-                    user_func = Some(func.clone());
-                }
-
-                if user_defined {
-                    let func = user_func.unwrap();
-
-                    env.enter_scope();
-
-                    for (ind, value) in args.into_iter().enumerate() {
-                        let current_arg = &func.args[ind];
-
-                        env.declare_var(current_arg.name.clone(),
-                                         BindingInfo::Argument {
-                                             declaration: current_arg.clone(),
-                                             info: ValueInfo(value),
-                                         })
-                            .unwrap();
-                    }
+            PatternMatch {
+                ref lhs,
+                ref rhs,
+                ref bindings,
+                ..
+            } => {
+                let rhs = rhs.eval(env)?;
+                let mut values = Vec::new();
+                let res = lhs.pattern_match(&rhs, &mut values, env)?;
 
-                    let rv = func.body.evaluate(env);
+                bind_pattern(bindings, if res { values } else { Vec::new() }, env);
 
-                    env.leave_scope();
-                    rv
-                } else {
-                    env.call_builtin(name, &args)
-                }
+                Ok(type_sys::Value::Bool(res))
             }
 
+            Function {
+                ref name,
+                ref args,
+                ref span,
+            } => eval_call(name, args, *span, env),
+
             If {
                 ref cond,
                 ref true_branch,
                 ref false_branch,
                 ..
             } => {
-                if cond.evaluate(env).truthy() {
-                    true_branch.evaluate(env)
-                } else {
-                    false_branch.evaluate(env)
+                match (cond.eval(env)?.truthy(), false_branch) {
+                    (true, &Some(_)) => eval_in_scope(true_branch, env),
+                    (true, &None) => {
+                        eval_in_scope(true_branch, env)?;
+                        Ok(type_sys::Value::Void)
+                    }
+                    (false, &Some((ref false_branch, _))) => eval_in_scope(false_branch, env),
+                    (false, &None) => Ok(type_sys::Value::Void),
                 }
             }
 
+            Case { ref expr, ref arms, .. } => eval_case(expr, arms, env),
+
             While { ref cond, ref expr, .. } => {
-                while cond.evaluate(env).truthy() {
-                    expr.evaluate(env);
+                while cond.eval(env)?.truthy() {
+                    if !run_loop_body(expr, env)? {
+                        break;
+                    }
                 }
-                type_sys::Value::Void
+                Ok(type_sys::Value::Void)
             }
 
             For {
                 ref binding,
+                direction,
                 ref goal,
+                ref step,
                 ref expr,
                 ..
-            } => {
-                env.enter_scope();
+            } => eval_for(binding, direction, goal, step, expr, env),
 
-                let val = binding.value.evaluate(env);
-                env.declare_var(binding.name.clone(),
-                                 BindingInfo::Variable {
-                                     declaration: (**binding).clone(),
-                                     info: ValueInfo(val.clone()),
-                                 })
-                    .unwrap();
-
-                let upper = goal.evaluate(env);
-                match (val, upper) {
-                    (type_sys::Value::Integer(mut val), type_sys::Value::Integer(upper)) => {
-                        while val < upper {
-                            expr.evaluate(env);
-                            val = 1 +
-                                  if let type_sys::Value::Integer(val) =
-                                *env.get_var(&binding.name).unwrap().get_value() {
-                                      val
-                                  } else {
-                                      panic!("Variable {} is not of type Integer anymore",
-                                             binding.name);
-                                  };
-                            env.assign(&binding.name, type_sys::Value::Integer(val));
-                        }
-                    }
-                    other => {
-                        panic!("{:?} is not of type (Integer, Integer) in for loop evaluation",
-                               other)
-                    }
-                }
-                env.leave_scope();
-                type_sys::Value::Void
-            }
+            ForIn {
+                ref name,
+                name_span,
+                ref iterable,
+                ref expr,
+                ..
+            } => eval_for_in(name, name_span, iterable, expr, env),
 
             BinaryOp {
                 ref lhs,
                 ref rhs,
                 ref op,
-                ..
+                ref span,
             } => {
-                let args = vec![lhs.evaluate(env), rhs.evaluate(env)];
+                let args = vec![lhs.eval(env)?, rhs.eval(env)?];
                 env.call_builtin(&op.to_string(), &args)
+                    .map_err(|mut err| {
+                                 err.set_span(*span);
+                                 err.into()
+                             })
+            }
+
+            LogicalOp {
+                ref lhs,
+                ref rhs,
+                op,
+                ..
+            } => {
+                let lhs = lhs.eval(env)?.truthy();
+                let result = match op {
+                    LogicalOpCode::And if !lhs => false,
+                    LogicalOpCode::Or if lhs => true,
+                    _ => eval_in_scope(rhs, env)?.truthy(),
+                };
+
+                Ok(type_sys::Value::Bool(result))
             }
 
-            UnaryOp { ref expr, ref op, .. } => {
-                let args = vec![expr.evaluate(env)];
+            UnaryOp {
+                ref expr,
+                op: UnaryOpCode::Not,
+                ..
+            } => Ok(type_sys::Value::Bool(!expr.eval(env)?.truthy())),
+
+            UnaryOp {
+                ref expr,
+                ref op,
+                ref span,
+            } => {
+                let args = vec![expr.eval(env)?];
                 env.call_builtin(&format!("un{}", op.to_string()), &args)
+                    .map_err(|mut err| {
+                                 err.set_span(*span);
+                                 err.into()
+                             })
+            }
+
+            Cast {
+                ref expr,
+                ref expr_span,
+                ref dest,
+            } => {
+                let dest = env.resolve_type(dest);
+
+                expr.eval(env)?
+                    .into(&dest)
+                    .map_err(|mut err| {
+                                 err.set_span(*expr_span);
+                                 err.into()
+                             })
             }
 
-            Cast { ref expr, ref dest, .. } => expr.evaluate(env).into(dest),
+            TryCast { ref expr, ref dest, .. } => {
+                let dest = env.resolve_type(dest);
+
+                let (ok, value) = match expr.eval(env)?.into(&dest) {
+                    Ok(value) => (true, value),
+                    Err(_) => (false, type_sys::Value::default_of(&dest, env)),
+                };
 
-            Variable { ref name, .. } => {
-                env.get_var(name)
-                    .expect(format!("Unbounded variable: {}", name).as_str())
-                    .get_value()
-                    .clone()
+                Ok(type_sys::Value::Tuple {
+                       element_types: vec![Type::Bool, dest],
+                       values: vec![type_sys::Value::Bool(ok), value],
+                   })
+            }
+
+            Index {
+                ref expr,
+                ref index,
+                ref index_span,
+                ..
+            } => {
+                let value = expr.eval(env)?;
+                let index = evaluate_index(index, env)?;
+
+                value
+                    .get_element(index)
+                    .map_err(|mut err| {
+                                 err.set_span(*index_span);
+                                 err.into()
+                             })
+            }
+
+            Projection { ref expr, index, .. } => {
+                match expr.eval(env)? {
+                    type_sys::Value::Tuple { mut values, .. } => Ok(values.swap_remove(index)),
+                    other => panic!("{:?} is not a Tuple in projection evaluation", other),
+                }
+            }
+
+            Field { ref expr, ref name, .. } => {
+                match expr.eval(env)? {
+                    type_sys::Value::Record { mut fields, .. } => {
+                        let pos = fields
+                            .iter()
+                            .position(|&(ref field_name, _)| field_name == name)
+                            .unwrap();
+                        Ok(fields.swap_remove(pos).1)
+                    }
+                    other => panic!("{:?} is not a Record in field evaluation", other),
+                }
             }
 
+            Variable { ref name, span } => eval_variable(name, span, env),
+
             Array {
                 ref values,
                 ref declared_type,
                 ..
             } => {
-                type_sys::Value::Array {
-                    element_type: declared_type.clone().unwrap(),
-                    values: values
-                        .iter()
-                        .map(|&(ref expr, _)| expr.evaluate(env))
-                        .collect(),
-                }
+                Ok(type_sys::Value::Array {
+                       element_type: env.resolve_type(declared_type.as_ref().unwrap()),
+                       values: values
+                           .iter()
+                           .map(|&(ref expr, _)| expr.eval(env))
+                           .collect::<Result<_, _>>()?,
+                   })
             }
 
             Tuple(ref exprs) => {
                 let (element_types, values) = exprs
                     .iter()
-                    .map(|expr| {
-                             let value = expr.evaluate(env);
-                             let type_ = value.get_type();
-                             (type_, value)
-                         })
+                    .map(|expr| expr.eval(env))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .into_iter()
+                    .map(|value| (value.get_type(), value))
                     .unzip();
 
-                type_sys::Value::Tuple {
-                    element_types,
-                    values,
+                Ok(type_sys::Value::Tuple {
+                       element_types,
+                       values,
+                   })
+            }
+
+            Record {
+                ref name,
+                ref fields,
+                ..
+            } => eval_record(name, fields, env),
+
+            Lambda(ref decl) => {
+                Ok(type_sys::Value::Closure {
+                       decl: Box::new(resolve_signature(decl, env)),
+                       scopes: env.scopes.clone(),
+                   })
+            }
+
+            Interpolation(ref parts) => eval_interpolation(parts, env),
+
+            Value(ref value) => Ok(value.clone()),
+
+            Break(_) => Err(Unwind::Break),
+
+            Continue(_) => Err(Unwind::Continue),
+
+            Return { ref value, .. } => Err(Unwind::Return(value.eval(env)?)),
+
+            Wildcard(_) | Rest { .. } => panic!("Pattern outside of a pattern match"),
+
+        }
+    }
+}
+
+// Evaluate a variable, a function used as a value or a variant without values
+fn eval_variable(name: &str,
+                 span: Span,
+                 env: &mut Environment<ValueInfo>)
+                 -> Result<Value, Unwind> {
+    match env.get_callable(name) {
+        Some(Callable::Variable(var)) => Ok(var.get_value().clone()),
+        Some(Callable::Function(func)) => {
+            let depth = env.get_depth(name).unwrap();
+
+            Ok(Value::Closure {
+                   decl: Box::new(resolve_signature(func, env)),
+                   scopes: env.scopes.iter().skip(depth).cloned().collect(),
+               })
+        }
+        None => {
+            match env.get_variant(name) {
+                Some((decl, _)) => {
+                    Ok(Value::Variant {
+                           type_name: decl.name.clone(),
+                           tag: name.to_string(),
+                           values: Vec::new(),
+                       })
+                }
+                None => {
+                    let err = UnboundedVarError::new(name.to_string(), span);
+                    Err(Unwind::Error(err.into()))
+                }
+            }
+        }
+    }
+}
+
+// Evaluate the construction of a record, whose fields are stored in the order of the declaration
+fn eval_record(name: &str,
+               fields: &[FieldInit],
+               env: &mut Environment<ValueInfo>)
+               -> Result<Value, Unwind> {
+    let mut values = Vec::with_capacity(fields.len());
+    for field in fields {
+        values.push((field.name.clone(), field.value.eval(env)?));
+    }
+
+    match env.get_type_decl(name)
+              .unwrap_or_else(|| panic!("Undeclared type {} in record evaluation", name))
+              .definition {
+        TypeDefinition::Record(ref field_decls) => {
+            values.sort_by_key(|&(ref field_name, _)| {
+                                   field_decls
+                                       .iter()
+                                       .position(|field| field.name == *field_name)
+                               });
+        }
+        TypeDefinition::Sum(_) => unreachable!("Record construction of a sum type"),
+    }
+
+    Ok(Value::Record {
+           type_name: name.to_string(),
+           fields: values,
+       })
+}
+
+// Evaluate an interpolated string, converting the values of the embedded expressions to Str
+fn eval_interpolation(parts: &[InterpolationPart],
+                      env: &mut Environment<ValueInfo>)
+                      -> Result<Value, Unwind> {
+    let mut result = String::new();
+
+    for part in parts {
+        match *part {
+            InterpolationPart::Text(ref text) => result.push_str(text),
+            InterpolationPart::Expr(ref expr, span) => {
+                let value = expr.eval(env)?
+                    .into(&Type::Str)
+                    .map_err(|mut err| {
+                                 err.set_span(span);
+                                 err
+                             })?;
+
+                match value {
+                    Value::Str(value) => result.push_str(&value),
+                    _ => unreachable!("Conversion to a Str of another type"),
+                }
+            }
+        }
+    }
+
+    Ok(Value::Str(result))
+}
+
+// Evaluate the call of a user function, a closure, a variant constructor or a builtin
+fn eval_call(name: &str,
+             args: &[(Box<Expr>, Span)],
+             span: Span,
+             env: &mut Environment<ValueInfo>)
+             -> Result<Value, Unwind> {
+    let args = args.iter()
+        .map(|&(ref expr, _)| expr.eval(env))
+        .collect::<Result<Vec<Value>, _>>()?;
+
+    let mut user_func = None;
+    let mut closure = None;
+
+    match env.get_callable(name) {
+        Some(Callable::Function(func)) => user_func = Some(func.clone()),
+        Some(Callable::Variable(var)) => closure = Some(var.get_value().clone()),
+        None => {}
+    }
+
+    if let Some(func) = user_func {
+        let depth = env.get_depth(name).unwrap();
+        call_declared_function(&func, depth, args, span, env)
+    } else if let Some(Value::Closure { decl, scopes }) = closure {
+        call_closure(&decl, scopes, args, span, env)
+    } else if let Some((decl, _)) = env.get_variant(name) {
+        Ok(Value::Variant {
+               type_name: decl.name.clone(),
+               tag: name.to_string(),
+               values: args,
+           })
+    } else {
+        env.call_builtin(name, &args)
+            .map_err(|mut err| {
+                         err.set_span(span);
+                         err.into()
+                     })
+    }
+}
+
+// Evaluate the body of the first arm whose pattern matches the value of the expression, and whose
+// guard holds
+fn eval_case(expr: &Expr,
+             arms: &[CaseArm],
+             env: &mut Environment<ValueInfo>)
+             -> Result<Value, Unwind> {
+    let value = expr.eval(env)?;
+
+    for arm in arms {
+        let mut values = Vec::new();
+        if !arm.pattern.pattern_match(&value, &mut values, env)? {
+            continue;
+        }
+
+        // The variables bound by the pattern only live in the arm
+        env.enter_scope();
+
+        let rv = (|| {
+            bind_pattern(&arm.bindings, values, env);
+
+            let guard = match arm.guard {
+                Some((ref guard, _)) => guard.eval(env)?.truthy(),
+                None => true,
+            };
+
+            if guard {
+                arm.body.eval(env).map(Some)
+            } else {
+                Ok(None)
+            }
+        })();
+
+        env.leave_scope();
+
+        if let Some(result) = rv? {
+            return Ok(result);
+        }
+    }
+
+    unreachable!("Non-exhaustive case expression")
+}
+
+// Evaluate a `for` loop over the integers from the value of the binding to the goal
+fn eval_for(binding: &VariableDecl,
+            direction: ForDirection,
+            goal: &Expr,
+            step: &Option<(Box<Expr>, Span)>,
+            body: &Expr,
+            env: &mut Environment<ValueInfo>)
+            -> Result<Value, Unwind> {
+    env.enter_scope();
+
+    let rv = (|| {
+        // The bounds and the step are evaluated before the variable is declared, like they are
+        // checked: they cannot refer to it
+        let val = binding.value.eval(env)?;
+        let goal = goal.eval(env)?;
+        let step = match *step {
+            Some((ref step, step_span)) => {
+                match step.eval(env)? {
+                    Value::Integer(step) if step > 0 => step,
+                    Value::Integer(step) => {
+                        let err = NonPositiveStepError::new(step, step_span);
+                        return Err(RuntimeError::from(err).into());
+                    }
+                    other => panic!("{:?} is not of type Integer in for loop evaluation", other),
                 }
             }
+            None => 1,
+        };
+
+        env.declare_var(binding.name.clone(),
+                         BindingInfo::Variable {
+                             declaration: binding.clone(),
+                             info: ValueInfo(val.clone()),
+                         })
+            .unwrap();
+
+        match (val, goal) {
+            (Value::Integer(mut val), Value::Integer(goal)) => {
+                while before_goal(val, goal, direction) {
+                    if !run_loop_body(body, env)? {
+                        break;
+                    }
+                    let current = if let Value::Integer(val) =
+                        *env.get_var(&binding.name).unwrap().get_value() {
+                        val
+                    } else {
+                        panic!("Variable {} is not of type Integer anymore", binding.name);
+                    };
+                    val = match next_iteration(current, step, direction) {
+                        Some(val) => val,
+                        None => break,
+                    };
+                    env.assign(&binding.name, Value::Integer(val))?;
+                }
+            }
+            other => {
+                panic!("{:?} is not of type (Integer, Integer) in for loop evaluation", other)
+            }
+        }
+
+        Ok(Value::Void)
+    })();
+
+    env.leave_scope();
+    rv
+}
+
+// Evaluate a `for` loop over the elements of an array or the characters of a string
+fn eval_for_in(name: &str,
+               name_span: Span,
+               iterable: &Expr,
+               body: &Expr,
+               env: &mut Environment<ValueInfo>)
+               -> Result<Value, Unwind> {
+    let elements = iterable.eval(env)?.into_elements();
+
+    env.enter_scope();
+
+    let rv = (|| {
+        for element in elements {
+            env.bind_var(name.to_string(),
+                         BindingInfo::Pattern {
+                             declaration: PatternBinding {
+                                 name: name.to_string(),
+                                 type_: element.get_type(),
+                                 span: name_span,
+                             },
+                             info: ValueInfo(element),
+                         });
+
+            if !run_loop_body(body, env)? {
+                break;
+            }
+        }
+
+        Ok(Value::Void)
+    })();
+
+    env.leave_scope();
+    rv
+}
+
+// Run the body of a loop, catching a `break` or a `continue` coming from it
+//
+// Returns false if the loop must stop.
+fn run_loop_body(body: &Expr, env: &mut Environment<ValueInfo>) -> Result<bool, Unwind> {
+    match eval_in_scope(body, env) {
+        Ok(_) | Err(Unwind::Continue) => Ok(true),
+        Err(Unwind::Break) => Ok(false),
+        Err(unwind) => Err(unwind),
+    }
+}
+
+// Evaluate an expression which may not be evaluated (a branch, the body of a loop, ...) in its own
+// scope, like the type checker does
+fn eval_in_scope(expr: &Expr, env: &mut Environment<ValueInfo>) -> Result<Value, Unwind> {
+    env.enter_scope();
+    let rv = expr.eval(env);
+    env.leave_scope();
+    rv
+}
+
+// Whether the variable of a For loop has not reached its goal yet
+fn before_goal(val: i64, goal: i64, direction: ForDirection) -> bool {
+    match direction {
+        ForDirection::Up => val < goal,
+        ForDirection::Down => val > goal,
+    }
+}
+
+// Get the next value of the variable of a For loop
+//
+// Going past the limits of an Integer means going past the goal: None is returned.
+fn next_iteration(val: i64, step: i64, direction: ForDirection) -> Option<i64> {
+    match direction {
+        ForDirection::Up => val.checked_add(step),
+        ForDirection::Down => val.checked_sub(step),
+    }
+}
+
+// Call a function on top of the current scopes, with already evaluated arguments
+//
+// The arguments are checked against the signature, as the AST might not have been type checked.
+fn call_function(func: &FunctionDecl,
+                 args: Vec<Value>,
+                 span: Span,
+                 env: &mut Environment<ValueInfo>)
+                 -> Result<Value, Unwind> {
+    let invalid_arguments = |args: &[Value]| {
+        let arg_types = args.iter().map(Value::get_type).collect();
+        RuntimeError::from(InvalidArgumentsError::new(func.name.clone(), arg_types, span))
+    };
+
+    if args.len() != func.args.len() {
+        return Err(invalid_arguments(&args).into());
+    }
+
+    // The type parameters of a generic function stand for the types of the given arguments
+    let params = if func.type_params.is_empty() {
+        HashMap::new()
+    } else {
+        let arg_types = args.iter().map(Value::get_type).collect::<Vec<_>>();
+        func.instantiate(&arg_types).ok_or_else(|| invalid_arguments(&args))?
+    };
+
+    env.enter_scope();
+
+    for (name, type_) in params {
+        env.declare_type_param(name, type_);
+    }
+
+    for (ind, value) in args.into_iter().enumerate() {
+        let current_arg = &func.args[ind];
+
+        env.declare_var(current_arg.name.clone(),
+                         BindingInfo::Argument {
+                             declaration: current_arg.clone(),
+                             info: ValueInfo(value),
+                         })
+            .unwrap();
+    }
+
+    let rv = match func.body.eval(env) {
+        Err(Unwind::Return(value)) => Ok(value),
+        rv => rv,
+    };
+
+    env.leave_scope();
+    rv
+}
+
+// Call a function declared in the scope at the given depth (0 being the innermost scope)
+//
+// The scopes above the declaration scope are put aside during the call, so the body only sees the
+// bindings visible where the function was declared (lexical scoping).
+fn call_declared_function(func: &FunctionDecl,
+                          depth: usize,
+                          args: Vec<Value>,
+                          span: Span,
+                          env: &mut Environment<ValueInfo>)
+                          -> Result<Value, Unwind> {
+    let decl_scopes = env.scopes.split_off(depth);
+    let caller_scopes = mem::replace(&mut env.scopes, decl_scopes);
+
+    let rv = call_function(func, args, span, env);
+
+    let mut decl_scopes = mem::replace(&mut env.scopes, caller_scopes);
+    env.scopes.append(&mut decl_scopes);
+    rv
+}
+
+// Call a closure in the scopes it captured, then restore the scopes of the caller
+fn call_closure(func: &FunctionDecl,
+                scopes: LinkedList<Scope<ValueInfo>>,
+                args: Vec<Value>,
+                span: Span,
+                env: &mut Environment<ValueInfo>)
+                -> Result<Value, Unwind> {
+    let caller_scopes = mem::replace(&mut env.scopes, scopes);
+    let rv = call_function(func, args, span, env);
+    env.scopes = caller_scopes;
+    rv
+}
+
+// Get a copy of a function declaration where the type parameters of the enclosing generic functions
+// are replaced in the signature by the types they stand for
+//
+// This gives closures a concrete type. The own type parameters of a generic function are left
+// as is.
+fn resolve_signature(decl: &FunctionDecl, env: &Environment<ValueInfo>) -> FunctionDecl {
+    let lookup = |name: &str| if decl.type_params.iter().any(|&(ref param, _)| param == name) {
+        None
+    } else {
+        env.get_type_param(name).cloned()
+    };
+
+    let mut decl = decl.clone();
+    for arg in &mut decl.args {
+        arg.type_ = arg.type_.as_ref().map(|type_| type_.substitute(&lookup));
+    }
+    decl.return_type = decl.return_type.as_ref().map(|type_| type_.substitute(&lookup));
+    decl
+}
+
+// Declare the variables bound by a pattern in the current scope, given their matched values
+//
+// If the pattern did not match, the variables keep the value of the variables they shadow (if of
+// the same type), or get a default value.
+fn bind_pattern(bindings: &[PatternBinding],
+                values: Vec<(String, Value)>,
+                env: &mut Environment<ValueInfo>) {
+    for binding in bindings {
+        let type_ = env.resolve_type(&binding.type_);
+        let matched = values
+            .iter()
+            .find(|&&(ref name, _)| *name == binding.name)
+            .map(|&(_, ref value)| value.clone());
+        let previous = env.get_var(&binding.name)
+            .map(|var| var.get_value().clone())
+            .filter(|value| value.get_type() == type_);
+
+        let value = matched
+            .or(previous)
+            .unwrap_or_else(|| Value::default_of(&type_, env));
+
+        env.bind_var(binding.name.clone(),
+                     BindingInfo::Pattern {
+                         declaration: binding.clone(),
+                         info: ValueInfo(value),
+                     });
+    }
+}
 
-            Value(ref value) => value.clone(),
+// Evaluate an index (which must be of type Integer)
+fn evaluate_index(index: &Expr, env: &mut Environment<ValueInfo>) -> Result<i64, Unwind> {
+    match index.eval(env)? {
+        Value::Integer(index) => Ok(index),
+        other => panic!("{:?} is not of type Integer in index evaluation", other),
+    }
+}
+
+// A step of the path from a variable to an assigned element, with its index evaluated
+enum PlaceStep<'a> {
+    Index(i64, Span),
+    Projection(usize),
+    Field(&'a str),
+}
+
+// Assign a value to a place (see `Expr::is_place`)
+//
+// The indexes of the place are evaluated once, from the variable to the element. The element is
+// then replaced inside the value of the variable, which is assigned back.
+fn assign_place(place: &Expr,
+                value: Value,
+                value_span: Span,
+                env: &mut Environment<ValueInfo>)
+                -> Result<(), Unwind> {
+    let mut steps = Vec::new();
+    let (name, span) = evaluate_place(place, &mut steps, env)?;
+
+    let mut root = eval_variable(name, span, env)?;
+    replace_element(&mut root, &steps, value, value_span)?;
+
+    env.assign(name, root)
+        .map_err(|mut err| {
+                     err.set_span(span);
+                     err.into()
+                 })
+}
+
+// Evaluate the indexes of a place, returning its variable (with its location)
+fn evaluate_place<'a>(place: &'a Expr,
+                      steps: &mut Vec<PlaceStep<'a>>,
+                      env: &mut Environment<ValueInfo>)
+                      -> Result<(&'a str, Span), Unwind> {
+    match *place {
+        Expr::Variable { ref name, span } => Ok((name, span)),
+
+        Expr::Index {
+            ref expr,
+            ref index,
+            index_span,
+            ..
+        } => {
+            let variable = evaluate_place(expr, steps, env)?;
+            steps.push(PlaceStep::Index(evaluate_index(index, env)?, index_span));
+            Ok(variable)
+        }
+
+        Expr::Projection { ref expr, index, .. } => {
+            let variable = evaluate_place(expr, steps, env)?;
+            steps.push(PlaceStep::Projection(index));
+            Ok(variable)
+        }
 
+        Expr::Field { ref expr, ref name, .. } => {
+            let variable = evaluate_place(expr, steps, env)?;
+            steps.push(PlaceStep::Field(name));
+            Ok(variable)
+        }
+
+        _ => unreachable!("Assignment to something that is not a place"),
+    }
+}
+
+// Replace the element at the end of the given steps inside a container
+fn replace_element(container: &mut Value,
+                   steps: &[PlaceStep],
+                   value: Value,
+                   value_span: Span)
+                   -> Result<(), RuntimeError> {
+    match steps.split_first() {
+        None => {
+            *container = value;
+            Ok(())
+        }
+
+        Some((&PlaceStep::Index(index, index_span), rest)) => {
+            let element = if rest.is_empty() {
+                value
+            } else {
+                let mut element = container
+                    .get_element(index)
+                    .map_err(|mut err| {
+                                 err.set_span(index_span);
+                                 err
+                             })?;
+                replace_element(&mut element, rest, value, value_span)?;
+                element
+            };
+
+            container
+                .set_element(index, element)
+                .map_err(|mut err| {
+                             match err {
+                                 RuntimeError::InvalidCharacter(_) => err.set_span(value_span),
+                                 _ => err.set_span(index_span),
+                             }
+                             err
+                         })
+        }
+
+        Some((&PlaceStep::Projection(index), rest)) => {
+            match *container {
+                Value::Tuple { ref mut values, .. } => {
+                    replace_element(&mut values[index], rest, value, value_span)
+                }
+                ref other => panic!("{:?} is not a Tuple in projection evaluation", other),
+            }
+        }
+
+        Some((&PlaceStep::Field(name), rest)) => {
+            match *container {
+                Value::Record { ref mut fields, .. } => {
+                    let field = fields
+                        .iter_mut()
+                        .find(|&&mut (ref field_name, _)| field_name == name)
+                        .unwrap();
+                    replace_element(&mut field.1, rest, value, value_span)
+                }
+                ref other => panic!("{:?} is not a Record in field evaluation", other),
+            }
         }
     }
 }
@@ -235,7 +957,9 @@ impl Evaluate for Expr {
 mod tests {
     use super::Evaluate;
 
+    use ast::Span;
     use env::Environment;
+    use error::*;
     use parser;
     use processing::TypeCheck;
     use type_sys::Value::*;
@@ -249,11 +973,37 @@ mod tests {
             // The type checker might modify the AST a bit before the evaluation
             ast.type_check(&mut Environment::new()).unwrap();
             let res = ast.evaluate(&mut Environment::new());
-            assert_eq!(res, $expected);
+            assert_eq!(res, Ok($expected));
         }
 
     }
 
+    macro_rules! assert_err {
+
+        ( $expr:expr, $err:pat ) => {
+            let mut ast = parser::parse_Expression($expr)
+                .unwrap();
+            ast.type_check(&mut Environment::new()).unwrap();
+            let res = ast.evaluate(&mut Environment::new());
+            assert!(match res {
+                Err($err) => true,
+                _ => false,
+            });
+        };
+
+        ( $expr:expr, $err:pat if $guard:expr ) => {
+            let mut ast = parser::parse_Expression($expr)
+                .unwrap();
+            ast.type_check(&mut Environment::new()).unwrap();
+            let res = ast.evaluate(&mut Environment::new());
+            assert!(match res {
+                Err($err) if $guard => true,
+                _ => false,
+            });
+        };
+
+    }
+
     #[test]
     fn grouping() {
         assert_result!("(1, 2, 3)", Integer(3));
@@ -302,32 +1052,221 @@ mod tests {
     #[test]
     fn function() {
         assert_result!("let
-                          function x(): Bool := true
+                          function x(): Bool := true
+                       in
+                          x()
+                       end",
+                       Bool(true));
+
+        assert_result!("let
+                          function x(): Integer := 2 + 2
+                       in
+                          x()
+                       end",
+                       Integer(4));
+
+        assert_result!("let
+                          function x(y: Integer): Integer := y + 2
+                       in
+                          x(40)
+                       end",
+                       Integer(42));
+
+        assert_result!("let
+                          function fact(n: Integer): Integer := if n then n*fact(n-1) else 1
+                       in
+                          fact(5)
+                       end",
+                       Integer(120));
+    }
+
+    #[test]
+    fn lexical_scoping() {
+        // The body of a function sees the variables of its declaration, not those of the caller
+        assert_result!("let
+                          var x := 1
+                          function f(): Integer := x
+                       in
+                          let
+                             var x := 2
+                          in
+                             f()
+                          end
+                       end",
+                       Integer(1));
+        assert_result!("let
+                          var count := 0
+                          function incr(): Integer := count := count + 1
+                       in
+                          incr(),
+                          let
+                             var count := 40
+                          in
+                             incr()
+                          end,
+                          count
+                       end",
+                       Integer(2));
+        assert_result!("let
+                          function is_even(n: Integer): Bool := if n then is_odd(n - 1) else true
+                          function is_odd(n: Integer): Bool := if n then is_even(n - 1) else false
+                       in
+                          is_even(42)
+                       end",
+                       Bool(true));
+    }
+
+    #[test]
+    fn first_class_function() {
+        assert_result!("let
+                          function double(x: Integer): Integer := x * 2
+                          function apply(f: Function(Integer): Integer, x: Integer): Integer := f(x)
+                       in
+                          apply(double, 21)
+                       end",
+                       Integer(42));
+        assert_result!("let
+                          function apply(f: Function(Integer): Integer, x: Integer): Integer := f(x)
+                       in
+                          apply(function(x: Integer): Integer := x + 1, 41)
+                       end",
+                       Integer(42));
+        assert_result!("let
+                          var f := function(x: Integer, y: Integer): Integer := x * y
+                       in
+                          f(6, 7)
+                       end",
+                       Integer(42));
+
+        // Closures capture the variables visible where they are created
+        assert_result!("let
+                          function make_adder(n: Integer): Function(Integer): Integer :=
+                             function(x: Integer): Integer := x + n
+                       in
+                          let
+                             var add_two := make_adder(2)
+                          in
+                             add_two(40)
+                          end
+                       end",
+                       Integer(42));
+        assert_result!("let
+                          var n := 40
+                          var f := function(x: Integer): Integer := x + n
+                       in
+                          n := 0,
+                          f(2)
+                       end",
+                       Integer(42));
+    }
+
+    #[test]
+    fn generic_function() {
+        assert_result!("let
+                          function id<T>(x: T): T := x
                        in
-                          x()
+                          {id(1), id(\"hello\")}
                        end",
-                       Bool(true));
+                       Tuple {
+                           element_types: vec![Type::Integer, Type::Str],
+                           values: vec![Integer(1), Str("hello".to_string())],
+                       });
+        assert_result!("let
+                          function apply<T>(f: Function(T): T, x: T): T := f(x)
+                       in
+                          apply(function(x: Float): Float := x * 2., 21.)
+                       end",
+                       Float(42.));
 
+        // The values built inside a generic function have the types of the instantiation
         assert_result!("let
-                          function x(): Integer := 2 + 2
+                          function singleton<T>(x: T): Array(T) := [x]
+                          function reset<T>(x: T): T := let in match {y, 1} := {x, 2}, y end
                        in
-                          x()
+                          {singleton(42), reset(1), reset(\"a\")}
                        end",
-                       Integer(4));
+                       Tuple {
+                           element_types: vec![Type::Array(Box::new(Type::Integer)),
+                                               Type::Integer,
+                                               Type::Str],
+                           values: vec![Array {
+                                            element_type: Type::Integer,
+                                            values: vec![Integer(42)],
+                                        },
+                                        Integer(0),
+                                        Str("".to_string())],
+                       });
+        assert_result!("let
+                          function pair<T>(x: T): Function(T): Tuple(T, T) :=
+                             function(y: T): Tuple(T, T) := {x, y}
+                       in
+                          let var f := pair(true) in f(false) end
+                       end",
+                       Tuple {
+                           element_types: vec![Type::Bool, Type::Bool],
+                           values: vec![Bool(true), Bool(false)],
+                       });
 
+        // Nested functions see the type parameters of the enclosing generic function
         assert_result!("let
-                          function x(y: Integer): Integer := y + 2
+                          function wrap<T>(x: T): Array(T) :=
+                             let function make(y: T): Array(T) := [y] in make(x) end
                        in
-                          x(40)
+                          wrap(3)
                        end",
-                       Integer(42));
+                       Array {
+                           element_type: Type::Integer,
+                           values: vec![Integer(3)],
+                       });
+
+        // Without the type checker, the arguments are checked at the call
+        let ast = parser::parse_Expression("let
+                                              function pair<T>(x: T, y: T): Tuple(T, T) := {x, y}
+                                           in
+                                              pair(1, true)
+                                           end")
+            .unwrap();
+        let res = ast.evaluate(&mut Environment::new());
+        assert_eq!(res,
+                   Err(RuntimeError::InvalidArguments(InvalidArgumentsError {
+                                                           func_name: "pair".to_string(),
+                                                           arg_types: vec![Type::Integer,
+                                                                           Type::Bool],
+                                                           span: Span(194, 207),
+                                                       })));
+    }
 
+    #[test]
+    fn inference() {
         assert_result!("let
-                          function fact(n: Integer): Integer := if n then n*fact(n-1) else 1
+                          function fact(n) := if n = 0 then 1 else n * fact(n - 1)
                        in
                           fact(5)
                        end",
                        Integer(120));
+
+        // The functions whose types are not constrained are generic
+        assert_result!("let
+                          function id(x) := x
+                          function apply(f, x) := f(x)
+                          function singleton(x) := [x]
+                       in
+                          {apply(id, 1), apply(singleton, \"a\")}
+                       end",
+                       Tuple {
+                           element_types: vec![Type::Integer, Type::Array(Box::new(Type::Str))],
+                           values: vec![Integer(1),
+                                        Array {
+                                            element_type: Type::Str,
+                                            values: vec![Str("a".to_string())],
+                                        }],
+                       });
+        assert_result!("let
+                          var f := function(x) := x * 2.
+                       in
+                          f(21.)
+                       end",
+                       Float(42.));
     }
 
     #[test]
@@ -352,6 +1291,32 @@ mod tests {
                           x
                        end",
                        Integer(2));
+
+        assert_result!("if true then 1", Void);
+        assert_result!("let
+                          var x := 1
+                       in
+                          if x then x := 2,
+                          if false then x := 3,
+                          x
+                       end",
+                       Integer(2));
+    }
+
+    #[test]
+    fn case_block() {
+        assert_result!("let var x := 0 in case 2 of 1 => 10 | 2 => 20 | x => 30 end end",
+                       Integer(20));
+        assert_result!("let var x := 0 in case 5 of x when x < 3 => 1 | x => x * 2 end end",
+                       Integer(10));
+        assert_result!("let
+                          type Shape = Circle(Float) | Rect(Float, Float)
+                          var a := 0.
+                          var b := 0.
+                       in
+                          case Rect(2., 3.) of Circle(a) => a | Rect(a, b) => a * b end
+                       end",
+                       Float(6.));
     }
 
     #[test]
@@ -407,6 +1372,209 @@ mod tests {
                           x
                        end",
                        Integer(1));
+
+        // The goal is never reached
+        assert_result!("let
+                          var x := 0
+                       in
+                          for var y := 5 downto 0 do x := x * 10 + y,
+                          x
+                       end",
+                       Integer(54321));
+        assert_result!("let
+                          var x := 0
+                       in
+                          for var y := 0 to 10 step 3 do x := x * 10 + y,
+                          x
+                       end",
+                       Integer(369));
+        assert_result!("let
+                          var x := 0
+                       in
+                          for var y := 9 downto 0 step 4 do x := x * 10 + y,
+                          x
+                       end",
+                       Integer(951));
+        assert_result!("let
+                          var x := 0
+                       in
+                          for var y := 0 downto 5 do x := 42,
+                          x
+                       end",
+                       Integer(0));
+
+        // Stops instead of overflowing
+        assert_result!("let
+                          var x := 0
+                       in
+                          for var y := 9223372036854775806 to 9223372036854775807 step 2 do
+                             x := x + 1,
+                          x
+                       end",
+                       Integer(1));
+
+        // The bounds and the step refer to the outer variable, not to the loop one
+        assert_result!("let
+                          var n := 3
+                          var x := 0
+                       in
+                          for var n := 0 to n step n - 2 do x := x * 10 + n,
+                          x
+                       end",
+                       Integer(12));
+
+        assert_err!("for var y := 0 to 10 step 0 do ()",
+                    RuntimeError::NonPositiveStep(NonPositiveStepError { step: 0, .. }));
+        assert_err!("for var y := 10 downto 0 step -1 do ()",
+                    RuntimeError::NonPositiveStep(NonPositiveStepError { step: -1, .. }));
+    }
+
+    #[test]
+    fn for_in_block() {
+        assert_result!("let
+                          var x := 0
+                       in
+                          for y in [1, 2, 3] do x := x * 10 + y,
+                          x
+                       end",
+                       Integer(123));
+        assert_result!(r#"let
+                          var s := ""
+                       in
+                          for c in "abc" do s := c + s,
+                          s
+                       end"#,
+                       Str("cba".to_string()));
+        assert_result!("let
+                          var x := 0
+                       in
+                          for y in Integer[] do x := 42,
+                          x
+                       end",
+                       Integer(0));
+
+        // The iterated value is evaluated once
+        assert_result!("let
+                          var xs := [1, 2]
+                          var n := 0
+                       in
+                          for x in xs do (xs := [1, 2, 3, 4], n := n + 1),
+                          n
+                       end",
+                       Integer(2));
+
+        // The variable is bound again at each iteration, in its own scope
+        assert_result!("let
+                          var x := 7
+                          var sum := 0
+                       in
+                          for x in [1, 2] do (sum := sum + x, x := 10),
+                          sum * 10 + x
+                       end",
+                       Integer(37));
+
+        assert_result!("let
+                          var x := 0
+                       in
+                          for y in [1, 2, 3, 4] do (
+                             if y = 2 then continue else (),
+                             if y = 4 then break else (),
+                             x := x * 10 + y
+                          ),
+                          x
+                       end",
+                       Integer(13));
+    }
+
+    #[test]
+    fn loop_jumps() {
+        // Search loop
+        assert_result!("let
+                          var xs := [3, 1, 4, 1, 5]
+                          var i := 0
+                       in
+                          while true do (
+                             if xs[i] = 4 then break else (),
+                             i := i + 1
+                          ),
+                          i
+                       end",
+                       Integer(2));
+        assert_result!("let
+                          var sum := 0
+                       in
+                          for var i := 0 to 10 do (
+                             if i = 3 then continue else (),
+                             if i = 6 then break else (),
+                             sum := sum + i
+                          ),
+                          sum
+                       end",
+                       Integer(12));
+
+        // Only the innermost loop is stopped
+        assert_result!("let
+                          var n := 0
+                       in
+                          for var i := 0 to 3 do
+                             while true do (
+                                n := n + 1,
+                                break
+                             ),
+                          n
+                       end",
+                       Integer(3));
+
+        // The scopes of the body are left
+        assert_result!("let
+                          var x := 1
+                       in
+                          while true do
+                             let
+                                var x := 2
+                             in
+                                case x of y => let var z := y in break end end
+                             end,
+                          x
+                       end",
+                       Integer(1));
+    }
+
+    #[test]
+    fn return_expr() {
+        assert_result!("let
+                          function find(xs: Array(Integer), x: Integer): Integer :=
+                             (for var i := 0 to 3 do
+                                 let var y := xs[i] in if y = x then return i else () end,
+                              -1)
+                       in
+                          find([3, 1, 4], 4) * 10 + find([3, 1, 4], 5)
+                       end",
+                       Integer(19));
+
+        // Through recursive calls
+        assert_result!("let
+                          function fact(n: Integer): Integer :=
+                             (if n <= 1 then return 1 else (), n * fact(n - 1))
+                       in
+                          fact(5)
+                       end",
+                       Integer(120));
+
+        // Only the innermost function is left, and the scopes of the caller are restored
+        assert_result!("let
+                          var x := 1
+                          var n := 0
+                       in
+                          for var i := 0 to 2 do
+                             let
+                                var f := function(): Integer := let var x := 2 in return x end
+                             in
+                                n := n + f()
+                             end,
+                          x * 10 + n
+                       end",
+                       Integer(14));
     }
 
     #[test]
@@ -449,6 +1617,94 @@ mod tests {
         // currently is.
     }
 
+    #[test]
+    fn integer_ops() {
+        // Floored, unlike `/`
+        assert_result!("7 mod 2", Integer(1));
+        assert_result!("-7 mod 2", Integer(1));
+        assert_result!("7 mod -2", Integer(-1));
+        assert_result!("-7 mod -2", Integer(-1));
+        assert_result!("(-9223372036854775807 - 1) mod -1", Integer(0));
+        assert_result!("7 div 2", Integer(3));
+        assert_result!("-7 div 2", Integer(-4));
+        assert_result!("7 div -2", Integer(-4));
+        assert_result!("-7 div -2", Integer(3));
+        assert_result!("-6 div 2", Integer(-3));
+        assert_result!("-7. mod 2.", Float(1f64));
+
+        assert_result!("2 ** 10", Integer(1024));
+        assert_result!("0 ** 0", Integer(1));
+        assert_result!("2 ** 3 ** 2", Integer(512));
+        assert_result!("-1 ** 9223372036854775807", Integer(-1));
+        assert_result!("2. ** 0.5", Float(2f64.sqrt()));
+
+        assert_result!("12 & 10", Integer(8));
+        assert_result!("12 | 10", Integer(14));
+        assert_result!("12 ^ 10", Integer(6));
+        assert_result!("1 << 4", Integer(16));
+        assert_result!("-1 << 63", Integer(-9223372036854775807 - 1));
+        assert_result!("0 << 100", Integer(0));
+        assert_result!("-16 >> 2", Integer(-4));
+        assert_result!("-16 >> 100", Integer(-1));
+        assert_result!("16 >> 100", Integer(0));
+
+        // Precedences
+        assert_result!("1 + 2 * 3 ** 2 mod 4", Integer(3));
+        assert_result!("1 | 2 ^ 3 & 1 << 2 + 1", Integer(3));
+        assert_result!("1 << 2 = 4", Bool(true));
+    }
+
+    #[test]
+    fn runtime_errors() {
+        assert_err!("1 / 0",
+                    RuntimeError::DivisionByZero(DivisionByZeroError { span: Span(0, 5) }));
+        assert_err!("let
+                        var x := 0
+                     in
+                        42 / x
+                     end",
+                    RuntimeError::DivisionByZero(DivisionByZeroError { .. }));
+        assert_err!("9223372036854775807 + 1",
+                    RuntimeError::Overflow(OverflowError { span: Span(0, 23), .. }));
+        assert_err!("-9223372036854775807 - 2",
+                    RuntimeError::Overflow(OverflowError { .. }));
+        assert_err!("4611686018427387904 * 2",
+                    RuntimeError::Overflow(OverflowError { .. }));
+        assert_err!("-(-9223372036854775807 - 1)",
+                    RuntimeError::Overflow(OverflowError { .. }));
+        assert_err!("1 mod 0",
+                    RuntimeError::DivisionByZero(DivisionByZeroError { span: Span(0, 7) }));
+        assert_err!("1 div 0",
+                    RuntimeError::DivisionByZero(DivisionByZeroError { .. }));
+        assert_err!("(-9223372036854775807 - 1) div -1",
+                    RuntimeError::Overflow(OverflowError { .. }));
+        assert_err!("2 ** 63",
+                    RuntimeError::Overflow(OverflowError { .. }));
+        assert_err!("2 ** 9223372036854775807",
+                    RuntimeError::Overflow(OverflowError { .. }));
+        assert_err!("2 ** -1",
+                    RuntimeError::NegativeOperand(NegativeOperandError { span: Span(0, 7), .. }));
+        assert_err!("1 << 63",
+                    RuntimeError::Overflow(OverflowError { .. }));
+        assert_err!("3 << 62",
+                    RuntimeError::Overflow(OverflowError { .. }));
+        assert_err!("1 << 64",
+                    RuntimeError::Overflow(OverflowError { .. }));
+        assert_err!("1 << -1",
+                    RuntimeError::NegativeOperand(NegativeOperandError { .. }));
+        assert_err!("1 >> -1",
+                    RuntimeError::NegativeOperand(NegativeOperandError { .. }));
+
+        // Errors inside a function call are reported where they happened
+        assert_err!("let
+                        function f(x: Integer): Integer := 1 / x
+                     in
+                        f(0)
+                     end",
+                    RuntimeError::DivisionByZero(DivisionByZeroError { span })
+                    if span != Span(0, 0));
+    }
+
     #[test]
     fn unary_ops() {
         assert_result!("-2", Integer(-2));
@@ -463,6 +1719,30 @@ mod tests {
         assert_result!("+0.", Float(0f64));
     }
 
+    #[test]
+    fn logical_ops() {
+        assert_result!("true and false", Bool(false));
+        assert_result!("true and 2", Bool(true));
+        assert_result!("0 or 0.", Bool(false));
+        assert_result!("Integer[] or [1]", Bool(true));
+        assert_result!("not 0", Bool(true));
+        assert_result!("not not 2", Bool(true));
+        assert_result!("not 1 = 2 and 3 > 4 or 5 < 6", Bool(true));
+
+        // Short-circuit evaluation
+        assert_result!("false and 1 / 0 = 1", Bool(false));
+        assert_result!("true or 1 / 0 = 1", Bool(true));
+        assert_result!("let
+                          var n := 0
+                          function count(): Bool := (n := n + 1, true)
+                       in
+                          count() or count(),
+                          count() and count(),
+                          n
+                       end",
+                       Integer(3));
+    }
+
     #[test]
     fn cast() {
         assert_result!("1 as Float", Float(1f64));
@@ -507,6 +1787,50 @@ mod tests {
                                             values: vec![Float(4f64), Integer(3), Bool(true)],
                                         }],
                        });
+
+        assert_result!(r#""42" as Integer"#, Integer(42));
+        assert_result!(r#""-42" as Integer"#, Integer(-42));
+        assert_result!(r#""4.5" as Float"#, Float(4.5f64));
+        assert_result!(r#""true" as Bool"#, Bool(true));
+        assert_result!(r#""false" as Bool"#, Bool(false));
+
+        assert_err!(r#""4x2" as Integer"#,
+                    RuntimeError::UnparsableStr(UnparsableStrError { span: Span(0, 5), .. }));
+        assert_err!("\"\" as Float",
+                    RuntimeError::UnparsableStr(UnparsableStrError { .. }));
+        assert_err!(r#""True" as Bool"#,
+                    RuntimeError::UnparsableStr(UnparsableStrError { .. }));
+        assert_err!(r#"["1", "a"] as Array(Integer)"#,
+                    RuntimeError::UnparsableStr(UnparsableStrError { ref value, .. })
+                    if value == "a");
+
+        assert_result!(r#""42" as? Integer"#,
+                       Tuple {
+                           element_types: vec![Type::Bool, Type::Integer],
+                           values: vec![Bool(true), Integer(42)],
+                       });
+        assert_result!(r#""4x2" as? Integer"#,
+                       Tuple {
+                           element_types: vec![Type::Bool, Type::Integer],
+                           values: vec![Bool(false), Integer(0)],
+                       });
+        assert_result!(r#"["1", "a"] as? Array(Integer)"#,
+                       Tuple {
+                           element_types: vec![Type::Bool, Type::Array(Box::new(Type::Integer))],
+                           values: vec![Bool(false),
+                                        Array {
+                                            element_type: Type::Integer,
+                                            values: vec![],
+                                        }],
+                       });
+    }
+
+    #[test]
+    fn interpolation() {
+        assert_result!(r#"let var x := 42 in "x = ${x}, y = ${x / 4 + 0.5}" end"#,
+                       Str("x = 42, y = 10.5".to_string()));
+        assert_result!(r#""${true}${"a${1}b"}${1 < 0}""#, Str("truea1bfalse".to_string()));
+        assert_result!(r#""\${x} ${ {1, 2}.1 }""#, Str("${x} 2".to_string()));
     }
 
     #[test]
@@ -561,6 +1885,223 @@ mod tests {
                        });
     }
 
+    #[test]
+    fn index() {
+        assert_result!("[1, 2, 3][0]", Integer(1));
+        assert_result!("[1, 2, 3][1 + 1]", Integer(3));
+        assert_result!("[[1], [2, 3]][1][0]", Integer(2));
+        assert_result!(r#""hello"[1]"#, Str("e".to_string()));
+        assert_result!(r#""héllo"[1]"#, Str("é".to_string()));
+
+        assert_result!("let var x := [1, 2, 3] in x[1] := 42, x end",
+                       Array {
+                           element_type: Type::Integer,
+                           values: vec![Integer(1), Integer(42), Integer(3)],
+                       });
+        assert_result!("let var x := [[1], [2, 3]] in x[1][0] := 42, x[1] end",
+                       Array {
+                           element_type: Type::Integer,
+                           values: vec![Integer(42), Integer(3)],
+                       });
+        assert_result!(r#"let var x := "hello" in x[0] := "j", x end"#,
+                       Str("jello".to_string()));
+        assert_result!("let var x := [1] in x[0] := 42 end", Integer(42));
+        assert_result!(r#"let var x := ["ab"] in x[0][1] := "c", x[0] end"#,
+                       Str("ac".to_string()));
+
+        // The indexes of the assigned element are evaluated once
+        assert_result!("let
+                          var x := [[1, 2], [3, 4]]
+                          var i := 0
+                       in
+                          x[i := i + 1][0] := 42,
+                          {i, x[1][0]}
+                       end",
+                       Tuple {
+                           element_types: vec![Type::Integer, Type::Integer],
+                           values: vec![Integer(1), Integer(42)],
+                       });
+
+        assert_err!("[1, 2, 3][3]",
+                    RuntimeError::IndexOutOfBounds(IndexOutOfBoundsError {
+                        index: 3,
+                        length: 3,
+                        span: Span(10, 11),
+                    }));
+        assert_err!("[1, 2, 3][-1]",
+                    RuntimeError::IndexOutOfBounds(IndexOutOfBoundsError { index: -1, .. }));
+        assert_err!(r#""hello"[5]"#,
+                    RuntimeError::IndexOutOfBounds(IndexOutOfBoundsError { index: 5, .. }));
+        assert_err!("let var x := [[1], [2, 3]] in x[0][1] := 42 end",
+                    RuntimeError::IndexOutOfBounds(IndexOutOfBoundsError {
+                        index: 1,
+                        length: 1,
+                        ..
+                    }));
+        assert_err!(r#"let var x := "hello" in x[0] := "ab" end"#,
+                    RuntimeError::InvalidCharacter(InvalidCharacterError {
+                        ref value,
+                        span: Span(32, 36),
+                    })
+                    if value == "ab");
+        assert_err!(r#"let var x := "hello" in x[0] := "" end"#,
+                    RuntimeError::InvalidCharacter(InvalidCharacterError { .. }));
+    }
+
+    #[test]
+    fn projection() {
+        assert_result!("{1, true}.0", Integer(1));
+        assert_result!("{1, true}.1", Bool(true));
+        assert_result!("{1, {2., [3]}}.1.1[0]", Integer(3));
+        assert_result!("let var t := {1, true} in t.0 := 42, t.0 end", Integer(42));
+        assert_result!("let var t := {1, {2., [3]}} in t.1.1[0] := 42, t end",
+                       Tuple {
+                           element_types: vec![Type::Integer,
+                                               Type::Tuple(vec![Type::Float,
+                                                                Type::Array(Box::new(Type::Integer))])],
+                           values: vec![Integer(1),
+                                        Tuple {
+                                            element_types: vec![Type::Float,
+                                                                Type::Array(Box::new(Type::Integer))],
+                                            values: vec![Float(2f64),
+                                                         Array {
+                                                             element_type: Type::Integer,
+                                                             values: vec![Integer(42)],
+                                                         }],
+                                        }],
+                       });
+    }
+
+    #[test]
+    fn record() {
+        assert_result!("let
+                          type Point = {x: Integer, y: Integer}
+                       in
+                          Point {y = 2, x = 1}
+                       end",
+                       Record {
+                           type_name: "Point".to_string(),
+                           fields: vec![("x".to_string(), Integer(1)),
+                                        ("y".to_string(), Integer(2))],
+                       });
+        assert_result!("let
+                          type Point = {x: Integer, y: Integer}
+                          var p := Point {x = 1, y = 2}
+                       in
+                          p.y
+                       end",
+                       Integer(2));
+        assert_result!("let
+                          type Point = {x: Integer, y: Integer}
+                          type Segment = {a: Point, b: Point}
+                          var s := Segment {a = Point {x = 0, y = 0}, b = Point {x = 1, y = 2}}
+                       in
+                          s.b.x := 42,
+                          s.b
+                       end",
+                       Record {
+                           type_name: "Point".to_string(),
+                           fields: vec![("x".to_string(), Integer(42)),
+                                        ("y".to_string(), Integer(2))],
+                       });
+        assert_result!(r#"let
+                             type Point = {x: Integer, y: Integer}
+                          in
+                             {"a", Point {x = 1, y = 2}} as? Tuple(Integer, Point)
+                          end"#,
+                       Tuple {
+                           element_types: vec![Type::Bool,
+                                               Type::Tuple(vec![Type::Integer,
+                                                                Type::Named("Point".to_string())])],
+                           values: vec![Bool(false),
+                                        Tuple {
+                                            element_types: vec![Type::Integer,
+                                                                Type::Named("Point".to_string())],
+                                            values: vec![Integer(0),
+                                                         Record {
+                                                             type_name: "Point".to_string(),
+                                                             fields: vec![("x".to_string(),
+                                                                           Integer(0)),
+                                                                          ("y".to_string(),
+                                                                           Integer(0))],
+                                                         }],
+                                        }],
+                       });
+    }
+
+    #[test]
+    fn sum_type() {
+        assert_result!("let
+                          type Shape = Circle(Float) | Rect(Float, Float) | Empty
+                       in
+                          Rect(1., 2.)
+                       end",
+                       Variant {
+                           type_name: "Shape".to_string(),
+                           tag: "Rect".to_string(),
+                           values: vec![Float(1.), Float(2.)],
+                       });
+        assert_result!("let
+                          type Shape = Circle(Float) | Rect(Float, Float) | Empty
+                       in
+                          Empty
+                       end",
+                       Variant {
+                           type_name: "Shape".to_string(),
+                           tag: "Empty".to_string(),
+                           values: vec![],
+                       });
+        assert_result!(r#"let
+                             type List = Cons(Integer, List) | Nil
+                          in
+                             {"a", Nil} as? Tuple(Integer, List)
+                          end"#,
+                       Tuple {
+                           element_types: vec![Type::Bool,
+                                               Type::Tuple(vec![Type::Integer,
+                                                                Type::Named("List".to_string())])],
+                           values: vec![Bool(false),
+                                        Tuple {
+                                            element_types: vec![Type::Integer,
+                                                                Type::Named("List".to_string())],
+                                            values: vec![Integer(0),
+                                                         Variant {
+                                                             type_name: "List".to_string(),
+                                                             tag: "Nil".to_string(),
+                                                             values: vec![],
+                                                         }],
+                                        }],
+                       });
+
+        // The default value of a recursive type is finite
+        assert_result!(r#"let
+                             type Tree = Node(Tree, Tree) | Leaf(Integer)
+                          in
+                             ({"a", Leaf(1)} as? Tuple(Integer, Tree)).1.1
+                          end"#,
+                       Variant {
+                           type_name: "Tree".to_string(),
+                           tag: "Leaf".to_string(),
+                           values: vec![Integer(0)],
+                       });
+        assert_result!(r#"let
+                             type Stream = {head: Integer, tail: Function(): Stream}
+                             function ones(): Stream := Stream {head = 1, tail = ones}
+                          in
+                             let
+                                var s := ({"a", ones()} as? Tuple(Integer, Stream)).1.1
+                                var tail := s.tail
+                                var next := tail()
+                             in
+                                {s.head, next.head}
+                             end
+                          end"#,
+                       Tuple {
+                           element_types: vec![Type::Integer, Type::Integer],
+                           values: vec![Integer(0), Integer(0)],
+                       });
+    }
+
     #[test]
     fn tuple() {
         assert_result!("{}",
@@ -0,0 +1,520 @@
+//! The module where the warnings are looked for
+//!
+//! The lint pass flags suspicious, but valid, code: variables and arguments never read, bindings
+//! shadowing outer ones, constant conditions, and values computed for nothing. It must run on a
+//! type checked AST, since it relies on the variables bound by the patterns and on the types of
+//! the expressions, which are written by the type checker.
+
+use ast::*;
+use env::{BindingInfo, Environment, UsageInfo};
+use error::{ConstantConditionWarning, DeadLoopWarning, Hint, IgnoredValueWarning,
+            ShadowedBindingWarning, UnusedBindingWarning, Warning};
+use type_sys::Type;
+
+/// That trait that must be implemented by part of the AST to look for warnings
+pub trait Lint {
+    /// Returns the warnings, in the order of the source code
+    fn lint(&self, env: &mut Environment<UsageInfo>) -> Vec<Warning>;
+}
+
+impl Lint for Exprs {
+    fn lint(&self, env: &mut Environment<UsageInfo>) -> Vec<Warning> {
+        lint_outermost(self, env)
+    }
+}
+
+impl Lint for Expr {
+    fn lint(&self, env: &mut Environment<UsageInfo>) -> Vec<Warning> {
+        lint_outermost(self, env)
+    }
+}
+
+// Look for the warnings of a whole part of the AST, and sort them
+fn lint_outermost<N: LintNode>(node: &N, env: &mut Environment<UsageInfo>) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    node.lint_node(env, &mut warnings);
+
+    // The unused bindings are only known when leaving their scope
+    warnings.sort_by_key(|warning| warning.hints()[0].span.0);
+    warnings
+}
+
+// Look for the warnings of a part of the AST
+trait LintNode {
+    fn lint_node(&self, env: &mut Environment<UsageInfo>, warnings: &mut Vec<Warning>);
+}
+
+impl LintNode for Exprs {
+    fn lint_node(&self, env: &mut Environment<UsageInfo>, warnings: &mut Vec<Warning>) {
+        for (i, &(ref expr, span)) in self.exprs.iter().enumerate() {
+            // The value of the last expression is the value of the whole
+            if i + 1 < self.exprs.len() && is_ignored_value(expr, self.types.get(i)) {
+                warnings.push(IgnoredValueWarning::new(span).into());
+            }
+
+            expr.lint_node(env, warnings);
+        }
+    }
+}
+
+impl LintNode for Expr {
+    fn lint_node(&self, env: &mut Environment<UsageInfo>, warnings: &mut Vec<Warning>) {
+        use ast::Expr::*;
+
+        match *self {
+            Grouping(ref exprs) => exprs.lint_node(env, warnings),
+
+            Let(_, ref bindings, ref function_decls, ref exprs) => {
+                env.enter_scope();
+
+                for binding in bindings {
+                    binding.value.lint_node(env, warnings);
+                    declare(BindingInfo::Variable {
+                                declaration: binding.clone(),
+                                info: UsageInfo(false),
+                            },
+                            env,
+                            warnings);
+                }
+
+                for function_decl in function_decls {
+                    env.declare_func(function_decl.clone())
+                        .expect("Function declared twice in a type checked let");
+                }
+
+                for function_decl in function_decls {
+                    function_decl.lint_node(env, warnings);
+                }
+
+                exprs.lint_node(env, warnings);
+
+                leave_scope(env, warnings);
+            }
+
+            Assign { ref value, .. } => value.lint_node(env, warnings),
+
+            ElementAssign {
+                ref target,
+                ref value,
+                ..
+            } => {
+                target.lint_node(env, warnings);
+                value.lint_node(env, warnings);
+            }
+
+            PatternMatch {
+                ref rhs,
+                ref bindings,
+                ..
+            } => {
+                rhs.lint_node(env, warnings);
+                bind_pattern(bindings, env);
+            }
+
+            Function { ref name, ref args, .. } => {
+                set_used(name, env);
+
+                for &(ref arg, _) in args {
+                    arg.lint_node(env, warnings);
+                }
+            }
+
+            If {
+                ref cond,
+                cond_span,
+                ref true_branch,
+                true_branch_span,
+                ref false_branch,
+                ref true_branch_type,
+            } => {
+                if let Value(ref value) = **cond {
+                    warnings.push(ConstantConditionWarning::new(value.truthy(), cond_span).into());
+                }
+
+                // Without an `else`, the value of the true branch is thrown away
+                if false_branch.is_none() &&
+                   is_ignored_value(true_branch, true_branch_type.as_ref()) {
+                    warnings.push(IgnoredValueWarning::new(true_branch_span).into());
+                }
+
+                cond.lint_node(env, warnings);
+                true_branch.lint_node(env, warnings);
+                if let Some((ref false_branch, _)) = *false_branch {
+                    false_branch.lint_node(env, warnings);
+                }
+            }
+
+            Case {
+                ref expr,
+                ref arms,
+                ..
+            } => {
+                expr.lint_node(env, warnings);
+
+                for arm in arms {
+                    env.enter_scope();
+                    bind_pattern(&arm.bindings, env);
+
+                    if let Some((ref guard, _)) = arm.guard {
+                        guard.lint_node(env, warnings);
+                    }
+
+                    arm.body.lint_node(env, warnings);
+
+                    leave_scope(env, warnings);
+                }
+            }
+
+            While {
+                ref cond,
+                cond_span,
+                ref expr,
+            } => {
+                match **cond {
+                    Value(ref value) if !value.truthy() => {
+                        warnings.push(DeadLoopWarning::new(cond_span).into());
+                    }
+                    _ => {}
+                }
+
+                cond.lint_node(env, warnings);
+                expr.lint_node(env, warnings);
+            }
+
+            For {
+                ref binding,
+                ref goal,
+                ref step,
+                ref expr,
+                ..
+            } => {
+                env.enter_scope();
+
+                binding.value.lint_node(env, warnings);
+                goal.lint_node(env, warnings);
+                if let Some((ref step, _)) = *step {
+                    step.lint_node(env, warnings);
+                }
+
+                // Iterating only to repeat the body is fine
+                declare(BindingInfo::Variable {
+                            declaration: (**binding).clone(),
+                            info: UsageInfo(true),
+                        },
+                        env,
+                        warnings);
+
+                expr.lint_node(env, warnings);
+
+                leave_scope(env, warnings);
+            }
+
+            ForIn {
+                ref name,
+                name_span,
+                ref iterable,
+                ref expr,
+                ..
+            } => {
+                iterable.lint_node(env, warnings);
+
+                env.enter_scope();
+
+                // Like a variable bound by a pattern (its type does not matter here)
+                bind_pattern(&[PatternBinding {
+                                   name: name.clone(),
+                                   type_: Type::Void,
+                                   span: name_span,
+                               }],
+                             env);
+
+                expr.lint_node(env, warnings);
+
+                leave_scope(env, warnings);
+            }
+
+            BinaryOp {
+                ref lhs,
+                ref rhs,
+                ..
+            } |
+            LogicalOp {
+                ref lhs,
+                ref rhs,
+                ..
+            } => {
+                lhs.lint_node(env, warnings);
+                rhs.lint_node(env, warnings);
+            }
+
+            UnaryOp { ref expr, .. } |
+            Cast { ref expr, .. } |
+            TryCast { ref expr, .. } |
+            Projection { ref expr, .. } |
+            Field { ref expr, .. } => expr.lint_node(env, warnings),
+
+            Index {
+                ref expr,
+                ref index,
+                ..
+            } => {
+                expr.lint_node(env, warnings);
+                index.lint_node(env, warnings);
+            }
+
+            Variable { ref name, .. } => set_used(name, env),
+
+            Array { ref values, .. } => {
+                for &(ref value, _) in values {
+                    value.lint_node(env, warnings);
+                }
+            }
+
+            Tuple(ref exprs) => {
+                for expr in exprs {
+                    expr.lint_node(env, warnings);
+                }
+            }
+
+            Record { ref fields, .. } => {
+                for field in fields {
+                    field.value.lint_node(env, warnings);
+                }
+            }
+
+            Lambda(ref decl) => decl.lint_node(env, warnings),
+
+            Interpolation(ref parts) => {
+                for part in parts {
+                    if let InterpolationPart::Expr(ref expr, _) = *part {
+                        expr.lint_node(env, warnings);
+                    }
+                }
+            }
+
+            Return { ref value, .. } => value.lint_node(env, warnings),
+
+            Break(_) | Continue(_) | Value(_) => {}
+
+            Wildcard(_) | Rest { .. } => panic!("Pattern outside of a pattern match"),
+        }
+    }
+}
+
+impl LintNode for FunctionDecl {
+    fn lint_node(&self, env: &mut Environment<UsageInfo>, warnings: &mut Vec<Warning>) {
+        env.enter_scope();
+
+        for arg in &self.args {
+            declare(BindingInfo::Argument {
+                        declaration: arg.clone(),
+                        info: UsageInfo(false),
+                    },
+                    env,
+                    warnings);
+        }
+
+        self.body.lint_node(env, warnings);
+
+        leave_scope(env, warnings);
+    }
+}
+
+// Declare a variable or an argument in the current scope, warning if it shadows an outer one
+fn declare(info: BindingInfo<UsageInfo>,
+           env: &mut Environment<UsageInfo>,
+           warnings: &mut Vec<Warning>) {
+    let declaration = info.get_declaration();
+
+    if let Some(outer) = env.get_var(declaration.name()) {
+        warnings.push(ShadowedBindingWarning::new(declaration.clone(), outer.get_declaration())
+                          .into());
+    }
+
+    env.bind_var(declaration.name().clone(), info);
+}
+
+// Bind the variables of a pattern in the current scope
+//
+// Binding a variable only to match its shape is fine, so they are not checked.
+fn bind_pattern(bindings: &[PatternBinding], env: &mut Environment<UsageInfo>) {
+    for binding in bindings {
+        env.bind_var(binding.name.clone(),
+                     BindingInfo::Pattern {
+                         declaration: binding.clone(),
+                         info: UsageInfo(true),
+                     });
+    }
+}
+
+// Remember that a variable is read, unless it is shadowed by a function (see the
+// `Environment::get_callable` method)
+fn set_used(name: &str, env: &mut Environment<UsageInfo>) {
+    for scope in env.scopes.iter_mut() {
+        if let Some(binding) = scope.variables.get_mut(name) {
+            binding.set_used();
+            return;
+        }
+
+        if scope.functions.contains_key(name) {
+            return;
+        }
+    }
+}
+
+// Leave the current scope, warning about the variables and arguments declared there but never
+// read
+fn leave_scope(env: &mut Environment<UsageInfo>, warnings: &mut Vec<Warning>) {
+    for binding in env.scopes
+            .front()
+            .expect("Tried to leave a scope when not in a scope")
+            .variables
+            .values() {
+        if !binding.is_used() {
+            warnings.push(UnusedBindingWarning::new(binding.get_declaration()).into());
+        }
+    }
+
+    env.leave_scope();
+}
+
+// Tells if the value of an expression is thrown away for nothing, from the type written by the
+// type checker
+//
+// Void values and unknown types (like the type of a jump) are fine, as are the values of
+// assignments and pattern matches, which are only by-products.
+fn is_ignored_value(expr: &Expr, type_: Option<&Type>) -> bool {
+    match *expr {
+        Expr::Assign { .. } |
+        Expr::ElementAssign { .. } |
+        Expr::PatternMatch { .. } => false,
+        _ => {
+            match type_ {
+                Some(&Type::Void) | Some(&Type::Var(_)) | None => false,
+                Some(_) => true,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lint;
+
+    use ast::Span;
+    use env::Environment;
+    use error::*;
+    use parser;
+    use processing::TypeCheck;
+
+    fn lint(input: &str) -> Vec<Warning> {
+        let mut ast = parser::parse_Expression(input).unwrap();
+        ast.type_check(&mut Environment::new()).unwrap();
+        ast.lint(&mut Environment::new())
+    }
+
+    #[test]
+    fn unused_binding() {
+        assert_eq!(lint("let var x := 1 var y := 2 in y end").len(), 1);
+        assert!(match lint("let var x := 1 in 2 end")[..] {
+                    [Warning::UnusedBinding(UnusedBindingWarning { ref declaration })] => {
+                        declaration.name() == "x"
+                    }
+                    _ => false,
+                });
+        assert!(match lint("let function f(x: Integer): Integer := 1 in f(1) end")[..] {
+                    [Warning::UnusedBinding(UnusedBindingWarning { ref declaration })] => {
+                        declaration.name() == "x"
+                    }
+                    _ => false,
+                });
+
+        // Assigning is not reading
+        assert_eq!(lint("let var x := 1 in x := 2 end").len(), 1);
+
+        // Read by a function, or called
+        assert!(lint("let var x := 1 function f() := x in f() end").is_empty());
+        assert!(lint("let var f := function(x: Integer) := x in f(1) end").is_empty());
+        assert!(lint("let var x := 1 in for var i := 1 to x do print(\"a\") end").is_empty());
+        assert!(lint("let var xs := [1] in for x in xs do print(\"a\") end").is_empty());
+        assert_eq!(lint("let var x := 1 in for x in [2] do print(x) end").len(), 1);
+        assert!(lint("let var x := {1, 2} in case x of {a, _} => a end end").is_empty());
+        assert!(lint("let var x := 1 in \"x = ${x}\" end").is_empty());
+    }
+
+    #[test]
+    fn shadowed_binding() {
+        assert!(match lint("let var x := 1 in let var x := x in x end end")[..] {
+                    [Warning::ShadowedBinding(ShadowedBindingWarning { ref declaration,
+                                                                       ref shadowed })] => {
+                        declaration.span() != shadowed.span()
+                    }
+                    _ => false,
+                });
+        assert_eq!(lint("let var x := 1 function f(x: Integer) := x in f(x) end").len(),
+                   1);
+        assert!(lint("let var x := 1 in let var y := x in y end end").is_empty());
+    }
+
+    #[test]
+    fn constant_condition() {
+        assert!(match lint("if true then 1 else 2")[..] {
+                    [Warning::ConstantCondition(ConstantConditionWarning { value, .. })] => value,
+                    _ => false,
+                });
+        assert!(match lint("if 0 then 1 else 2")[..] {
+                    [Warning::ConstantCondition(ConstantConditionWarning { value, .. })] => !value,
+                    _ => false,
+                });
+        assert!(lint("let var x := true in if x then 1 else 2 end").is_empty());
+
+        assert!(match lint("while false do print(1)")[..] {
+                    [Warning::DeadLoop(_)] => true,
+                    _ => false,
+                });
+        assert!(lint("let var x := 1 in while x do x := 0 end").is_empty());
+    }
+
+    #[test]
+    fn ignored_value() {
+        assert!(match lint("(1 + 2, print(3), 4)")[..] {
+                    [Warning::IgnoredValue(IgnoredValueWarning { span })] => span == Span(1, 6),
+                    _ => false,
+                });
+        assert!(lint("let var x := [1] in x[0] := 2, x end").is_empty());
+
+        // Without an `else`, the value of the true branch is thrown away
+        assert!(match lint("let var x := 1 in if x then x + 1 end")[..] {
+                    [Warning::IgnoredValue(IgnoredValueWarning { span })] => span == Span(28, 33),
+                    _ => false,
+                });
+        assert!(lint("let var x := 1 in if x then x := 2 end").is_empty());
+        assert_eq!(lint(r#"("${1 + 2}", "${(print(3), 4)}", 5)"#).len(), 2);
+
+        // Whatever computes the value
+        let warnings = lint("let
+                               var c := 1
+                               function f(x: Integer): Integer := (print(x), x)
+                            in
+                               f(1), if c then f(2), f(3)
+                            end");
+        assert_eq!(warnings.len(), 2);
+        assert!(match warnings[..] {
+                    [Warning::IgnoredValue(IgnoredValueWarning { span: first }),
+                     Warning::IgnoredValue(IgnoredValueWarning { span: second })] => {
+                        first == Span(188, 192) && second == Span(204, 208)
+                    }
+                    _ => false,
+                });
+        assert!(lint("let var x := 1 in while x do (x := 0, match {y} := {x}, break, y) end")
+                    .is_empty());
+
+        // In the order of the source code
+        let warnings = lint("let var x := 1 in 1, 2 end");
+        assert_eq!(warnings.len(), 2);
+        assert!(match warnings[0] {
+                    Warning::UnusedBinding(_) => true,
+                    _ => false,
+                });
+    }
+}
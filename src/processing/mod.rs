@@ -7,11 +7,13 @@
 //! [`ast::Exprs`]: ../ast/struct.Exprs.html
 
 mod evaluate;
+mod lint;
 mod print;
 mod type_check;
 pub mod pattern_match_check;
 pub mod pattern_match;
 
 pub use self::evaluate::*;
+pub use self::lint::*;
 pub use self::print::*;
 pub use self::type_check::*;
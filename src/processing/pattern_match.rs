@@ -2,43 +2,96 @@
 
 use ast::*;
 use env::{Environment, ValueInfo};
-use processing::Evaluate;
+use error::RuntimeError;
 use type_sys;
 
 /// That trait that must be implemented by part of the AST for pattern matching
 pub trait PatternMatch {
-    fn pattern_match(&self, rhs: &type_sys::Value, env: &mut Environment<ValueInfo>) -> bool;
+    /// Match a value against the pattern
+    ///
+    /// The values of the variables of the pattern are pushed to the given bindings, without being
+    /// declared: this is up to the caller, once the whole pattern matched.
+    fn pattern_match(&self,
+                     rhs: &type_sys::Value,
+                     bindings: &mut Vec<(String, type_sys::Value)>,
+                     env: &Environment<ValueInfo>)
+                     -> Result<bool, RuntimeError>;
 }
 
 impl PatternMatch for Expr {
-    fn pattern_match(&self, rhs: &type_sys::Value, env: &mut Environment<ValueInfo>) -> bool {
+    fn pattern_match(&self,
+                     rhs: &type_sys::Value,
+                     bindings: &mut Vec<(String, type_sys::Value)>,
+                     env: &Environment<ValueInfo>)
+                     -> Result<bool, RuntimeError> {
         use ast::Expr::*;
 
         match *self {
-            // This should not set the value but pin the value to ensure subsequent use of the same
-            // variable must have the same value (Prolog / Erlang style), and then assign variables
-            // if everything matches, not saving the environment and restore it if the pattern does
-            // not match (NOT elegant), but hey, time is missing, I even file like I won't finish
-            // this sent...
-            Variable { ref name, .. } => {
-                let assign = Expr::Assign {
-                    name: name.clone(),
-                    name_span: Span(0, 0),
-                    value: Box::new(Value(rhs.clone())),
-                    value_span: Span(0, 0),
-                };
-
-                assign.evaluate(env);
-                true
+            Wildcard(_) => Ok(true),
+
+            Variable { ref name, .. } if env.is_variant_pattern(name) => {
+                if let type_sys::Value::Variant { ref tag, .. } = *rhs {
+                    Ok(tag == name)
+                } else {
+                    panic!("Wrong pattern");
+                }
             }
 
+            // A variable used several times is pinned to its first value (Prolog / Erlang style)
+            Variable { ref name, .. } => bind(name, rhs, bindings),
+
             Array { ref values, .. } => {
-                if let type_sys::Value::Array{ values: ref candidate_values, .. } = *rhs {
-                    values.len() == candidate_values.len() &&
-                    values
+                if let type_sys::Value::Array {
+                           values: ref candidate_values,
+                           ref element_type,
+                       } = *rhs {
+                    let rest = values
                         .iter()
-                        .zip(candidate_values)
-                        .all(|(&(ref value, _), candidate)| value.pattern_match(&*candidate, env))
+                        .position(|&(ref value, _)| if let Rest { .. } = **value {
+                                      true
+                                  } else {
+                                      false
+                                  });
+
+                    let (prefix, suffix) = match rest {
+                        Some(rest) => {
+                            if candidate_values.len() < values.len() - 1 {
+                                return Ok(false);
+                            }
+                            (&values[..rest], &values[rest + 1..])
+                        }
+                        None => {
+                            if candidate_values.len() != values.len() {
+                                return Ok(false);
+                            }
+                            (&values[..], &values[values.len()..])
+                        }
+                    };
+
+                    let suffix_start = candidate_values.len() - suffix.len();
+
+                    for (&(ref value, _), candidate) in
+                        prefix
+                            .iter()
+                            .zip(&candidate_values[..prefix.len()])
+                            .chain(suffix.iter().zip(&candidate_values[suffix_start..])) {
+                        if !value.pattern_match(candidate, bindings, env)? {
+                            return Ok(false);
+                        }
+                    }
+
+                    if let Some(rest) = rest {
+                        if let Rest { name: Some(ref name), .. } = *values[rest].0 {
+                            let rest_value = type_sys::Value::Array {
+                                element_type: element_type.clone(),
+                                values: candidate_values[prefix.len()..suffix_start].to_vec(),
+                            };
+
+                            return bind(name, &rest_value, bindings);
+                        }
+                    }
+
+                    Ok(true)
                 } else {
                     panic!("Wrong pattern");
                 }
@@ -46,18 +99,63 @@ impl PatternMatch for Expr {
 
             Tuple(ref values) => {
                 if let type_sys::Value::Tuple { values: ref candidate_values, .. } = *rhs {
-                    values.len() == candidate_values.len() &&
-                    values
-                        .iter()
-                        .zip(candidate_values)
-                        .all(|(value, candidate)| value.pattern_match(&*candidate, env))
+                    if values.len() != candidate_values.len() {
+                        return Ok(false);
+                    }
+
+                    for (value, candidate) in values.iter().zip(candidate_values) {
+                        if !value.pattern_match(candidate, bindings, env)? {
+                            return Ok(false);
+                        }
+                    }
+
+                    Ok(true)
                 } else {
                     panic!("Wrong pattern");
                 }
             }
 
             Value(ref value) => {
-                value == rhs
+                Ok(value == rhs)
+            }
+
+            Record { ref fields, .. } => {
+                if let type_sys::Value::Record { fields: ref candidate_fields, .. } = *rhs {
+                    for field in fields {
+                        let candidate = &candidate_fields
+                                             .iter()
+                                             .find(|&&(ref name, _)| *name == field.name)
+                                             .unwrap()
+                                             .1;
+
+                        if !field.value.pattern_match(candidate, bindings, env)? {
+                            return Ok(false);
+                        }
+                    }
+
+                    Ok(true)
+                } else {
+                    panic!("Wrong pattern");
+                }
+            }
+
+            // A variant pattern
+            Function { ref name, ref args, .. } => {
+                if let type_sys::Value::Variant { ref tag, ref values, .. } = *rhs {
+                    if tag != name {
+                        return Ok(false);
+                    }
+
+                    for (&(ref arg, _), candidate) in args.iter().zip(values) {
+                        if !arg.pattern_match(candidate, bindings, env)? {
+                            return Ok(false);
+                        }
+                    }
+
+                    Ok(true)
+                } else {
+                    panic!("Wrong pattern");
+                }
             }
 
             _ => panic!("Forbidden pattern"),
@@ -65,6 +163,19 @@ impl PatternMatch for Expr {
     }
 }
 
+// Bind a variable of a pattern, or check that it is bound to the same value if it is already
+fn bind(name: &str,
+        value: &type_sys::Value,
+        bindings: &mut Vec<(String, type_sys::Value)>)
+        -> Result<bool, RuntimeError> {
+    if let Some(&(_, ref previous)) = bindings.iter().find(|&&(ref bound, _)| bound == name) {
+        return Ok(previous == value);
+    }
+
+    bindings.push((name.to_string(), value.clone()));
+    Ok(true)
+}
+
 #[cfg(test)]
 mod tests {
     use env::Environment;
@@ -81,7 +192,7 @@ mod tests {
             // The type checker might modify the AST a bit before the evaluation
             ast.type_check(&mut Environment::new()).unwrap();
             let res = ast.evaluate(&mut Environment::new());
-            assert_eq!(res, $expected);
+            assert_eq!(res, Ok($expected));
         }
 
     }
@@ -120,6 +231,118 @@ mod tests {
         assert_result!("let var x := 1 in match [x, 1] := [42, 2], x end", Integer(1));
     }
 
+    #[test]
+    fn record() {
+        assert_result!("let
+                          type Point = {x: Integer, y: Integer}
+                          var px := 0
+                       in
+                          match Point {y = 2, x = px} := Point {x = 42, y = 2},
+                          px
+                       end",
+                       Integer(42));
+        assert_result!("let
+                          type Point = {x: Integer, y: Integer}
+                          var px := 0
+                       in
+                          match Point {x = px, y = 3} := Point {x = 42, y = 2}
+                       end",
+                       Bool(false));
+    }
+
+    #[test]
+    fn sum_type() {
+        assert_result!("let
+                          type Shape = Circle(Float) | Rect(Float, Float)
+                          var w := 0.
+                       in
+                          match Rect(w, 2.) := Rect(42., 2.),
+                          w
+                       end",
+                       Float(42.));
+        assert_result!("let
+                          type Shape = Circle(Float) | Rect(Float, Float)
+                          var r := 0.
+                       in
+                          match Circle(r) := Rect(42., 2.)
+                       end",
+                       Bool(false));
+        assert_result!("let
+                          type List = Cons(Integer, List) | Nil
+                          var head := 0
+                       in
+                          match Cons(head, Nil) := Cons(1, Cons(2, Nil))
+                       end",
+                       Bool(false));
+        assert_result!("let
+                          type List = Cons(Integer, List) | Nil
+                          var head := 0
+                       in
+                          match Cons(head, Nil) := Cons(1, Nil),
+                          head
+                       end",
+                       Integer(1));
+    }
+
+    #[test]
+    fn wildcard() {
+        assert_result!("match _ := 42", Bool(true));
+        assert_result!("match {_, 2} := {1, 2}", Bool(true));
+        assert_result!("match {_, 2} := {1, 3}", Bool(false));
+        assert_result!("match [_, _] := [1]", Bool(false));
+    }
+
+    #[test]
+    fn rest() {
+        assert_result!("match [..] := Integer[]", Bool(true));
+        assert_result!("match [a, ..r] := [1]", Bool(true));
+        assert_result!("match [a, ..r] := Integer[]", Bool(false));
+        assert_result!("match [a, ..r, b] := [1]", Bool(false));
+        assert_result!("match [1, .., 3] := [1, 2, 2, 3]", Bool(true));
+        assert_result!("match [1, .., 3] := [1, 2, 2, 4]", Bool(false));
+        assert_result!("let in match [a, ..r] := [1, 2, 3], r end",
+                       Array {
+                           element_type: Type::Integer,
+                           values: vec![Integer(2), Integer(3)],
+                       });
+        assert_result!("let in match [..r, a, b] := [1, 2, 3], {r, a, b} end",
+                       Tuple {
+                           element_types: vec![Type::Array(Box::new(Type::Integer)),
+                                               Type::Integer,
+                                               Type::Integer],
+                           values: vec![Array {
+                                            element_type: Type::Integer,
+                                            values: vec![Integer(1)],
+                                        },
+                                        Integer(2),
+                                        Integer(3)],
+                       });
+    }
+
+    #[test]
+    fn pinned() {
+        assert_result!("match {x, x} := {1, 1}", Bool(true));
+        assert_result!("match {x, x} := {1, 2}", Bool(false));
+        assert_result!("match [x, .., x] := [1, 2, 3, 1]", Bool(true));
+        assert_result!("match [x, .., x] := [1, 2, 3, 4]", Bool(false));
+        assert_result!("case {1, 2} of {x, x} => 1 | {x, y} => 2 end", Integer(2));
+    }
+
+    #[test]
+    fn fresh_bindings() {
+        assert_result!("let in match {x, y} := {1, 2.}, x end", Integer(1));
+        assert_result!(r#"let var x := 1 in match x := "hello", x end"#, Str("hello".to_string()));
+        // A failed match keeps the value of the shadowed variable, or gives a default value
+        assert_result!(r#"let var x := 1 in match {x, 2} := {3, 4}, x end"#, Integer(1));
+        assert_result!(r#"let var x := 1 in match {x, 2} := {"a", 4}, x end"#,
+                       Str("".to_string()));
+        assert_result!("let var x := 1 in (case 42 of x => x end) + x end", Integer(43));
+        // The variables bound in a branch only live in it
+        assert_result!("let var x := 1 in if 1 then match x := 2, x end", Integer(1));
+        assert_result!("let var x := 1 in for var i := 0 to 2 do match x := i, x end",
+                       Integer(1));
+    }
+
     #[test]
     fn megamix() {
         assert_result!("match [{}] := [{}]", Bool(true));
@@ -1,52 +1,766 @@
 //! The module where the pattern match type checking is implemented
+//!
+//! This is also where the arms of case expressions are checked for exhaustiveness and
+//! reachability, using the usefulness algorithm described by Luc Maranget in "Warnings for
+//! pattern matching" (2007).
 
 use ast::*;
 use env::{Environment, TypeInfo};
-use error::{TypeCheckError, MismatchedTypesError};
+use error::{TypeCheckError, InvalidPatternError, MismatchedTypesError, NoSuchFieldError,
+            NonExhaustiveMatchError, NotARecordError, UndefinedFunctionError, UndefinedTypeError,
+            UnreachableArmError, VariantArityError};
 use processing::TypeCheck;
+use processing::type_check::resolve_type_params;
+use type_sys::{AbstractType, Generic, Type, Value};
+
+use itertools::Itertools;
+
+use std::fmt;
+use std::iter;
 
 /// That trait that must be implemented by the part of the AST for pattern match type checking
 pub trait PatternMatchCheck: TypeCheck {
-    fn check_match(&mut self,
-                   rhs: &mut Self,
-                   env: &mut Environment<TypeInfo>)
-                   -> Result<(), TypeCheckError>;
+    /// Check that the pattern can match a value of the given type
+    ///
+    /// Returns the variables bound by the pattern, without declaring them: this is up to the
+    /// caller, which knows in which scope they live.
+    fn check_pattern(&mut self,
+                     type_: &Type,
+                     span: Span,
+                     env: &mut Environment<TypeInfo>)
+                     -> Result<Vec<PatternBinding>, TypeCheckError>;
 }
 
 impl PatternMatchCheck for Expr {
-    fn check_match(&mut self,
-                   rhs: &mut Self,
-                   env: &mut Environment<TypeInfo>)
-                   -> Result<(), TypeCheckError> {
-        use ast::Expr::*;
+    fn check_pattern(&mut self,
+                     type_: &Type,
+                     span: Span,
+                     env: &mut Environment<TypeInfo>)
+                     -> Result<Vec<PatternBinding>, TypeCheckError> {
+        let mut bindings = Vec::new();
+        check_sub_pattern(self, type_, span, &mut bindings, env)?;
+        Ok(bindings)
+    }
+}
+
+// Check a part of a pattern against the type of the value it will match, adding the variables it
+// binds to the given bindings
+//
+// The type of the value may still be unknown: it is then deduced from the pattern.
+fn check_sub_pattern(pattern: &mut Expr,
+                     type_: &Type,
+                     span: Span,
+                     bindings: &mut Vec<PatternBinding>,
+                     env: &mut Environment<TypeInfo>)
+                     -> Result<(), TypeCheckError> {
+    use ast::Expr::*;
+
+    let type_ = &env.substitution.apply(type_);
+
+    let mismatch = |expected: Generic| -> TypeCheckError {
+        MismatchedTypesError::new(expected, type_.clone(), span).into()
+    };
+
+    match *pattern {
+        Wildcard(_) => Ok(()),
+
+        Variable { ref name, span } if env.is_variant_pattern(name) => {
+            check_variant(name, 0, type_, span, env).map(|_| ())
+        }
+
+        Variable { ref name, span } => bind(name, type_, span, bindings, env),
+
+        Value(ref value) => {
+            if !env.substitution.unify(&value.get_type(), type_) {
+                return Err(mismatch(value.get_type().into()));
+            }
+
+            Ok(())
+        }
+
+        Tuple(ref mut values) => {
+            if let Type::Var(_) = *type_ {
+                let types = values.iter().map(|_| env.substitution.fresh()).collect();
+                env.substitution.unify(type_, &Type::Tuple(types));
+            }
+
+            match env.substitution.apply(type_) {
+                Type::Tuple(ref types) if types.len() == values.len() => {
+                    for (value, type_) in values.iter_mut().zip(types) {
+                        check_sub_pattern(value, type_, span, bindings, env)?;
+                    }
+
+                    Ok(())
+                }
+                _ => Err(mismatch(Generic::Abstract(AbstractType::Tuple(Box::new(Generic::Any))))),
+            }
+        }
 
-        if let Variable { ref name, span } = *self {
-            let mut assign = Expr::Assign {
-                name: name.clone(),
-                name_span: span,
-                value: Box::new(rhs.clone()),
-                // TODO
-                value_span: Span(0, 0),
+        Array {
+            ref mut values,
+            ref mut declared_type,
+            ..
+        } => {
+            if let Some(ref mut declared_type) = *declared_type {
+                resolve_type_params(declared_type, env);
+            }
+
+            if let Type::Var(_) = *type_ {
+                let element_type = match *declared_type {
+                    Some(ref declared_type) => declared_type.clone(),
+                    None => env.substitution.fresh(),
+                };
+                env.substitution
+                    .unify(type_, &Type::Array(Box::new(element_type)));
+            }
+
+            let element_type = match env.substitution.apply(type_) {
+                Type::Array(ref element_type) if declared_type
+                                                      .as_ref()
+                                                      .map_or(true, |declared_type| {
+                                                          env.substitution
+                                                              .unify(declared_type, element_type)
+                                                      }) => {
+                    env.substitution.apply(element_type)
+                }
+                _ => {
+                    return Err(mismatch(match *declared_type {
+                                            Some(ref declared_type) => {
+                                                Type::Array(Box::new(declared_type.clone())).into()
+                                            }
+                                            None => {
+                                                Generic::Abstract(AbstractType::Array(Box::new(Generic::Any)))
+                                            }
+                                        }))
+                }
             };
 
-            assign.type_check(env)?;
+            // Written for the pretty printer, like the types of the array expressions
+            *declared_type = Some(element_type.clone());
+
+            for &mut (ref mut value, value_span) in values.iter_mut() {
+                if let Rest { ref name, span } = **value {
+                    // The rest of an array is an array
+                    if let Some(ref name) = *name {
+                        bind(name, type_, span, bindings, env)?;
+                    }
+                    continue;
+                }
+
+                check_sub_pattern(value, &element_type, value_span, bindings, env)?;
+            }
+
             Ok(())
-        } else {
-            let my_type = self.type_check(env)?;
-            let rhs_type = rhs.type_check(env)?;
+        }
+
+        Record {
+            ref name,
+            ref mut fields,
+            span,
+        } => {
+            let decl = env.get_type_decl(name)
+                .cloned()
+                .ok_or_else(|| UndefinedTypeError::new(name.clone(), span))?;
+
+            if let TypeDefinition::Sum(_) = decl.definition {
+                return Err(NotARecordError::new(name.clone(), span).into());
+            }
 
-            if my_type != rhs_type {
-                // TODO
-                return Err(MismatchedTypesError::new(my_type.into(), rhs_type, Span(0, 0)).into());
+            let record_type = Type::Named(name.clone());
+            if !env.substitution.unify(&record_type, type_) {
+                return Err(mismatch(record_type.into()));
+            }
+
+            // The fields omitted in the pattern match anything
+            for field in fields.iter_mut() {
+                let field_decl = decl.get_field(&field.name)
+                    .ok_or_else(|| {
+                                    NoSuchFieldError::new(field.name.clone(),
+                                                          record_type.clone(),
+                                                          field.name_span)
+                                })?;
+
+                check_sub_pattern(&mut field.value,
+                                  &field_decl.type_,
+                                  field.value_span,
+                                  bindings,
+                                  env)?;
             }
 
             Ok(())
         }
+
+        Function {
+            ref name,
+            ref mut args,
+            span,
+        } => {
+            let types = check_variant(name, args.len(), type_, span, env)?;
+
+            for (&mut (ref mut arg, arg_span), type_) in args.iter_mut().zip(&types) {
+                check_sub_pattern(arg, type_, arg_span, bindings, env)?;
+            }
+
+            Ok(())
+        }
+
+        _ => Err(InvalidPatternError::new(span).into()),
+    }
+}
+
+// Check that a variant pattern has the right number of values, and can match a value of the given
+// type
+//
+// Returns the types of the values carried by the variant.
+fn check_variant(name: &str,
+                 arity: usize,
+                 type_: &Type,
+                 span: Span,
+                 env: &mut Environment<TypeInfo>)
+                 -> Result<Vec<Type>, TypeCheckError> {
+    let (type_name, types) = env.get_variant(name)
+        .map(|(decl, variant)| (decl.name.clone(), variant.types.clone()))
+        .ok_or_else(|| UndefinedFunctionError::new(name.to_string(), span))?;
+
+    if types.len() != arity {
+        return Err(VariantArityError::new(name.to_string(), types.len(), arity, span).into());
+    }
+
+    let variant_type = Type::Named(type_name);
+    if !env.substitution.unify(&variant_type, type_) {
+        return Err(MismatchedTypesError::new(variant_type.into(), type_.clone(), span).into());
+    }
+
+    Ok(types)
+}
+
+// Bind a variable of a pattern
+//
+// A variable used several times in the same pattern is pinned to its first value, and so must
+// have the same type every time.
+fn bind(name: &str,
+        type_: &Type,
+        span: Span,
+        bindings: &mut Vec<PatternBinding>,
+        env: &mut Environment<TypeInfo>)
+        -> Result<(), TypeCheckError> {
+    match bindings.iter().find(|binding| binding.name == name) {
+        Some(previous) if !env.substitution.unify(&previous.type_, type_) => {
+            let previous_type = env.substitution.apply(&previous.type_);
+            return Err(MismatchedTypesError::from_binding(Declaration::Pattern(previous.clone()),
+                                                          previous_type.into(),
+                                                          env.substitution.apply(type_),
+                                                          span)
+                               .into());
+        }
+        Some(_) => return Ok(()),
+        None => {}
+    }
+
+    bindings.push(PatternBinding {
+                      name: name.to_string(),
+                      type_: type_.clone(),
+                      span,
+                  });
+    Ok(())
+}
+
+/// Check that the arms of a case expression cover every value of the given type, and that every
+/// arm can be taken
+///
+/// Arms with a guard or with a variable used several times are ignored for the exhaustiveness,
+/// since they may not match even if their shape does.
+pub fn check_arms(type_: &Type,
+                  arms: &[CaseArm],
+                  span: Span,
+                  env: &Environment<TypeInfo>)
+                  -> Result<(), TypeCheckError> {
+    let types = [type_.clone()];
+    let mut rows = Vec::new();
+
+    for arm in arms {
+        let row = vec![Pattern::from_expr(&arm.pattern, arm.pattern_span, env)?];
+
+        if useful(&rows, &row, &types, env).is_none() {
+            return Err(UnreachableArmError::new(arm.pattern_span).into());
+        }
+
+        if arm.guard.is_none() && !has_pinned_variables(&arm.pattern, env) {
+            rows.push(row);
+        }
+    }
+
+    if let Some(witness) = useful(&rows, &[Pattern::Wildcard], &types, env) {
+        return Err(NonExhaustiveMatchError::new(witness[0].to_string(), span).into());
+    }
+
+    Ok(())
+}
+
+/// Get the variables bound by a pattern which failed its check, with erroneous types
+///
+/// They are declared anyway, so that the type check goes on without reporting their uses.
+pub fn erroneous_bindings(pattern: &Expr, env: &mut Environment<TypeInfo>) -> Vec<PatternBinding> {
+    let mut variables = Vec::new();
+    collect_variables(pattern, env, &mut variables);
+
+    variables
+        .into_iter()
+        .unique_by(|&(name, _)| name)
+        .map(|(name, span)| {
+                 PatternBinding {
+                     name: name.to_string(),
+                     type_: env.substitution.fresh_erroneous(),
+                     span,
+                 }
+             })
+        .collect()
+}
+
+// Tells if a variable is used several times in a pattern
+fn has_pinned_variables(pattern: &Expr, env: &Environment<TypeInfo>) -> bool {
+    let mut variables = Vec::new();
+    collect_variables(pattern, env, &mut variables);
+    variables.iter().unique_by(|&&(name, _)| name).count() != variables.len()
+}
+
+// Collect the variables of a pattern with their locations, every time they are used
+fn collect_variables<'a>(pattern: &'a Expr,
+                         env: &Environment<TypeInfo>,
+                         variables: &mut Vec<(&'a str, Span)>) {
+    use ast::Expr::*;
+
+    match *pattern {
+        Variable { ref name, span } if !env.is_variant_pattern(name) => {
+            variables.push((name, span))
+        }
+        Rest {
+            name: Some(ref name),
+            span,
+        } => variables.push((name, span)),
+        Tuple(ref values) => {
+            for value in values {
+                collect_variables(value, env, variables);
+            }
+        }
+        Array { ref values, .. } => {
+            for &(ref value, _) in values {
+                collect_variables(value, env, variables);
+            }
+        }
+        Record { ref fields, .. } => {
+            for field in fields {
+                collect_variables(&field.value, env, variables);
+            }
+        }
+        Function { ref args, .. } => {
+            for &(ref arg, _) in args {
+                collect_variables(arg, env, variables);
+            }
+        }
+        _ => {}
+    }
+}
+
+// A simplified pattern, only keeping what matters for exhaustiveness
+#[derive(Debug, Clone, PartialEq)]
+enum Pattern {
+    // Matches anything
+    Wildcard,
+    // Matches the values built by the constructor whose sub-values match the sub-patterns
+    Constructor(Constructor, Vec<Pattern>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Constructor {
+    Literal(Value),
+    Tuple,
+    // An array of the given length
+    Array(usize),
+    // An array of at least `prefix + suffix` elements, only the first `prefix` and last `suffix`
+    // of them being matched against sub-patterns
+    Slice { prefix: usize, suffix: usize },
+    // A record of the given type, with the names of the fields (in the declaration order)
+    Record(String, Vec<String>),
+    Variant(String),
+}
+
+impl Pattern {
+    // Simplify a pattern, or fail if the expression is not a pattern (`span` is its location, or
+    // the location of the innermost pattern containing it)
+    fn from_expr(expr: &Expr,
+                 span: Span,
+                 env: &Environment<TypeInfo>)
+                 -> Result<Self, TypeCheckError> {
+        use ast::Expr::*;
+
+        Ok(match *expr {
+            Variable { ref name, .. } if env.is_variant_pattern(name) => {
+                Pattern::Constructor(Constructor::Variant(name.clone()), Vec::new())
+            }
+            Variable { .. } | Wildcard(_) => Pattern::Wildcard,
+            Value(ref value) => Pattern::Constructor(Constructor::Literal(value.clone()), Vec::new()),
+            Tuple(ref values) => {
+                Pattern::Constructor(Constructor::Tuple,
+                                     values
+                                         .iter()
+                                         .map(|value| Pattern::from_expr(value, span, env))
+                                         .collect::<Result<_, _>>()?)
+            }
+            Array { ref values, .. } => {
+                let rest = values
+                    .iter()
+                    .position(|&(ref value, _)| if let Rest { .. } = **value {
+                                  true
+                              } else {
+                                  false
+                              });
+                let constructor = match rest {
+                    Some(prefix) => {
+                        Constructor::Slice {
+                            prefix,
+                            suffix: values.len() - prefix - 1,
+                        }
+                    }
+                    None => Constructor::Array(values.len()),
+                };
+
+                Pattern::Constructor(constructor,
+                                     values
+                                         .iter()
+                                         .filter(|&&(ref value, _)| if let Rest { .. } = **value {
+                                                     false
+                                                 } else {
+                                                     true
+                                                 })
+                                         .map(|&(ref value, value_span)| {
+                                                  Pattern::from_expr(value, value_span, env)
+                                              })
+                                         .collect::<Result<_, _>>()?)
+            }
+            Record {
+                ref name,
+                ref fields,
+                ..
+            } => {
+                let field_names = field_names(name, env);
+                let patterns = field_names
+                    .iter()
+                    .map(|field_name| match fields.iter().find(|field| field.name == *field_name) {
+                             Some(field) => Pattern::from_expr(&field.value, field.value_span, env),
+                             None => Ok(Pattern::Wildcard),
+                         })
+                    .collect::<Result<_, _>>()?;
+
+                Pattern::Constructor(Constructor::Record(name.clone(), field_names), patterns)
+            }
+            Function { ref name, ref args, .. } => {
+                Pattern::Constructor(Constructor::Variant(name.clone()),
+                                     args.iter()
+                                         .map(|&(ref arg, arg_span)| {
+                                                  Pattern::from_expr(arg, arg_span, env)
+                                              })
+                                         .collect::<Result<_, _>>()?)
+            }
+            _ => return Err(InvalidPatternError::new(span).into()),
+        })
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Constructor(ref constructor, ref patterns) => {
+                match *constructor {
+                    Constructor::Literal(ref value) => write!(f, "{}", value),
+                    Constructor::Tuple => write!(f, "{{{}}}", patterns.iter().join(", ")),
+                    Constructor::Array(_) => write!(f, "[{}]", patterns.iter().join(", ")),
+                    Constructor::Slice { prefix, .. } => {
+                        write!(f,
+                               "[{}]",
+                               patterns[..prefix]
+                                   .iter()
+                                   .map(|pattern| pattern.to_string())
+                                   .chain(iter::once("..".to_string()))
+                                   .chain(patterns[prefix..].iter().map(|pattern| pattern.to_string()))
+                                   .join(", "))
+                    }
+                    Constructor::Record(ref name, ref field_names) => {
+                        write!(f,
+                               "{} {{{}}}",
+                               name,
+                               field_names
+                                   .iter()
+                                   .zip(patterns)
+                                   .map(|(name, pattern)| format!("{} = {}", name, pattern))
+                                   .join(", "))
+                    }
+                    Constructor::Variant(ref name) if patterns.is_empty() => write!(f, "{}", name),
+                    Constructor::Variant(ref name) => {
+                        write!(f, "{}({})", name, patterns.iter().join(", "))
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Returns the names of the fields of a record type, in the declaration order
+fn field_names(name: &str, env: &Environment<TypeInfo>) -> Vec<String> {
+    match env.get_type_decl(name).map(|decl| &decl.definition) {
+        Some(&TypeDefinition::Record(ref fields)) => {
+            fields.iter().map(|field| field.name.clone()).collect()
+        }
+        _ => panic!("Undeclared record type {} in pattern", name),
+    }
+}
+
+// Returns every constructor of the given type, or None if there are too many of them to be
+// enumerated (like integers)
+//
+// Since arrays can be of any length, the constructors of an array type depend on the patterns:
+// the lengths up to the biggest one that matters are enumerated, the last one standing for every
+// longer array.
+fn all_constructors(type_: &Type,
+                    patterns: &[&Pattern],
+                    env: &Environment<TypeInfo>)
+                    -> Option<Vec<Constructor>> {
+    match *type_ {
+        Type::Bool => {
+            Some(vec![Constructor::Literal(Value::Bool(true)),
+                      Constructor::Literal(Value::Bool(false))])
+        }
+        Type::Tuple(_) => Some(vec![Constructor::Tuple]),
+        Type::Array(_) => {
+            let max_length = max_array_length(patterns);
+            Some((0..max_length)
+                     .map(Constructor::Array)
+                     .chain(iter::once(Constructor::Slice {
+                                           prefix: max_length,
+                                           suffix: 0,
+                                       }))
+                     .collect())
+        }
+        Type::Named(ref name) => {
+            let decl = env.get_type_decl(name)
+                .unwrap_or_else(|| panic!("Undeclared type {} in pattern", name));
+
+            match decl.definition {
+                TypeDefinition::Record(ref fields) => {
+                    Some(vec![Constructor::Record(name.clone(),
+                                                  fields
+                                                      .iter()
+                                                      .map(|field| field.name.clone())
+                                                      .collect())])
+                }
+                TypeDefinition::Sum(ref variants) => {
+                    Some(variants
+                             .iter()
+                             .map(|variant| Constructor::Variant(variant.name.clone()))
+                             .collect())
+                }
+            }
+        }
+        Type::Void | Type::Integer | Type::Float | Type::Str | Type::Function(..) |
+        Type::Param(_) | Type::Var(_) => None,
+    }
+}
+
+// Returns the length from which the given array patterns cannot tell arrays apart
+fn max_array_length(patterns: &[&Pattern]) -> usize {
+    let mut max_fixed = 0;
+    let mut max_prefix = 0;
+    let mut max_suffix = 0;
+
+    for pattern in patterns {
+        match **pattern {
+            Pattern::Constructor(Constructor::Array(length), _) => {
+                max_fixed = max_fixed.max(length + 1)
+            }
+            Pattern::Constructor(Constructor::Slice { prefix, suffix }, _) => {
+                max_prefix = max_prefix.max(prefix);
+                max_suffix = max_suffix.max(suffix);
+            }
+            _ => {}
+        }
+    }
+
+    max_fixed.max(max_prefix + max_suffix)
+}
+
+// Returns the types of the sub-values of a constructor of the given type
+fn sub_types(constructor: &Constructor, type_: &Type, env: &Environment<TypeInfo>) -> Vec<Type> {
+    match (constructor, type_) {
+        (&Constructor::Literal(_), _) => Vec::new(),
+        (&Constructor::Tuple, &Type::Tuple(ref types)) => types.clone(),
+        (&Constructor::Array(length), &Type::Array(ref element_type)) => {
+            iter::repeat((**element_type).clone()).take(length).collect()
+        }
+        (&Constructor::Slice { prefix, suffix }, &Type::Array(ref element_type)) => {
+            iter::repeat((**element_type).clone())
+                .take(prefix + suffix)
+                .collect()
+        }
+        (&Constructor::Record(ref name, _), _) => {
+            match env.get_type_decl(name).map(|decl| &decl.definition) {
+                Some(&TypeDefinition::Record(ref fields)) => {
+                    fields.iter().map(|field| field.type_.clone()).collect()
+                }
+                _ => panic!("Undeclared record type {} in pattern", name),
+            }
+        }
+        (&Constructor::Variant(ref name), _) => {
+            env.get_variant(name)
+                .unwrap_or_else(|| panic!("Undeclared variant {} in pattern", name))
+                .1
+                .types
+                .clone()
+        }
+        _ => panic!("Constructor {:?} for type {:?} in pattern", constructor, type_),
+    }
+}
+
+// Returns the sub-patterns of a pattern if it may match the values built by the given
+// constructor, with the given number of sub-values
+fn sub_patterns(pattern: &Pattern, constructor: &Constructor, arity: usize) -> Option<Vec<Pattern>> {
+    match *pattern {
+        Pattern::Wildcard => Some(vec![Pattern::Wildcard; arity]),
+        Pattern::Constructor(ref pattern_constructor, ref patterns)
+            if pattern_constructor == constructor => Some(patterns.clone()),
+        // A slice pattern matches the arrays long enough, whatever is in the middle
+        Pattern::Constructor(Constructor::Slice { prefix, suffix }, ref patterns)
+            if arity >= prefix + suffix => {
+            match *constructor {
+                Constructor::Array(_) |
+                Constructor::Slice { .. } => {
+                    Some(patterns[..prefix]
+                             .iter()
+                             .cloned()
+                             .chain(iter::repeat(Pattern::Wildcard).take(arity - prefix - suffix))
+                             .chain(patterns[prefix..].iter().cloned())
+                             .collect())
+                }
+                _ => None,
+            }
+        }
+        Pattern::Constructor(..) => None,
+    }
+}
+
+// Keep the rows that may match the given constructor, replacing their first pattern by the
+// sub-patterns
+fn specialize(rows: &[Vec<Pattern>], constructor: &Constructor, arity: usize) -> Vec<Vec<Pattern>> {
+    rows.iter()
+        .filter_map(|row| {
+                        sub_patterns(&row[0], constructor, arity)
+                            .map(|patterns| patterns.into_iter().chain(row[1..].iter().cloned()).collect())
+                    })
+        .collect()
+}
+
+// Keep the rows starting with a wildcard, without their first pattern
+fn default(rows: &[Vec<Pattern>]) -> Vec<Vec<Pattern>> {
+    rows.iter()
+        .filter(|row| row[0] == Pattern::Wildcard)
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
+
+// Rebuild a constructor pattern from the first patterns of a row
+fn rebuild(constructor: Constructor, arity: usize, mut row: Vec<Pattern>) -> Vec<Pattern> {
+    let rest = row.split_off(arity);
+    iter::once(Pattern::Constructor(constructor, row)).chain(rest).collect()
+}
+
+// Tells if the row is useful for the given constructor: see `useful`
+fn useful_constructor(rows: &[Vec<Pattern>],
+                      row: &[Pattern],
+                      types: &[Type],
+                      constructor: &Constructor,
+                      env: &Environment<TypeInfo>)
+                      -> Option<Vec<Pattern>> {
+    let sub_types = sub_types(constructor, &types[0], env);
+    let arity = sub_types.len();
+
+    let row = sub_patterns(&row[0], constructor, arity)
+        .expect("Useless constructor")
+        .into_iter()
+        .chain(row[1..].iter().cloned())
+        .collect::<Vec<_>>();
+    let types = sub_types
+        .into_iter()
+        .chain(types[1..].iter().cloned())
+        .collect::<Vec<_>>();
+
+    useful(&specialize(rows, constructor, arity), &row, &types, env)
+        .map(|witness| rebuild(constructor.clone(), arity, witness))
+}
+
+// Tells if a row of patterns (of the given types) matches values not matched by any of the rows
+//
+// Returns an example of such values if so.
+fn useful(rows: &[Vec<Pattern>],
+          row: &[Pattern],
+          types: &[Type],
+          env: &Environment<TypeInfo>)
+          -> Option<Vec<Pattern>> {
+    if row.is_empty() {
+        return if rows.is_empty() { Some(Vec::new()) } else { None };
+    }
+
+    let heads = rows.iter()
+        .map(|row| &row[0])
+        .chain(iter::once(&row[0]))
+        .collect::<Vec<_>>();
+
+    match row[0] {
+        // A slice pattern is split into the array lengths it matches
+        Pattern::Constructor(Constructor::Slice { prefix, suffix }, _) => {
+            let max_length = max_array_length(&heads);
+
+            (prefix + suffix..max_length)
+                .map(Constructor::Array)
+                .chain(iter::once(Constructor::Slice {
+                                      prefix: max_length,
+                                      suffix: 0,
+                                  }))
+                .filter_map(|constructor| useful_constructor(rows, row, types, &constructor, env))
+                .next()
+        }
+
+        Pattern::Constructor(ref constructor, _) => {
+            useful_constructor(rows, row, types, constructor, env)
+        }
+
+        Pattern::Wildcard => {
+            let used = heads
+                .iter()
+                .filter_map(|pattern| match **pattern {
+                                Pattern::Constructor(ref constructor, _) => Some(constructor),
+                                Pattern::Wildcard => None,
+                            })
+                .collect::<Vec<_>>();
+
+            match all_constructors(&types[0], &heads, env) {
+                Some(ref all) if !used.is_empty() => {
+                    all.iter()
+                        .filter_map(|constructor| {
+                                        useful_constructor(rows, row, types, constructor, env)
+                                    })
+                        .next()
+                }
+
+                _ => useful(&default(rows), &row[1..], &types[1..], env)
+                    .map(|witness| iter::once(Pattern::Wildcard).chain(witness).collect()),
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use ast::Span;
     use env::Environment;
     use error::*;
     use parser;
@@ -61,7 +775,10 @@ mod tests {
                 .unwrap()
                 .type_check(&mut Environment::new());
             assert!(match res {
-                Err($err) => true,
+                Err(ref errors) if errors.len() == 1 => match errors[0] {
+                    $err => true,
+                    _ => false,
+                },
                 _ => false,
             });
         };
@@ -71,7 +788,10 @@ mod tests {
                 .unwrap()
                 .type_check(&mut Environment::new());
             assert!(match res {
-                Err($err) if $guard => true,
+                Err(ref errors) if errors.len() == 1 => match errors[0] {
+                    $err if $guard => true,
+                    _ => false,
+                },
                 _ => false,
             });
         };
@@ -89,7 +809,7 @@ mod tests {
 
     #[test]
     fn value() {
-        assert_err!("match 1 := 2+3.4",
+        assert_err!("match 1 := 2+true",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
 
@@ -110,7 +830,7 @@ mod tests {
 
     #[test]
     fn array() {
-        assert_err!("match [1] := [2+3.4]",
+        assert_err!("match [1] := [2+true]",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
 
@@ -127,7 +847,7 @@ mod tests {
 
     #[test]
     fn tuple() {
-        assert_err!("match {1} := {2+3.4}",
+        assert_err!("match {1} := {2+true}",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
 
@@ -138,8 +858,94 @@ mod tests {
                         ref got,
                         ..
                     })
-                    if *expected == Generic::Builtin(Tuple(vec![Integer])) &&
-                       *got == Tuple(vec![Float]));
+                    if *expected == Generic::Builtin(Integer) && *got == Float);
+    }
+
+
+    #[test]
+    fn bindings() {
+        assert_type!("match {x, _} := {1, 2.}", Bool);
+        assert_type!("let in match [a, ..r] := [1, 2], r[0] + a end", Integer);
+        assert_type!("let var x := 1. in match x := 1, x + 1 end", Integer);
+        assert_err!("match {x, x} := {1, 2.}",
+                    TypeCheckError::MismatchedTypes(MismatchedTypesError {
+                        expected: Generic::Builtin(Integer),
+                        got: Float,
+                        ..
+                    }));
+        assert_err!("let in match {x, 1} := {1, 2}, y end",
+                    TypeCheckError::UnboundedVar(UnboundedVarError { ref name, .. })
+                    if name == "y");
+        assert_err!("(case 1 of x => x end) + x",
+                    TypeCheckError::UnboundedVar(UnboundedVarError { ref name, .. })
+                    if name == "x");
+        assert_err!("let
+                        type Shape = Circle(Float) | Rect(Float, Float)
+                     in
+                        match Rect(w) := Circle(1.)
+                     end",
+                    TypeCheckError::VariantArity(VariantArityError { expected: 2, got: 1, .. }));
+    }
+
+    #[test]
+    fn exhaustiveness() {
+        assert_type!("case true of true => 1 | false => 2 end", Integer);
+        assert_type!("let
+                        type List = Cons(Integer, List) | Nil
+                        var x := 0
+                        var l := Nil
+                     in
+                        case Cons(1, Nil) of Cons(1, l) => 1 | Cons(x, Nil) => 2 | Cons(x, l) => 3
+                                           | Nil => 4 end
+                     end",
+                     Integer);
+        assert_type!("let
+                        type Point = {x: Bool, y: Integer}
+                        var y := 0
+                     in
+                        case Point {x = true, y = 1} of Point {x = true, y = y} => 1
+                                                      | Point {y = y, x = false} => 2 end
+                     end",
+                     Integer);
+
+        assert_err!("case 1 of 1 => 1 | 2 => 2 end",
+                    TypeCheckError::NonExhaustiveMatch(NonExhaustiveMatchError { ref missing, .. })
+                    if missing == "_");
+        assert_err!("case {true, false} of {true, false} => 1 | {false, true} => 2 end",
+                    TypeCheckError::NonExhaustiveMatch(NonExhaustiveMatchError { ref missing, .. })
+                    if missing == "{true, true}");
+        assert_err!("let
+                        type Shape = Circle(Float) | Rect(Float, Float) | Empty
+                        var r := 0.
+                     in
+                        case Empty of Circle(r) => 1 | Empty => 2 end
+                     end",
+                    TypeCheckError::NonExhaustiveMatch(NonExhaustiveMatchError { ref missing, .. })
+                    if missing == "Rect(_, _)");
+        assert_err!("let var x := 0 in case 1 of x when x > 0 => 1 end end",
+                    TypeCheckError::NonExhaustiveMatch(NonExhaustiveMatchError { ref missing, .. })
+                    if missing == "_");
+
+        assert_err!("let var x := 0 in case 1 of x => 1 | 2 => 2 end end",
+                    TypeCheckError::UnreachableArm(UnreachableArmError { span })
+                    if span == Span(37, 38));
+        assert_err!("case true of true => 1 | false => 2 | true => 3 end",
+                    TypeCheckError::UnreachableArm(UnreachableArmError { .. }));
+
+        assert_type!("case [1] of [] => 1 | [x, ..r] => 2 end", Integer);
+        assert_type!("case [1] of [] => 1 | [x] => 2 | [x, y, ..] => 3 end", Integer);
+        assert_type!("case [1] of [..r, 1] => 1 | [..r, x] => 2 | [] => 3 end", Integer);
+        assert_err!("case [1] of [x, ..r] => 1 end",
+                    TypeCheckError::NonExhaustiveMatch(NonExhaustiveMatchError { ref missing, .. })
+                    if missing == "[]");
+        assert_err!("case [1] of [] => 1 | [x] => 2 end",
+                    TypeCheckError::NonExhaustiveMatch(NonExhaustiveMatchError { ref missing, .. })
+                    if missing == "[_, _, ..]");
+        assert_err!("case {1, 2} of {x, x} => 1 end",
+                    TypeCheckError::NonExhaustiveMatch(NonExhaustiveMatchError { ref missing, .. })
+                    if missing == "_");
+        assert_err!("case [1] of [..] => 1 | [x] => 2 end",
+                    TypeCheckError::UnreachableArm(UnreachableArmError { .. }));
     }
 
 }
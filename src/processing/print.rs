@@ -19,31 +19,63 @@ impl Print for Expr {
 
         match *self {
             Grouping(ref exprs) => {
-                let mut fmt_exprs = exprs.pretty_print(indent + 2);
-
-                if exprs.exprs.is_empty() {
+                if exprs.exprs.is_empty() && exprs.comments.is_empty() {
                     return "()".to_string();
-                } else if exprs.exprs.len() == 1 {
-                    // Add a comma for single expr grouping
-                    fmt_exprs.pop();
-                    fmt_exprs += ",\n";
                 }
 
+                // Add a comma for single expr grouping
+                let fmt_exprs = print_exprs(exprs,
+                                            &exprs.comments,
+                                            indent + 2,
+                                            exprs.exprs.len() == 1);
+
                 format!("(\n{}{})", fmt_exprs, ws)
             }
 
-            Let(ref bindings, ref function_decls, ref exprs) => {
-                format!("let\n{}{}{}in\n{}{}end",
-                        bindings
-                            .iter()
-                            .map(|binding| format!("{}\n", binding.pretty_print(indent + 2)))
-                            .join(""),
-                        function_decls
+            Let(ref type_decls, ref bindings, ref function_decls, ref exprs) => {
+                let declarations = type_decls
+                    .iter()
+                    .map(|type_decl| (type_decl.span, type_decl.pretty_print(indent + 2)))
+                    .chain(bindings
+                               .iter()
+                               .map(|binding| (binding.span, binding.pretty_print(indent + 2))))
+                    .chain(function_decls.iter().map(|decl| {
+                        (Span(decl.signature_span.0, decl.body_span.1),
+                         decl.pretty_print(indent + 2))
+                    }))
+                    .collect::<Vec<_>>();
+
+                // The comments of the declarations are printed before the declaration containing
+                // or following them
+                let declarations_end = declarations.iter().map(|&(span, _)| span.1).max();
+                let (declaration_comments, body_comments): (Vec<_>, Vec<_>) =
+                    exprs
+                        .comments
+                        .iter()
+                        .partition(|comment| {
+                                       declarations_end.map_or(false, |end| comment.span.0 < end)
+                                   });
+
+                let mut printed_declarations = String::new();
+                for &(span, ref declaration) in &declarations {
+                    for comment in &declaration_comments {
+                        let owner = declarations
                             .iter()
-                            .map(|binding| format!("{}\n", binding.pretty_print(indent + 2)))
-                            .join(""),
+                            .map(|&(span, _)| span)
+                            .filter(|span| comment.span.0 < span.1)
+                            .min_by_key(|span| span.1);
+
+                        if owner == Some(span) {
+                            printed_declarations += &format!("{}  {}\n", ws, comment.text);
+                        }
+                    }
+                    printed_declarations += &format!("{}\n", declaration);
+                }
+
+                format!("let\n{}{}in\n{}{}end",
+                        printed_declarations,
                         ws,
-                        exprs.pretty_print(indent + 2),
+                        print_exprs(exprs, body_comments, indent + 2, false),
                         ws)
             }
 
@@ -53,6 +85,12 @@ impl Print for Expr {
                 ..
             } => format!("{} := {}", name, value.pretty_print(indent)),
 
+            ElementAssign {
+                ref target,
+                ref value,
+                ..
+            } => format!("{} := {}", target.pretty_print(indent), value.pretty_print(indent)),
+
             PatternMatch {
                 ref lhs,
                 ref rhs,
@@ -73,10 +111,39 @@ impl Print for Expr {
                 ref false_branch,
                 ..
             } => {
-                format!("(if {} then {} else {})",
-                        cond.pretty_print(indent),
-                        true_branch.pretty_print(indent),
-                        false_branch.pretty_print(indent))
+                match *false_branch {
+                    Some((ref false_branch, _)) => {
+                        format!("(if {} then {} else {})",
+                                cond.pretty_print(indent),
+                                true_branch.pretty_print(indent),
+                                false_branch.pretty_print(indent))
+                    }
+                    None => {
+                        format!("(if {} then {})",
+                                cond.pretty_print(indent),
+                                true_branch.pretty_print(indent))
+                    }
+                }
+            }
+
+            Case { ref expr, ref arms, .. } => {
+                format!("(case {} of {} end)",
+                        expr.pretty_print(indent),
+                        arms.iter()
+                            .map(|arm| match arm.guard {
+                                     Some((ref guard, _)) => {
+                                         format!("{} when {} => {}",
+                                                 arm.pattern.pretty_print(indent),
+                                                 guard.pretty_print(indent),
+                                                 arm.body.pretty_print(indent))
+                                     }
+                                     None => {
+                                         format!("{} => {}",
+                                                 arm.pattern.pretty_print(indent),
+                                                 arm.body.pretty_print(indent))
+                                     }
+                                 })
+                            .join(" | "))
             }
 
             While { ref cond, ref expr, .. } => {
@@ -87,13 +154,31 @@ impl Print for Expr {
 
             For {
                 ref binding,
+                direction,
                 ref goal,
+                ref step,
                 ref expr,
                 ..
             } => {
-                format!("(for {} to {} do {})",
+                format!("(for {} {} {}{} do {})",
                         binding.pretty_print(indent),
+                        direction,
                         goal.pretty_print(indent),
+                        step.as_ref()
+                            .map(|&(ref step, _)| format!(" step {}", step.pretty_print(indent)))
+                            .unwrap_or_default(),
+                        expr.pretty_print(indent))
+            }
+
+            ForIn {
+                ref name,
+                ref iterable,
+                ref expr,
+                ..
+            } => {
+                format!("(for {} in {} do {})",
+                        name,
+                        iterable.pretty_print(indent),
                         expr.pretty_print(indent))
             }
 
@@ -108,6 +193,15 @@ impl Print for Expr {
                     Sub => "-",
                     Mul => "*",
                     Div => "/",
+                    Mod => "mod",
+                    Pow => "**",
+                    IntDiv => "div",
+
+                    BitAnd => "&",
+                    BitOr => "|",
+                    BitXor => "^",
+                    Shl => "<<",
+                    Shr => ">>",
 
                     Lt => "<",
                     Le => "<=",
@@ -123,10 +217,23 @@ impl Print for Expr {
                         &rhs.pretty_print(indent))
             }
 
+            LogicalOp {
+                ref lhs,
+                ref rhs,
+                op,
+                ..
+            } => {
+                format!("({} {} {})",
+                        &lhs.pretty_print(indent),
+                        op,
+                        &rhs.pretty_print(indent))
+            }
+
             UnaryOp { ref expr, ref op, .. } => {
                 match *op {
                     Plus => format!("(+{})", expr.pretty_print(indent)),
                     Minus => format!("(-{})", expr.pretty_print(indent)),
+                    Not => format!("(not {})", expr.pretty_print(indent)),
                 }
             }
 
@@ -134,6 +241,20 @@ impl Print for Expr {
                 format!("({} as {:?})", expr.pretty_print(indent), dest)
             }
 
+            TryCast { ref expr, ref dest, .. } => {
+                format!("({} as? {:?})", expr.pretty_print(indent), dest)
+            }
+
+            Index {
+                ref expr,
+                ref index,
+                ..
+            } => format!("{}[{}]", expr.pretty_print(indent), index.pretty_print(indent)),
+
+            Projection { ref expr, index, .. } => format!("{}.{}", expr.pretty_print(indent), index),
+
+            Field { ref expr, ref name, .. } => format!("{}.{}", expr.pretty_print(indent), name),
+
             Variable { ref name, .. } => name.clone(),
 
             Array {
@@ -157,39 +278,136 @@ impl Print for Expr {
                             .join(", "))
             }
 
-            Value(type_sys::Value::Str(ref value)) => {
-                let string = value.chars().map(|c| {
-                    if c.is_control() {
-                        format!("\\u{:04x}", c as usize)
-                    } else if c == '"' {
-                        "\\\"".to_string()
-                    } else if c == '\\' {
-                        "\\\\".to_string()
-                    } else {
-                        format!("{}", c)
-                    }
-                })
-                .join("");
+            Record {
+                ref name,
+                ref fields,
+                ..
+            } => {
+                format!("{} {{{}}}",
+                        name,
+                        fields
+                            .iter()
+                            .map(|field| {
+                                     format!("{} = {}", field.name, field.value.pretty_print(indent))
+                                 })
+                            .join(", "))
+            }
+
+            Lambda(ref decl) => {
+                format!("(function({}){} := {})",
+                        decl.args
+                            .iter()
+                            .map(|arg| arg.pretty_print(indent))
+                            .join(", "),
+                        print_return_type(decl),
+                        decl.body.pretty_print(indent))
+            }
+
+            Interpolation(ref parts) => {
+                let string = parts
+                    .iter()
+                    .map(|part| match *part {
+                             InterpolationPart::Text(ref text) => escape_str(text),
+                             InterpolationPart::Expr(ref expr, _) => {
+                                 format!("${{{}}}", expr.pretty_print(indent))
+                             }
+                         })
+                    .join("");
 
                 format!(r#""{}""#, string)
             }
 
+            Value(type_sys::Value::Str(ref value)) => format!(r#""{}""#, escape_str(value)),
+
+            Break(_) => "break".to_string(),
+
+            Continue(_) => "continue".to_string(),
+
+            Return { ref value, .. } => format!("(return {})", value.pretty_print(indent)),
+
             Value(ref value) => value.to_string(),
 
+            Wildcard(_) => "_".to_string(),
+
+            Rest { ref name, .. } => format!("..{}", name.as_ref().map_or("", String::as_str)),
+
         }
     }
 }
 
 impl Print for Exprs {
+    fn pretty_print(&self, indent: usize) -> String {
+        print_exprs(self, &self.comments, indent, false)
+    }
+}
+
+// Print the expressions one per line, each of the given comments on its own line before the
+// expression it precedes (or is inside of)
+fn print_exprs<'a, I>(exprs: &Exprs, comments: I, indent: usize, trailing_comma: bool) -> String
+    where I: IntoIterator<Item = &'a Comment>
+{
+    let strws = " ".repeat(indent);
+    let ws = strws.as_str();
+
+    let mut comments = comments.into_iter().peekable();
+    let mut result = String::new();
+
+    for (i, &(ref exp, span)) in exprs.exprs.iter().enumerate() {
+        while comments.peek().map_or(false, |comment| comment.span.0 < span.1) {
+            result += &format!("{}{}\n", ws, comments.next().unwrap().text);
+        }
+
+        let comma = if i + 1 < exprs.exprs.len() || trailing_comma { "," } else { "" };
+        result += &format!("{}{}{}\n", ws, exp.pretty_print(indent), comma);
+    }
+
+    for comment in comments {
+        result += &format!("{}{}\n", ws, comment.text);
+    }
+
+    if result.is_empty() {
+        // Keep the line break of an empty sequence
+        result.push('\n');
+    }
+
+    result
+}
+
+impl Print for TypeDecl {
     fn pretty_print(&self, indent: usize) -> String {
         let strws = " ".repeat(indent);
         let ws = strws.as_str();
 
-        self.exprs
-            .iter()
-            .map(|exp| exp.pretty_print(indent))
-            .map(|disp| format!("{}{}", ws, disp))
-            .join(",\n") + "\n"
+        match self.definition {
+            TypeDefinition::Record(ref fields) => {
+                format!("{}type {} = {{{}}}",
+                        ws,
+                        self.name,
+                        fields
+                            .iter()
+                            .map(|field| format!("{}: {:?}", field.name, field.type_))
+                            .join(", "))
+            }
+            TypeDefinition::Sum(ref variants) => {
+                format!("{}type {} = {}",
+                        ws,
+                        self.name,
+                        variants
+                            .iter()
+                            .map(|variant| if variant.types.is_empty() {
+                                     variant.name.clone()
+                                 } else {
+                                     format!("{}({})",
+                                             variant.name,
+                                             variant
+                                                 .types
+                                                 .iter()
+                                                 .map(|type_| format!("{:?}", type_))
+                                                 .join(", "))
+                                 })
+                            .join(" | "))
+            }
+        }
     }
 }
 
@@ -210,26 +428,66 @@ impl Print for FunctionDecl {
         let strws = " ".repeat(indent);
         let ws = strws.as_str();
 
+        let type_params = if self.type_params.is_empty() {
+            String::new()
+        } else {
+            format!("<{}>",
+                    self.type_params
+                        .iter()
+                        .map(|&(ref name, _)| name)
+                        .join(", "))
+        };
+
         let args = self.args
             .iter()
             .map(|arg| arg.pretty_print(indent))
             .join(", ");
 
-        format!("{}function {}({}) : {:?} := {}",
+        format!("{}function {}{}({}){} := {}",
                 ws,
                 self.name,
+                type_params,
                 args,
-                self.return_type,
+                print_return_type(self),
                 self.body.pretty_print(indent))
     }
 }
 
 impl Print for ArgumentDecl {
     fn pretty_print(&self, _indent: usize) -> String {
-        format!("{}: {:?}", self.name, self.type_)
+        match self.type_ {
+            Some(ref type_) => format!("{}: {:?}", self.name, type_),
+            None => self.name.clone(),
+        }
     }
 }
 
+// Print the return type annotation of a function (if not omitted)
+fn print_return_type(decl: &FunctionDecl) -> String {
+    decl.return_type
+        .as_ref()
+        .map_or_else(String::new, |type_| format!(" : {:?}", type_))
+}
+
+// Escape the text of a string literal (a `${` would start an embedded expression)
+fn escape_str(value: &str) -> String {
+    let mut chars = value.chars().peekable();
+    let mut result = String::new();
+
+    while let Some(c) = chars.next() {
+        if c.is_control() {
+            result.push_str(&format!("\\u{:04x}", c as usize));
+        } else if c == '"' || c == '\\' || (c == '$' && chars.peek() == Some(&'{')) {
+            result.push('\\');
+            result.push(c);
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::Print;
@@ -280,6 +538,17 @@ mod tests {
         perfect_coding!("((2 + 2) + 2)");
         perfect_coding!("((2 + (+2)) - (-2))");
         perfect_coding!("((2 <> 2) > 2)");
+        perfect_coding!("(((7 mod 2) div 2) * 3)");
+        perfect_coding!("(2 ** (3 ** 2))");
+        perfect_coding!("((2 ** 3) ** 2)");
+        perfect_coding!("(((1 << 2) | (3 & 4)) ^ (5 >> 6))");
+    }
+
+    #[test]
+    fn logical_operators() {
+        perfect_coding!("((a and b) or (not c))");
+        perfect_coding!("(a and (b or c))");
+        perfect_coding!("(not (not (1 = 2)))");
     }
 
     #[test]
@@ -339,6 +608,32 @@ in
   x(1, 2)
 end");
 
+        perfect_coding!("let
+  function first<T, U>(a: T, b: U) : T := a
+in
+  first(1, 2)
+end");
+
+        perfect_coding!("let
+  function add(x, y: Integer) := (x + y)
+in
+  add(1, 2)
+end");
+
+        perfect_coding!("let
+  type Point = {x: Float, y: Float}
+  var p := Point {x = 1., y = 2.}
+in
+  p.x := p.y,
+  p
+end");
+
+        perfect_coding!("let
+  type List = Cons(Integer, List) | Nil
+in
+  Cons(1, Nil)
+end");
+
         perfect_coding!("let
   var x := let
     var x := 2
@@ -370,6 +665,14 @@ end");
         perfect_coding!("x(1, y(2), 3)");
     }
 
+    #[test]
+    fn lambda() {
+        perfect_coding!("(function(x: Integer) : Integer := (x * 2))");
+        perfect_coding!("x((function() : Bool := true))");
+        almost_perfect_coding!("function(f: Function(Integer): Str) : Void := ()",
+                               "(function(f: Function(Integer): Str) : Void := ())");
+    }
+
     #[test]
     fn if_block() {
         perfect_coding!("(if 1 then 1 else 0)");
@@ -384,6 +687,15 @@ end");
   0,
   1
 ))");
+        perfect_coding!("(if 1 then 1)");
+        perfect_coding!("(if 1 then (if 0 then 1 else 0))");
+        perfect_coding!("(if 1 then (if 0 then 1) else 0)");
+    }
+
+    #[test]
+    fn case_block() {
+        perfect_coding!("(case x of 1 => 2 | y when (y > 1) => 3 end)");
+        perfect_coding!("(case x of Integer[] => 0 | Integer[_, ..r] => 1 | Integer[..] => 2 end)");
     }
 
     #[test]
@@ -396,6 +708,23 @@ end");
     fn for_block() {
         perfect_coding!("(for var x := 1 to 1 do 1)");
         perfect_coding!("(for var x := (for var x := 1 to 2 do 1) to (for var x := 1 to 2 do 1) do (for var x := 1 to 2 do 1))");
+        perfect_coding!("(for var x := 10 downto 0 do x)");
+        perfect_coding!("(for var x := 0 to 100 step 5 do x)");
+        perfect_coding!("(for var x := 10 downto (-10) step (1 + 1) do x)");
+        perfect_coding!("(for x in xs do x)");
+        perfect_coding!("(for c in (s + \"abc\") do (for x in c do x))");
+    }
+
+    #[test]
+    fn loop_jumps() {
+        perfect_coding!("(while 1 do break)");
+        perfect_coding!("(for var x := 1 to 2 do (if x then continue else break))");
+    }
+
+    #[test]
+    fn return_expr() {
+        perfect_coding!("(return 1)");
+        perfect_coding!("(return (return (1 + 2)))");
     }
 
     #[test]
@@ -403,6 +732,25 @@ end");
         perfect_coding!("((+2) as Str)");
         perfect_coding!("(+(2 as Str))");
         perfect_coding!("(+(2 as Str))");
+        perfect_coding!(r#"("42" as? Integer)"#);
+        perfect_coding!("(((+2) as? Str) as Tuple(Bool, Str))");
+    }
+
+    #[test]
+    fn index() {
+        perfect_coding!("x[1]");
+        perfect_coding!("x[(1 + 1)][y[0]]");
+        perfect_coding!("Integer[1, 2][0]");
+        perfect_coding!("(-x[0])");
+        perfect_coding!("x[0][1] := y[2]");
+    }
+
+    #[test]
+    fn projection() {
+        perfect_coding!("t.0");
+        perfect_coding!("t.0.1");
+        perfect_coding!("{1, 2}.1");
+        perfect_coding!("t.1[0].2 := 3");
     }
 
     #[test]
@@ -430,6 +778,77 @@ end");
         perfect_coding!(r#""hel\\lo""#);
         perfect_coding!(r#""hel\u001flo""#);
         almost_perfect_coding!(r#""hel\x1flo""#, r#""hel\u001flo""#);
+        perfect_coding!(r#""\${not interpolated} $1""#);
+        almost_perfect_coding!(r#""\$1""#, r#""$1""#);
+    }
+
+    #[test]
+    fn interpolation() {
+        perfect_coding!(r#""x = ${x}, y = ${(y + 1)}""#);
+        perfect_coding!(r#""${x}${y}""#);
+        perfect_coding!(r#""a ${"b ${c} \${d}"} {e}""#);
+        perfect_coding!(r#""${Point {x = 1.}.x} ${{1, 2}.0}""#);
+        almost_perfect_coding!(r#""${ x }""#, r#""${x}""#);
+    }
+
+    #[test]
+    fn comments() {
+        let input = "// start\nlet in /* x */ 1 end, (1, /* two */ 2), 1 + /* one */ 1 // end";
+        let exprs = ::parse_expressions(input).unwrap();
+
+        let output = "// start
+let
+in
+  /* x */
+  1
+end,
+(
+  1,
+  /* two */
+  2
+),
+/* one */
+(1 + 1)
+// end
+";
+
+        assert_eq!(exprs.pretty_print(0), output);
+        assert_eq!(::parse_expressions(output).unwrap().pretty_print(0), output);
+
+        // The comments stay in their declaration, or in the body of their function
+        let input = "let
+  // one
+  var x := 1 /* after one */
+  var y := if x then (/* two */ 2, 3) else 4
+  function f() := (1, /* three */ 2)
+in
+  // four
+  f(x + y)
+end";
+        let exprs = ::parse_expressions(input).unwrap();
+
+        let output = "let
+  // one
+  var x := 1
+  /* after one */
+  var y := (if x then (
+    /* two */
+    2,
+    3
+  ) else 4)
+  function f() := (
+    1,
+    /* three */
+    2
+  )
+in
+  // four
+  f((x + y))
+end
+";
+
+        assert_eq!(exprs.pretty_print(0), output);
+        assert_eq!(::parse_expressions(output).unwrap().pretty_print(0), output);
     }
 
 }
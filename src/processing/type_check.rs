@@ -1,65 +1,278 @@
+//! The module where the type checking is implemented
+//!
+//! The omitted types of the functions are inferred with the Hindley-Milner algorithm: they start
+//! as type variables, which are unified with the types they are used as (see the
+//! [`type_sys::Substitution`] struct). Once the bodies of the functions of a let are checked, the
+//! type variables of their signatures that are not constrained by the enclosing scopes become type
+//! parameters, so that these functions are generic (let-polymorphism).
+//!
+//! When the outermost check is done, the inferred types are written back inside the AST.
+//!
+//! The check goes on after an error, to report all of them: an erroneous expression is given a
+//! new type variable, which may be deduced to be anything. The errors caused by the unknown type
+//! of an erroneous expression are not reported.
+//!
+//! [`type_sys::Substitution`]: ../../type_sys/struct.Substitution.html
+
 use ast::*;
-use env::{Environment, BindingInfo, TypeInfo};
-use error::{ArrayTypeDecl, ConversionError, IncompatibleArmTypesError,
-            InconsistentArrayTypingError, MismatchedTypesError, NoSuchSignatureError,
-            TypeCheckError, UnboundedVarError, UndefinedFunctionError, UntypedEmptyArrayError,
-            VoidVarDeclartionError};
-use processing::pattern_match_check::PatternMatchCheck;
-use type_sys::Type;
+use env::{Environment, BindingInfo, Callable, TypeInfo};
+use error::{AlreadyDeclaredError, ArrayTypeDecl, ConversionError, IncompatibleArmTypesError,
+            IncompatibleCaseArmTypesError, InconsistentArrayTypingError, MismatchedTypesError,
+            MissingFieldError, NoSuchFieldError, NoSuchSignatureError, NotARecordError,
+            NotCallableError, OutsideFunctionError, OutsideLoopError, TupleIndexOutOfRangeError,
+            TypeAnnotationNeededError, TypeCheckError, UnboundedVarError, UndefinedFunctionError,
+            UndefinedTypeError, UninferableTypeParamError, UninhabitedTypeError,
+            UntypedEmptyArrayError, VoidVarDeclartionError};
+use processing::pattern_match_check::{check_arms, erroneous_bindings, PatternMatchCheck};
+use type_sys::{self, AbstractType, Generic, Match, Substitution, Type};
+
+use std::mem;
 
 /// That trait that must be implemented by part of the AST for type checking
 pub trait TypeCheck {
-    fn type_check(&mut self, env: &mut Environment<TypeInfo>) -> Result<Type, TypeCheckError>;
+    /// Type check, and write the inferred types inside the AST
+    ///
+    /// Returns every error found (in the order of the source code) if the check fails.
+    fn type_check(&mut self,
+                  env: &mut Environment<TypeInfo>)
+                  -> Result<Type, Vec<TypeCheckError>>;
 }
 
 impl TypeCheck for Exprs {
-    fn type_check(&mut self, env: &mut Environment<TypeInfo>) -> Result<Type, TypeCheckError> {
-        let mut final_type = Type::Void;
-        for expr in &mut self.exprs {
-            final_type = expr.type_check(env)?;
-        }
-        Ok(final_type)
+    fn type_check(&mut self,
+                  env: &mut Environment<TypeInfo>)
+                  -> Result<Type, Vec<TypeCheckError>> {
+        check_outermost(self, env)
     }
 }
 
 impl TypeCheck for Expr {
-    fn type_check(&mut self, env: &mut Environment<TypeInfo>) -> Result<Type, TypeCheckError> {
+    fn type_check(&mut self,
+                  env: &mut Environment<TypeInfo>)
+                  -> Result<Type, Vec<TypeCheckError>> {
+        check_outermost(self, env)
+    }
+}
+
+// Run the outermost type check, and write the inferred types inside the AST if there is no error
+fn check_outermost<N>(node: &mut N,
+                      env: &mut Environment<TypeInfo>)
+                      -> Result<Type, Vec<TypeCheckError>>
+    where N: Check + ResolveInferred
+{
+    let type_ = node.check(env);
+
+    let errors = mem::replace(&mut env.errors, Vec::new());
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut errors = Vec::new();
+    node.resolve_inferred(&env.substitution, &mut errors);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(env.substitution.apply(&type_))
+}
+
+// Type check a part of the AST, going on after an error
+//
+// The errors are recorded inside the environment.
+trait Check {
+    fn check(&mut self, env: &mut Environment<TypeInfo>) -> Type;
+}
+
+impl Check for Exprs {
+    fn check(&mut self, env: &mut Environment<TypeInfo>) -> Type {
+        self.types = self.exprs
+            .iter_mut()
+            .map(|&mut (ref mut expr, _)| expr.check(env))
+            .collect();
+        self.types.last().cloned().unwrap_or(Type::Void)
+    }
+}
+
+impl Check for Expr {
+    fn check(&mut self, env: &mut Environment<TypeInfo>) -> Type {
+        recover(env, |env| self.check_expr(env))
+    }
+}
+
+impl Check for FunctionDecl {
+    fn check(&mut self, env: &mut Environment<TypeInfo>) -> Type {
+        recover(env, |env| self.check_function(env))
+    }
+}
+
+// Run a check which may fail, and record its error
+//
+// The scopes entered by the failed check are left, and the erroneous part of the AST gets a new
+// type variable.
+fn recover<F>(env: &mut Environment<TypeInfo>, check: F) -> Type
+    where F: FnOnce(&mut Environment<TypeInfo>) -> Result<Type, TypeCheckError>
+{
+    let depth = env.scopes.len();
+
+    match check(env) {
+        Ok(type_) => type_,
+        Err(err) => {
+            while env.scopes.len() > depth {
+                env.leave_scope();
+            }
+
+            record_error(err, env);
+            env.substitution.fresh_erroneous()
+        }
+    }
+}
+
+// Record an error, unless it is only caused by a previous error
+//
+// The type of an erroneous expression is never deduced (see `Substitution::fresh_erroneous`), so
+// an error about it is a consequence of the error of the expression.
+fn record_error(err: TypeCheckError, env: &mut Environment<TypeInfo>) {
+    let types = match err {
+        TypeCheckError::MismatchedTypes(ref err) => vec![&err.got],
+        TypeCheckError::Conversion(ref err) => vec![&err.from],
+        TypeCheckError::IncompatibleArmTypes(ref err) => vec![&err.expected, &err.got],
+        TypeCheckError::IncompatibleCaseArmTypes(ref err) => vec![&err.expected, &err.got],
+        TypeCheckError::NoSuchSignature(ref err) => err.arg_types.iter().collect(),
+        TypeCheckError::InconsistentArrayTyping(ref err) => vec![&err.expected, &err.got],
+        TypeCheckError::TupleIndexOutOfRange(ref err) => vec![&err.tuple_type],
+        TypeCheckError::NotCallable(ref err) => vec![&err.type_],
+        TypeCheckError::NoSuchField(ref err) => vec![&err.type_],
+        TypeCheckError::TypeAnnotationNeeded(ref err) => vec![&err.type_],
+        _ => Vec::new(),
+    };
+
+    if types.iter().any(|type_| env.substitution.is_erroneous(type_)) {
+        return;
+    }
+
+    env.errors.push(err);
+}
+
+// Type check an expression, failing on its own error (the errors of the sub-expressions are
+// recorded)
+trait CheckExpr {
+    fn check_expr(&mut self, env: &mut Environment<TypeInfo>) -> Result<Type, TypeCheckError>;
+}
+
+impl CheckExpr for Expr {
+    fn check_expr(&mut self, env: &mut Environment<TypeInfo>) -> Result<Type, TypeCheckError> {
         use ast::Expr::*;
 
         match *self {
-            Grouping(ref mut exprs) => exprs.type_check(env),
+            Grouping(ref mut exprs) => Ok(exprs.check(env)),
 
-            Let(ref mut bindings, ref mut function_decls, ref mut exprs) => {
+            Let(ref type_decls, ref mut bindings, ref mut function_decls, ref mut exprs) => {
                 env.enter_scope();
 
+                // Declare every type before checking the fields, to allow recursive types
+                //
+                // An erroneous declaration is left out, and the check goes on with the others.
+                let mut declared_types = Vec::new();
+
+                for type_decl in type_decls.iter() {
+                    // A type cannot shadow another one, since the values of the other type may
+                    // still be reachable
+                    if let Some(other) = env.declared_types.get(&type_decl.name).cloned() {
+                        record_error(AlreadyDeclaredError::new(type_decl.name.clone(),
+                                                               Declaration::Type(other),
+                                                               type_decl.span)
+                                             .into(),
+                                     env);
+                        continue;
+                    }
+
+                    if let Err(err) = env.declare_type(type_decl.clone()) {
+                        record_error(err.into(), env);
+                        continue;
+                    }
+
+                    env.declared_types.insert(type_decl.name.clone(), type_decl.clone());
+                    declared_types.push(type_decl);
+                }
+
+                for type_decl in &declared_types {
+                    let mut results = Vec::new();
+
+                    match type_decl.definition {
+                        TypeDefinition::Record(ref fields) => {
+                            for field in fields {
+                                results.push(check_type_declared(&field.type_, field.span, env));
+                            }
+                        }
+                        TypeDefinition::Sum(ref variants) => {
+                            for variant in variants {
+                                for type_ in &variant.types {
+                                    results.push(check_type_declared(type_, variant.span, env));
+                                }
+                            }
+                        }
+                    }
+
+                    for err in results.into_iter().filter_map(Result::err) {
+                        record_error(err, env);
+                    }
+                }
+
+                // Every type must have a finite value (the default value of the `as?` operator)
+                for type_decl in &declared_types {
+                    let type_ = Type::Named(type_decl.name.clone());
+
+                    if !is_inhabited(&type_, env, &mut Vec::new()) {
+                        record_error(UninhabitedTypeError::new(type_decl.name.clone(),
+                                                               type_decl.span)
+                                             .into(),
+                                     env);
+                    }
+                }
+
                 for binding in bindings.iter_mut() {
-                    let type_ = binding.value.type_check(env)?;
+                    let type_ = binding.value.check(env);
 
+                    // The variable is declared anyway, to go on with the check
                     if type_ == Type::Void {
-                        return Err(VoidVarDeclartionError::new(binding.name.clone(),
-                                                               binding.value_span)
-                                           .into());
+                        record_error(VoidVarDeclartionError::new(binding.name.clone(),
+                                                                 binding.value_span)
+                                             .into(),
+                                     env);
                     }
 
-                    env.declare_var(binding.name.clone(),
-                                     BindingInfo::Variable {
-                                         declaration: binding.clone(),
-                                         info: TypeInfo(type_),
-                                     })
-                        .map_err(|mut err| {
-                                     err.span = binding.span;
-                                     err
-                                 })?;
+                    let declared = env.declare_var(binding.name.clone(),
+                                                   BindingInfo::Variable {
+                                                       declaration: binding.clone(),
+                                                       info: TypeInfo(type_),
+                                                   });
+
+                    if let Err(mut err) = declared {
+                        err.span = binding.span;
+                        record_error(err.into(), env);
+                    }
+                }
+
+                // Declare every function before cheking the type of the bodies, to allow
+                // (mutual) recursion: at runtime, every function of the scope is visible.
+                for function_decl in function_decls.iter_mut() {
+                    resolve_signature(function_decl, env);
+
+                    if let Err(err) = env.declare_func(function_decl.clone()) {
+                        record_error(err.into(), env);
+                    }
                 }
 
                 for function_decl in function_decls.iter_mut() {
-                    // Declare before cheking the type of the body, to allow
-                    // recursion.
-                    env.declare_func(function_decl.clone())?;
-                    function_decl.type_check(env)?;
+                    function_decl.check(env);
                 }
 
-                let final_type = exprs.type_check(env)?;
+                // The functions are only generic for the uses after their declaration
+                if let Err(err) = generalize(function_decls, env) {
+                    record_error(err, env);
+                }
+
+                let final_type = exprs.check(env);
 
                 env.leave_scope();
                 Ok(final_type)
@@ -71,32 +284,68 @@ impl TypeCheck for Expr {
                 ref mut value,
                 ref value_span,
             } => {
-                let assign_type = value.type_check(env)?;
+                let assign_type = value.check(env);
 
-                let var_info =
+                let (declaration, declared_type) =
                     env.get_var(name)
+                        .map(|var_info| (var_info.get_declaration(), var_info.get_type().clone()))
                         .ok_or_else(|| UnboundedVarError::new(name.clone(), *name_span))?;
-                let declared_type = var_info.get_type();
-
-                if *declared_type != assign_type {
-                    return Err(MismatchedTypesError::from_binding(var_info
-                                                                      .get_declaration()
-                                                                      .clone(),
-                                                                  declared_type.clone().into(),
-                                                                  assign_type,
+
+                if !unify(&declared_type, &assign_type, env) {
+                    return Err(MismatchedTypesError::from_binding(declaration,
+                                                                  applied(&declared_type, env)
+                                                                      .into(),
+                                                                  applied(&assign_type, env),
                                                                   *value_span)
                                        .into());
                 }
 
-                Ok(declared_type.clone())
+                Ok(applied(&declared_type, env))
+            }
+
+            ElementAssign {
+                ref mut target,
+                ref mut value,
+                ref value_span,
+                ..
+            } => {
+                let assign_type = value.check(env);
+                let element_type = target.check(env);
+
+                if !unify(&element_type, &assign_type, env) {
+                    return Err(MismatchedTypesError::new(applied(&element_type, env).into(),
+                                                         applied(&assign_type, env),
+                                                         *value_span)
+                                       .into());
+                }
+
+                Ok(applied(&element_type, env))
             }
 
             PatternMatch {
                 ref mut lhs,
+                lhs_span,
                 ref mut rhs,
+                ref mut bindings,
                 ..
             } => {
-                lhs.check_match(rhs, env)?;
+                let rhs_type = rhs.check(env);
+                *bindings = match lhs.check_pattern(&rhs_type, lhs_span, env) {
+                    Ok(bindings) => bindings,
+                    Err(err) => {
+                        record_error(err, env);
+                        erroneous_bindings(lhs, env)
+                    }
+                };
+
+                for binding in bindings.iter() {
+                    env.bind_var(binding.name.clone(),
+                                 BindingInfo::Pattern {
+                                     declaration: binding.clone(),
+                                     info: TypeInfo(binding.type_.clone()),
+                                 });
+                }
+
                 Ok(Type::Bool)
             }
 
@@ -106,35 +355,71 @@ impl TypeCheck for Expr {
                 ref span,
             } => {
                 let arg_types = args.iter_mut()
-                    //.map(|&mut (ref mut expr, ref span)| expr.type_check(env).map(|arg_type| (arg_type, span)))
-                    .map(|&mut (ref mut expr, _)| expr.type_check(env))
-                    .collect::<Result<Vec<_>, _>>()?;
+                    .map(|&mut (ref mut expr, _)| expr.check(env))
+                    .collect::<Vec<_>>();
+
+                // User functions, variables holding a closure and variant constructors shadow
+                // builtins
+                let (func, callable_type) = match env.get_callable(name) {
+                    Some(Callable::Function(func)) => (Some(func.clone()), None),
+                    Some(Callable::Variable(var)) => (None, Some(var.get_type().clone())),
+                    None => (None, None),
+                };
 
-                let mut user_defined = false;
-                let mut func = None;
+                if let Some(func) = func {
+                    // Generic functions are instantiated with new type variables, deduced from the
+                    // types of the arguments
+                    if let Type::Function(params, return_type) = instantiate(&func, env) {
+                        if unify_all(&params, &arg_types, env) {
+                            return Ok(applied(&return_type, env));
+                        }
+                    }
 
-                if let Some(user_func) = env.get_func(name) {
-                    user_defined = true;
-                    func = Some(user_func.clone());
-                }
+                    Err(NoSuchSignatureError::from_function(name.clone(),
+                                                            applied(&func.get_type(), env),
+                                                            func.signature_span,
+                                                            applied_all(&arg_types, env),
+                                                            *span)
+                                .into())
+                } else if let Some(callable_type) = callable_type {
+                    // A variable of unknown type is called: it must hold a closure
+                    if let Type::Var(_) = applied(&callable_type, env) {
+                        let params = arg_types
+                            .iter()
+                            .map(|_| env.substitution.fresh())
+                            .collect();
+                        let return_type = Box::new(env.substitution.fresh());
+                        let closure_type = Type::Function(params, return_type);
+                        unify(&callable_type, &closure_type, env);
+                    }
 
-                if user_defined {
-                    func.unwrap()
-                        .return_type(&arg_types)
-                        .cloned()
-                        .ok_or_else(|| {
-                                        NoSuchSignatureError::new(name.clone(), arg_types, *span)
-                                            .into()
-                                    })
-                } else if let Some(builtin) = env.get_builtin(name) {
-                    builtin
-                        .return_type(&arg_types, &env.types)
-                        .ok_or_else(|| {
-                                        NoSuchSignatureError::new(name.clone(), arg_types, *span)
-                                            .into()
-                                    })
+                    match applied(&callable_type, env) {
+                        Type::Function(ref params, ref return_type) if unify_all(params,
+                                                                                  &arg_types,
+                                                                                  env) => {
+                            Ok(applied(return_type, env))
+                        }
+                        Type::Function(..) => {
+                            Err(NoSuchSignatureError::new(name.clone(),
+                                                          applied_all(&arg_types, env),
+                                                          *span)
+                                        .into())
+                        }
+                        other => Err(NotCallableError::new(name.clone(), other, *span).into()),
+                    }
+                } else if let Some((type_name, types)) =
+                    env.get_variant(name)
+                        .map(|(decl, variant)| (decl.name.clone(), variant.types.clone())) {
+                    if unify_all(&types, &arg_types, env) {
+                        Ok(Type::Named(type_name))
+                    } else {
+                        Err(NoSuchSignatureError::new(name.clone(),
+                                                      applied_all(&arg_types, env),
+                                                      *span)
+                                    .into())
+                    }
                 } else {
-                    Err(UndefinedFunctionError::new(name.clone(), *span).into())
+                    builtin_return_type(name, &arg_types, *span, env)
                 }
             }
 
@@ -144,25 +429,95 @@ impl TypeCheck for Expr {
                 ref mut true_branch,
                 ref true_branch_span,
                 ref mut false_branch,
-                ref false_branch_span,
+                true_branch_type: ref mut written_type,
             } => {
-                let cond_type = cond.type_check(env)?;
-                if !cond_type.may_truthy() {
-                    return Err(ConversionError::new(cond_type, Type::Bool, *cond_span).into());
-                }
+                let cond_type = cond.check(env);
+                check_condition(&cond_type, *cond_span, env)?;
+
+                let true_branch_type = check_in_scope(true_branch, env);
+                *written_type = Some(true_branch_type.clone());
 
-                let true_branch_type = true_branch.type_check(env)?;
-                let false_branch_type = false_branch.type_check(env)?;
+                // Without an `else`, the value of the true branch is thrown away
+                let (false_branch, false_branch_span) = match *false_branch {
+                    Some((ref mut false_branch, ref false_branch_span)) => {
+                        (false_branch, false_branch_span)
+                    }
+                    None => return Ok(Type::Void),
+                };
+                let false_branch_type = check_in_scope(false_branch, env);
 
-                if true_branch_type != false_branch_type {
-                    return Err(IncompatibleArmTypesError::new(true_branch_type,
-                                                              false_branch_type,
+                if !unify(&true_branch_type, &false_branch_type, env) {
+                    return Err(IncompatibleArmTypesError::new(applied(&true_branch_type, env),
+                                                              applied(&false_branch_type, env),
                                                               *true_branch_span,
                                                               *false_branch_span)
                                        .into());
                 }
 
-                Ok(true_branch_type)
+                Ok(applied(&true_branch_type, env))
+            }
+
+            Case {
+                ref mut expr,
+                ref mut arms,
+                span,
+                ..
+            } => {
+                let type_ = expr.check(env);
+                let mut first_arm: Option<(Type, Span)> = None;
+                let mut erroneous_patterns = false;
+
+                for arm in arms.iter_mut() {
+                    // The variables bound by the pattern only live in the arm
+                    env.enter_scope();
+
+                    arm.bindings = match arm.pattern.check_pattern(&type_, arm.pattern_span, env) {
+                        Ok(bindings) => bindings,
+                        Err(err) => {
+                            record_error(err, env);
+                            erroneous_patterns = true;
+                            erroneous_bindings(&arm.pattern, env)
+                        }
+                    };
+
+                    for binding in &arm.bindings {
+                        env.bind_var(binding.name.clone(),
+                                     BindingInfo::Pattern {
+                                         declaration: binding.clone(),
+                                         info: TypeInfo(binding.type_.clone()),
+                                     });
+                    }
+
+                    if let Some((ref mut guard, guard_span)) = arm.guard {
+                        let guard_type = guard.check(env);
+                        check_condition(&guard_type, guard_span, env)?;
+                    }
+
+                    let body_type = arm.body.check(env);
+
+                    env.leave_scope();
+
+                    match first_arm {
+                        Some((ref first_type, first_span)) if !unify(first_type,
+                                                                     &body_type,
+                                                                     env) => {
+                            let err = IncompatibleCaseArmTypesError::new(applied(first_type, env),
+                                                                         applied(&body_type, env),
+                                                                         first_span,
+                                                                         arm.body_span);
+                            return Err(err.into());
+                        }
+                        Some(_) => {}
+                        None => first_arm = Some((body_type, arm.body_span)),
+                    }
+                }
+
+                // The patterns may have told what the matched type is
+                if !erroneous_patterns {
+                    check_arms(&applied(&type_, env), arms, span, env)?;
+                }
+
+                Ok(applied(&first_arm.expect("Case expression without arms").0, env))
             }
 
             While {
@@ -170,12 +525,10 @@ impl TypeCheck for Expr {
                 ref mut expr,
                 ref cond_span,
             } => {
-                let cond_type = cond.type_check(env)?;
-                if !cond_type.may_truthy() {
-                    return Err(ConversionError::new(cond_type, Type::Bool, *cond_span).into());
-                }
+                let cond_type = cond.check(env);
+                check_condition(&cond_type, *cond_span, env)?;
 
-                expr.type_check(env)?;
+                check_loop_body(expr, env);
 
                 Ok(Type::Void)
             }
@@ -183,35 +536,90 @@ impl TypeCheck for Expr {
             For {
                 ref mut binding,
                 ref mut goal,
-                ref mut expr,
                 ref goal_span,
+                ref mut step,
+                ref mut expr,
+                ..
             } => {
                 env.enter_scope();
 
-                let binding_type = binding.value.type_check(env)?;
-                let goal_type = goal.type_check(env)?;
+                let binding_type = binding.value.check(env);
+                let goal_type = goal.check(env);
 
-                if binding_type != Type::Integer {
+                if !unify(&binding_type, &Type::Integer, env) {
                     return Err(MismatchedTypesError::new(Type::Integer.into(),
-                                                         binding_type,
+                                                         applied(&binding_type, env),
                                                          binding.value_span)
                                        .into());
                 }
 
-                if goal_type != binding_type {
+                let binding_type = Type::Integer;
+
+                if !unify(&goal_type, &binding_type, env) {
                     return Err(MismatchedTypesError::new(binding_type.into(),
-                                                         goal_type,
+                                                         applied(&goal_type, env),
                                                          *goal_span)
                                        .into());
                 }
 
+                if let Some((ref mut step, step_span)) = *step {
+                    let step_type = step.check(env);
+
+                    if !unify(&step_type, &binding_type, env) {
+                        return Err(MismatchedTypesError::new(binding_type.into(),
+                                                             applied(&step_type, env),
+                                                             step_span)
+                                           .into());
+                    }
+                }
+
                 env.declare_var(binding.name.clone(),
                                  BindingInfo::Variable {
                                      declaration: (**binding).clone(),
                                      info: TypeInfo(binding_type),
                                  })?;
 
-                expr.type_check(env)?;
+                check_loop_body(expr, env);
+
+                env.leave_scope();
+                Ok(Type::Void)
+            }
+
+            ForIn {
+                ref name,
+                name_span,
+                ref mut iterable,
+                iterable_span,
+                ref mut expr,
+            } => {
+                let iterable_type = iterable.check(env);
+
+                let element_type = match known_type(iterable_type, iterable_span, env)? {
+                    Type::Array(element_type) => *element_type,
+                    Type::Str => Type::Str,
+                    other => {
+                        return Err(MismatchedTypesError::new(Generic::Named("Indexable"
+                                                                                .to_string()),
+                                                             other,
+                                                             iterable_span)
+                                           .into())
+                    }
+                };
+
+                env.enter_scope();
+
+                // Like a variable bound by a pattern, its type comes from the iterated value
+                env.bind_var(name.clone(),
+                             BindingInfo::Pattern {
+                                 declaration: PatternBinding {
+                                     name: name.clone(),
+                                     type_: element_type.clone(),
+                                     span: name_span,
+                                 },
+                                 info: TypeInfo(element_type),
+                             });
+
+                check_loop_body(expr, env);
 
                 env.leave_scope();
                 Ok(Type::Void)
@@ -220,22 +628,52 @@ impl TypeCheck for Expr {
             BinaryOp {
                 ref mut lhs,
                 ref mut rhs,
-                ref op,
-                ref span,
+                op,
+                span,
+            } => {
+                let mut lhs_type = lhs.check(env);
+                let mut rhs_type = rhs.check(env);
+
+                if promotes_operands(op) {
+                    promote_operands(lhs, &mut lhs_type, rhs, &mut rhs_type, span, env);
+                }
+
+                if is_comparison(op) && !unify(&lhs_type, &rhs_type, env) {
+                    return Err(NoSuchSignatureError::new(op.to_string(),
+                                                         applied_all(&[lhs_type, rhs_type], env),
+                                                         span)
+                                       .into());
+                }
+
+                builtin_return_type(&op.to_string(), &[lhs_type, rhs_type], span, env)
+            }
+
+            // The operands are conditions
+            LogicalOp {
+                ref mut lhs,
+                lhs_span,
+                ref mut rhs,
+                rhs_span,
+                ..
             } => {
-                let arg_types = vec![lhs.type_check(env)?, rhs.type_check(env)?];
+                let lhs_type = lhs.check(env);
+                check_condition(&lhs_type, lhs_span, env)?;
+
+                let rhs_type = check_in_scope(rhs, env);
+                check_condition(&rhs_type, rhs_span, env)?;
+
+                Ok(Type::Bool)
+            }
 
-                let name = &op.to_string();
+            UnaryOp {
+                ref mut expr,
+                op: UnaryOpCode::Not,
+                span,
+            } => {
+                let expr_type = expr.check(env);
+                check_condition(&expr_type, span, env)?;
 
-                env.get_builtin(name)
-                    .ok_or_else(|| UndefinedFunctionError::new(name.clone(), *span))?
-                    .return_type(&arg_types, &env.types)
-                    .ok_or_else(|| {
-                                    NoSuchSignatureError::new(name.clone(),
-                                                              arg_types.clone(),
-                                                              *span)
-                                            .into()
-                                })
+                Ok(Type::Bool)
             }
 
             UnaryOp {
@@ -243,27 +681,19 @@ impl TypeCheck for Expr {
                 ref op,
                 ref span,
             } => {
-                let arg_types = vec![expr.type_check(env)?];
+                let arg_types = vec![expr.check(env)];
 
-                let name = &format!("un{}", op.to_string());
-
-                env.get_builtin(name)
-                    .ok_or_else(|| UndefinedFunctionError::new(name.clone(), *span))?
-                    .return_type(&arg_types, &env.types)
-                    .ok_or_else(|| {
-                                    NoSuchSignatureError::new(name.clone(),
-                                                              arg_types.clone(),
-                                                              *span)
-                                            .into()
-                                })
+                builtin_return_type(&format!("un{}", op.to_string()), &arg_types, *span, env)
             }
 
             Cast {
                 ref mut expr,
                 ref expr_span,
-                ref dest,
+                ref mut dest,
             } => {
-                let src_type = expr.type_check(env)?;
+                let src_type = known_type(expr.check(env), *expr_span, env)?;
+                resolve_type_params(dest, env);
+                check_type_declared(dest, *expr_span, env)?;
 
                 if src_type.is_convertible_to(dest) {
                     Ok(dest.clone())
@@ -273,11 +703,115 @@ impl TypeCheck for Expr {
 
             }
 
+            TryCast {
+                ref mut expr,
+                ref expr_span,
+                ref mut dest,
+            } => {
+                let src_type = known_type(expr.check(env), *expr_span, env)?;
+                resolve_type_params(dest, env);
+                check_type_declared(dest, *expr_span, env)?;
+
+                if src_type.is_convertible_to(dest) {
+                    Ok(Type::Tuple(vec![Type::Bool, dest.clone()]))
+                } else {
+                    Err(ConversionError::new(src_type, dest.clone(), *expr_span).into())
+                }
+            }
+
+            Index {
+                ref mut expr,
+                ref expr_span,
+                ref mut index,
+                ref index_span,
+            } => {
+                let expr_type = expr.check(env);
+                let index_type = index.check(env);
+
+                if !unify(&index_type, &Type::Integer, env) {
+                    return Err(MismatchedTypesError::new(Type::Integer.into(),
+                                                         applied(&index_type, env),
+                                                         *index_span)
+                                       .into());
+                }
+
+                match known_type(expr_type, *expr_span, env)? {
+                    Type::Array(element_type) => Ok(*element_type),
+                    Type::Str => Ok(Type::Str),
+                    other => {
+                        Err(MismatchedTypesError::new(Generic::Named("Indexable".to_string()),
+                                                      other,
+                                                      *expr_span)
+                                    .into())
+                    }
+                }
+            }
+
+            Projection {
+                ref mut expr,
+                ref expr_span,
+                index,
+                ref index_span,
+            } => {
+                let expr_type = expr.check(env);
+
+                match known_type(expr_type, *expr_span, env)? {
+                    Type::Tuple(ref types) if index < types.len() => Ok(types[index].clone()),
+                    tuple_type @ Type::Tuple(_) => {
+                        Err(TupleIndexOutOfRangeError::new(index,
+                                                           tuple_type,
+                                                           *index_span,
+                                                           *expr_span)
+                                    .into())
+                    }
+                    other => {
+                        let any_tuple = Generic::Abstract(AbstractType::Tuple(Box::new(Generic::Any)));
+                        Err(MismatchedTypesError::new(any_tuple, other, *expr_span).into())
+                    }
+                }
+            }
+
+            Field {
+                ref mut expr,
+                ref expr_span,
+                ref name,
+                ref name_span,
+            } => {
+                let expr_type = known_type(expr.check(env), *expr_span, env)?;
+
+                let field_type = match expr_type {
+                    Type::Named(ref type_name) => {
+                        env.get_type_decl(type_name)
+                            .and_then(|decl| decl.get_field(name))
+                            .map(|field| field.type_.clone())
+                    }
+                    _ => None,
+                };
+
+                field_type.ok_or_else(|| {
+                                          NoSuchFieldError::new(name.clone(), expr_type, *name_span)
+                                              .into()
+                                      })
+            }
+
             Variable { ref name, ref span } => {
-                env.get_var(name)
-                    .map(|var| var.get_type())
-                    .cloned()
-                    .ok_or_else(|| UnboundedVarError::new(name.clone(), *span).into())
+                match env.get_callable(name) {
+                    Some(Callable::Variable(var)) => Ok(applied(var.get_type(), env)),
+                    // A generic function used as a value is instantiated with new type variables
+                    Some(Callable::Function(func)) => {
+                        let func = func.clone();
+                        Ok(instantiate(&func, env))
+                    }
+                    // Variants without values are constructed without parentheses
+                    None => {
+                        match env.get_variant(name) {
+                            Some((decl, variant)) if variant.types.is_empty() => {
+                                Ok(Type::Named(decl.name.clone()))
+                            }
+                            _ => Err(UnboundedVarError::new(name.clone(), *span).into()),
+                        }
+                    }
+                }
             }
 
             Array {
@@ -288,17 +822,27 @@ impl TypeCheck for Expr {
             } => {
                 let first_span;
 
+                // Each element is checked only once, to report its errors only once
+                let types = values
+                    .iter_mut()
+                    .map(|&mut (ref mut expr, _)| expr.check(env))
+                    .collect::<Vec<_>>();
+
                 let type_ = match *declared_type {
-                    Some(ref type_) => {
+                    Some(ref mut type_) => {
+                        // The type may have been deduced by a previous check of this expression
+                        if let Some(declared_type_span) = declared_type_span {
+                            resolve_type_params(type_, env);
+                            check_type_declared(type_, declared_type_span, env)?;
+                        }
                         first_span = None;
                         type_.clone()
                     }
                     None => {
-                        let type_ = values
-                            .get_mut(0)
-                            .map(|&mut (ref mut value, _)| value)
-                            .ok_or_else(|| UntypedEmptyArrayError::new(span))?
-                            .type_check(env)?;
+                        let type_ = types
+                            .get(0)
+                            .cloned()
+                            .ok_or_else(|| UntypedEmptyArrayError::new(span))?;
 
                         first_span = Some(values[0].1);
 
@@ -308,21 +852,16 @@ impl TypeCheck for Expr {
 
                 *declared_type = Some(type_.clone());
 
-                let types = values
-                    .iter_mut()
-                    .map(|&mut (ref mut expr, _)| expr.type_check(env))
-                    .collect::<Result<Vec<_>, _>>()?;
-
                 let intruder = types
                     .iter()
-                    .position(|candidate_type| *candidate_type != type_);
+                    .position(|candidate_type| !unify(candidate_type, &type_, env));
 
                 if let Some(pos) = intruder {
-                    let wrong_type = types[pos].clone();
+                    let wrong_type = applied(&types[pos], env);
                     let span = values[pos].1;
 
                     return Err(InconsistentArrayTypingError {
-                                       expected: type_,
+                                       expected: applied(&type_, env),
                                        got: wrong_type,
                                        argument_id: pos,
                                        span,
@@ -335,33 +874,164 @@ impl TypeCheck for Expr {
                                    .into());
                 }
 
-                Ok(Type::Array(Box::new(type_)))
+                Ok(Type::Array(Box::new(applied(&type_, env))))
             }
 
             Tuple(ref mut exprs) => {
                 let element_types = exprs
                     .iter_mut()
-                    .map(|expr| expr.type_check(env))
-                    .collect::<Result<Vec<_>, _>>()?;
+                    .map(|expr| expr.check(env))
+                    .collect::<Vec<_>>();
+
+                Ok(applied(&Type::Tuple(element_types), env))
+            }
+
+            Record {
+                ref name,
+                ref mut fields,
+                span,
+            } => {
+                let decl = env.get_type_decl(name)
+                    .cloned()
+                    .ok_or_else(|| UndefinedTypeError::new(name.clone(), span))?;
+                let record_type = Type::Named(name.clone());
+
+                let field_decls = match decl.definition {
+                    TypeDefinition::Record(ref field_decls) => field_decls,
+                    TypeDefinition::Sum(_) => {
+                        return Err(NotARecordError::new(name.clone(), span).into())
+                    }
+                };
+
+                for field in fields.iter_mut() {
+                    let field_decl = decl.get_field(&field.name)
+                        .ok_or_else(|| {
+                                        NoSuchFieldError::new(field.name.clone(),
+                                                              record_type.clone(),
+                                                              field.name_span)
+                                    })?;
+
+                    let value_type = field.value.check(env);
+
+                    if !unify(&value_type, &field_decl.type_, env) {
+                        return Err(MismatchedTypesError::new(field_decl.type_.clone().into(),
+                                                             applied(&value_type, env),
+                                                             field.value_span)
+                                           .into());
+                    }
+                }
 
-                Ok(Type::Tuple(element_types))
+                if let Some(missing) = field_decls
+                       .iter()
+                       .find(|field_decl| !fields.iter().any(|field| field.name == field_decl.name)) {
+                    return Err(MissingFieldError::new(missing.name.clone(),
+                                                      missing.type_.clone(),
+                                                      span)
+                                       .into());
+                }
+
+                Ok(record_type)
+            }
+
+            // Lambdas are not generic: their omitted types are deduced from their uses
+            Lambda(ref mut decl) => {
+                resolve_signature(decl, env);
+                decl.check(env);
+                Ok(applied(&decl.get_type(), env))
+            }
+
+            // Like with `as Str`, the embedded expressions must be of a known type convertible to
+            // a Str
+            // The result is a Str whatever the parts, so the errors of a part do not stop the
+            // check of the next ones
+            Interpolation(ref mut parts) => {
+                for part in parts {
+                    if let InterpolationPart::Expr(ref mut expr, span) = *part {
+                        let type_ = expr.check(env);
+                        let converted = known_type(type_, span, env).and_then(|type_| {
+                            if type_.is_convertible_to(&Type::Str) {
+                                Ok(())
+                            } else {
+                                Err(ConversionError::new(type_, Type::Str, span).into())
+                            }
+                        });
+
+                        if let Err(err) = converted {
+                            record_error(err, env);
+                        }
+                    }
+                }
+
+                Ok(Type::Str)
+            }
+
+            Break(span) => check_loop_jump("break", span, env),
+
+            Continue(span) => check_loop_jump("continue", span, env),
+
+            // Like a loop jump, the function is left, so the expression itself is of any type
+            Return {
+                ref mut value,
+                value_span,
+                span,
+            } => {
+                let value_type = value.check(env);
+
+                let decl = match env.function {
+                    Some(ref decl) => decl.clone(),
+                    None => return Err(OutsideFunctionError::new(span).into()),
+                };
+                let return_type = decl.get_return_type();
+
+                if !unify(&value_type, &return_type, env) {
+                    return Err(MismatchedTypesError::from_binding(Declaration::Function(decl),
+                                                                  applied(&return_type, env)
+                                                                      .into(),
+                                                                  applied(&value_type, env),
+                                                                  value_span)
+                                       .into());
+                }
+
+                Ok(env.substitution.fresh())
             }
 
             Value(ref value) => Ok(value.get_type()),
 
+            Wildcard(_) | Rest { .. } => panic!("Pattern outside of a pattern match"),
+
         }
     }
 }
 
-impl TypeCheck for FunctionDecl {
-    fn type_check(&mut self, env: &mut Environment<TypeInfo>) -> Result<Type, TypeCheckError> {
+// Type check a function, failing on its own error (the errors of its body are recorded)
+trait CheckFunction {
+    fn check_function(&mut self, env: &mut Environment<TypeInfo>) -> Result<Type, TypeCheckError>;
+}
+
+impl CheckFunction for FunctionDecl {
+    fn check_function(&mut self, env: &mut Environment<TypeInfo>) -> Result<Type, TypeCheckError> {
+        let return_type = self.get_return_type();
+        check_type_declared(&return_type, self.signature_span, env)?;
+
         env.enter_scope();
 
+        // Inside the body, a type parameter stands for an unknown type
+        for &(ref name, span) in &self.type_params {
+            if !self.args.iter().any(|arg| mentions_type_param(&arg.get_type(), name)) {
+                return Err(UninferableTypeParamError::new(name.clone(), self.name.clone(), span)
+                               .into());
+            }
+
+            env.declare_type_param(name.clone(), Type::Param(name.clone()));
+        }
+
         for arg in &self.args {
+            check_type_declared(&arg.get_type(), arg.span, env)?;
+
             env.declare_var(arg.name.clone(),
                              BindingInfo::Argument {
                                  declaration: arg.clone(),
-                                 info: TypeInfo(arg.type_.clone()),
+                                 info: TypeInfo(arg.get_type()),
                              })
                 .map_err(|mut err| {
                              err.span = arg.span;
@@ -369,18 +1039,707 @@ impl TypeCheck for FunctionDecl {
                          })?;
         }
 
-        let final_type = self.body.type_check(env)?;
+        // The loops around the declaration cannot be stopped from the body, and a `return` only
+        // leaves the innermost function
+        let loop_depth = mem::replace(&mut env.loop_depth, 0);
+        let function = mem::replace(&mut env.function, Some(self.clone()));
+        let final_type = self.body.check(env);
+        env.loop_depth = loop_depth;
+        env.function = function;
 
-        if final_type != self.return_type {
+        if !unify(&final_type, &return_type, env) {
             return Err(MismatchedTypesError::from_binding(Declaration::Function(self.clone()),
-                                                          self.return_type.clone().into(),
-                                                          final_type,
+                                                          applied(&return_type, env).into(),
+                                                          applied(&final_type, env),
                                                           self.body_span)
                                .into());
         }
 
         env.leave_scope();
-        Ok(self.return_type.clone())
+        Ok(applied(&return_type, env))
+    }
+}
+
+// Type check the body of a loop, where `break` and `continue` are allowed
+fn check_loop_body(body: &mut Expr, env: &mut Environment<TypeInfo>) {
+    env.loop_depth += 1;
+    check_in_scope(body, env);
+    env.loop_depth -= 1;
+}
+
+// Type check an expression which may not be evaluated (a branch, the body of a loop, ...) in its
+// own scope: the variables bound by its pattern matches are not declared after it
+fn check_in_scope(expr: &mut Expr, env: &mut Environment<TypeInfo>) -> Type {
+    env.enter_scope();
+    let type_ = expr.check(env);
+    env.leave_scope();
+    type_
+}
+
+// Type check a `break` or a `continue`
+//
+// The loop is left or restarted, so the value of the expression never matters: it is of any type
+// (like `if found then break else x`).
+fn check_loop_jump(keyword: &str,
+                   span: Span,
+                   env: &mut Environment<TypeInfo>)
+                   -> Result<Type, TypeCheckError> {
+    if env.loop_depth == 0 {
+        return Err(OutsideLoopError::new(keyword.to_string(), span).into());
+    }
+
+    Ok(env.substitution.fresh())
+}
+
+// Check that every user-declared type used inside a given type is declared
+fn check_type_declared(type_: &Type,
+                       span: Span,
+                       env: &Environment<TypeInfo>)
+                       -> Result<(), TypeCheckError> {
+    match *type_ {
+        Type::Array(ref element_type) => check_type_declared(element_type, span, env),
+        Type::Tuple(ref types) => {
+            for type_ in types {
+                check_type_declared(type_, span, env)?;
+            }
+            Ok(())
+        }
+        Type::Function(ref args, ref return_type) => {
+            for arg in args {
+                check_type_declared(arg, span, env)?;
+            }
+            check_type_declared(return_type, span, env)
+        }
+        Type::Named(ref name) if env.get_type_decl(name).is_none() => {
+            Err(UndefinedTypeError::new(name.clone(), span).into())
+        }
+        Type::Void | Type::Integer | Type::Float | Type::Bool | Type::Str | Type::Named(_) |
+        Type::Param(_) | Type::Var(_) => Ok(()),
+    }
+}
+
+// Tell if a type has a finite value, without going through the user types being built (the ones in
+// `building`)
+//
+// Functions always have a value, whatever their return type.
+fn is_inhabited(type_: &Type, env: &Environment<TypeInfo>, building: &mut Vec<String>) -> bool {
+    match *type_ {
+        Type::Tuple(ref types) => types.iter().all(|type_| is_inhabited(type_, env, building)),
+        Type::Named(ref name) if building.contains(name) => false,
+        Type::Named(ref name) => {
+            let definition = match env.get_type_decl(name) {
+                Some(decl) => decl.definition.clone(),
+                None => return true,
+            };
+
+            building.push(name.clone());
+
+            let inhabited = match definition {
+                TypeDefinition::Record(ref fields) => {
+                    fields
+                        .iter()
+                        .all(|field| is_inhabited(&field.type_, env, building))
+                }
+                TypeDefinition::Sum(ref variants) => {
+                    variants
+                        .iter()
+                        .any(|variant| {
+                                 variant
+                                     .types
+                                     .iter()
+                                     .all(|type_| is_inhabited(type_, env, building))
+                             })
+                }
+            };
+
+            building.pop();
+            inhabited
+        }
+        Type::Void | Type::Integer | Type::Float | Type::Bool | Type::Str | Type::Array(_) |
+        Type::Function(..) | Type::Param(_) | Type::Var(_) => true,
+    }
+}
+
+/// Turn the names of the type parameters in scope used inside a given type into type parameters
+///
+/// The parser cannot tell a type parameter from a user-declared type: both are parsed as named
+/// types.
+pub fn resolve_type_params(type_: &mut Type, env: &Environment<TypeInfo>) {
+    let param = match *type_ {
+        Type::Array(ref mut element_type) => {
+            resolve_type_params(element_type, env);
+            None
+        }
+        Type::Tuple(ref mut types) => {
+            for type_ in types {
+                resolve_type_params(type_, env);
+            }
+            None
+        }
+        Type::Function(ref mut args, ref mut return_type) => {
+            for arg in args {
+                resolve_type_params(arg, env);
+            }
+            resolve_type_params(return_type, env);
+            None
+        }
+        Type::Named(ref name) if env.is_type_param(name) => Some(name.clone()),
+        Type::Void | Type::Integer | Type::Float | Type::Bool | Type::Str | Type::Named(_) |
+        Type::Param(_) | Type::Var(_) => None,
+    };
+
+    if let Some(name) = param {
+        *type_ = Type::Param(name);
+    }
+}
+
+// Resolve the type parameters used in the signature of a function (its own type parameters, and
+// those of the enclosing generic functions), and replace the omitted types by new type variables
+fn resolve_signature(decl: &mut FunctionDecl, env: &mut Environment<TypeInfo>) {
+    env.enter_scope();
+
+    for &(ref name, _) in &decl.type_params {
+        env.declare_type_param(name.clone(), Type::Param(name.clone()));
+        env.declared_type_params.insert(name.clone());
+    }
+
+    for arg in &mut decl.args {
+        match arg.type_ {
+            Some(ref mut type_) => resolve_type_params(type_, env),
+            None => arg.type_ = Some(env.substitution.fresh()),
+        }
+    }
+
+    match decl.return_type {
+        Some(ref mut type_) => resolve_type_params(type_, env),
+        None => decl.return_type = Some(env.substitution.fresh()),
+    }
+
+    env.leave_scope();
+}
+
+// Get the type of a function used as a value or called, where its own type parameters are
+// replaced by new type variables
+fn instantiate(func: &FunctionDecl, env: &mut Environment<TypeInfo>) -> Type {
+    let vars = func.type_params
+        .iter()
+        .map(|&(ref name, _)| (name.clone(), env.substitution.fresh()))
+        .collect::<Vec<_>>();
+
+    applied(&func.get_type(), env).substitute(&|name| {
+        vars.iter()
+            .find(|&&(ref param, _)| param == name)
+            .map(|&(_, ref var)| var.clone())
+    })
+}
+
+// Turn the type variables of the signatures of functions declared together, which are not used
+// by the enclosing scopes, into type parameters of these functions (let-polymorphism)
+//
+// The functions are declared in the current scope, and their declarations are updated.
+fn generalize(decls: &mut [FunctionDecl],
+              env: &mut Environment<TypeInfo>)
+              -> Result<(), TypeCheckError> {
+    let mut env_vars = Vec::new();
+
+    for (depth, scope) in env.scopes.iter().enumerate() {
+        for var in scope.variables.values() {
+            env_vars.extend(applied(var.get_type(), env).free_vars());
+        }
+
+        for (name, func) in &scope.functions {
+            if depth == 0 && decls.iter().any(|decl| decl.name == *name) {
+                continue;
+            }
+
+            env_vars.extend(applied(&func.get_type(), env).free_vars());
+        }
+    }
+
+    // Every function must get the type parameters of its whole signature, so the variables are
+    // bound only once they are all known
+    let mut generalized = Vec::new();
+
+    for decl in decls.iter() {
+        let arg_vars = applied(&Type::Tuple(decl.args.iter().map(ArgumentDecl::get_type).collect()),
+                               env)
+                .free_vars();
+        let mut vars = Vec::new();
+
+        for id in applied(&decl.get_type(), env).free_vars() {
+            if env_vars.contains(&id) {
+                continue;
+            }
+
+            // At runtime, the type parameters are deduced from the arguments
+            if !arg_vars.contains(&id) {
+                return Err(TypeAnnotationNeededError::new(applied(&decl.get_return_type(), env),
+                                                          decl.signature_span)
+                                   .into());
+            }
+
+            vars.push(id);
+        }
+
+        generalized.push(vars);
+    }
+
+    for &id in generalized.iter().flat_map(|vars| vars) {
+        let name = param_name(id, env);
+        env.substitution.bind(id, Type::Param(name));
+    }
+
+    for (decl, vars) in decls.iter_mut().zip(generalized) {
+        for id in vars {
+            decl.type_params.push((param_name(id, env), decl.signature_span));
+        }
+
+        for arg in &mut decl.args {
+            arg.type_ = Some(applied(&arg.get_type(), env));
+        }
+        decl.return_type = Some(applied(&decl.get_return_type(), env));
+
+        env.update_func(decl.clone());
+    }
+
+    Ok(())
+}
+
+// Name the type parameter deduced from a type variable: the name of the variable in uppercase
+// (like `A`), followed by underscores if needed to differ from the types and the type parameters
+// declared so far (it could shadow them in the printed program)
+fn param_name(id: usize, env: &Environment<TypeInfo>) -> String {
+    let mut name = type_sys::var_name(id).to_uppercase();
+
+    while env.declared_types.contains_key(&name) || env.declared_type_params.contains(&name) {
+        name.push('_');
+    }
+
+    name
+}
+
+// Make two types equal, by deducing the type variables they contain (see the
+// `type_sys::Substitution::unify` method)
+fn unify(lhs: &Type, rhs: &Type, env: &mut Environment<TypeInfo>) -> bool {
+    env.substitution.unify(lhs, rhs)
+}
+
+// Make two lists of types equal
+//
+// Nothing is deduced if they cannot be made equal.
+fn unify_all(lhs: &[Type], rhs: &[Type], env: &mut Environment<TypeInfo>) -> bool {
+    let snapshot = env.substitution.snapshot();
+
+    if lhs.len() == rhs.len() && lhs.iter().zip(rhs).all(|(lhs, rhs)| unify(lhs, rhs, env)) {
+        true
+    } else {
+        env.substitution.rollback(snapshot);
+        false
+    }
+}
+
+// Get a type where the type variables are replaced by what was deduced for them
+fn applied(type_: &Type, env: &Environment<TypeInfo>) -> Type {
+    env.substitution.apply(type_)
+}
+
+fn applied_all(types: &[Type], env: &Environment<TypeInfo>) -> Vec<Type> {
+    types.iter().map(|type_| applied(type_, env)).collect()
+}
+
+// Get the type of an expression whose structure must be known (to index it, to read one of its
+// fields...)
+fn known_type(type_: Type,
+              span: Span,
+              env: &Environment<TypeInfo>)
+              -> Result<Type, TypeCheckError> {
+    match applied(&type_, env) {
+        Type::Var(id) => Err(TypeAnnotationNeededError::new(Type::Var(id), span).into()),
+        type_ => Ok(type_),
+    }
+}
+
+// Check that the type of a condition (of a if, a while or a guard) may be truthy
+//
+// A condition of unknown type is deduced to be a Bool.
+fn check_condition(type_: &Type,
+                   span: Span,
+                   env: &mut Environment<TypeInfo>)
+                   -> Result<(), TypeCheckError> {
+    if let Type::Var(_) = applied(type_, env) {
+        unify(type_, &Type::Bool, env);
+    }
+
+    let type_ = applied(type_, env);
+    if !type_.may_truthy() {
+        return Err(ConversionError::new(type_, Type::Bool, span).into());
+    }
+
+    Ok(())
+}
+
+// Whether an Integer operand of a given operator is promoted to a Float (see `promote_operands`)
+fn promotes_operands(op: BinaryOpCode) -> bool {
+    use ast::BinaryOpCode::*;
+
+    match op {
+        Add | Sub | Mul | Div | Mod | Pow | Lt | Le | Gt | Ge | Eq | Ne => true,
+        IntDiv | BitAnd | BitOr | BitXor | Shl | Shr => false,
+    }
+}
+
+// Whether the operands of a given operator must be of the same type
+fn is_comparison(op: BinaryOpCode) -> bool {
+    use ast::BinaryOpCode::*;
+
+    match op {
+        Lt | Le | Gt | Ge | Eq | Ne => true,
+        _ => false,
+    }
+}
+
+// Cast an Integer operand to a Float if the other operand is a Float
+//
+// `1 + 2.5` is type checked as `(1 as Float) + 2.5`. The cast cannot fail, so it is simply located
+// at the whole operation.
+fn promote_operands(lhs: &mut Box<Expr>,
+                    lhs_type: &mut Type,
+                    rhs: &mut Box<Expr>,
+                    rhs_type: &mut Type,
+                    span: Span,
+                    env: &Environment<TypeInfo>) {
+    let (promoted, promoted_type) = match (applied(lhs_type, env), applied(rhs_type, env)) {
+        (Type::Integer, Type::Float) => (lhs, lhs_type),
+        (Type::Float, Type::Integer) => (rhs, rhs_type),
+        _ => return,
+    };
+
+    let expr = mem::replace(promoted, Box::new(Expr::Value(type_sys::Value::Void)));
+    *promoted = Box::new(Expr::Cast {
+                             expr,
+                             expr_span: span,
+                             dest: Type::Float,
+                         });
+    *promoted_type = Type::Float;
+}
+
+// Get the return type of a builtin called with arguments of the given types
+//
+// Builtins are overloaded: the type variables inside the types of the arguments are deduced from
+// the only signature that may match. If several signatures may match, the types cannot be
+// inferred.
+fn builtin_return_type(name: &str,
+                       arg_types: &[Type],
+                       span: Span,
+                       env: &mut Environment<TypeInfo>)
+                       -> Result<Type, TypeCheckError> {
+    let arg_types = applied_all(arg_types, env);
+    let no_such_signature = || NoSuchSignatureError::new(name.to_string(), arg_types.clone(), span);
+
+    let builtin = env.builtins
+        .get(name)
+        .ok_or_else(|| UndefinedFunctionError::new(name.to_string(), span))?;
+
+    if arg_types.iter().all(|type_| type_.free_vars().is_empty()) {
+        return builtin
+                   .return_type(&arg_types, &env.types)
+                   .ok_or_else(|| no_such_signature().into());
+    }
+
+    let mut candidates = Vec::new();
+
+    for (params, return_type) in &builtin.signatures {
+        if params.len() != arg_types.len() {
+            continue;
+        }
+
+        let mut substitution = env.substitution.snapshot();
+
+        let matches = params
+            .iter()
+            .zip(&arg_types)
+            .all(|(param, arg_type)| match *param {
+                     Generic::Builtin(ref type_) => substitution.unify(type_, arg_type),
+                     // Nothing can be deduced from the other generic types
+                     _ => {
+                         !arg_type.free_vars().is_empty() || param.match_with(arg_type, &env.types)
+                     }
+                 });
+
+        if matches {
+            candidates.push((substitution, return_type.clone()));
+        }
+    }
+
+    match candidates.len() {
+        0 => Err(no_such_signature().into()),
+        1 => {
+            let (substitution, return_type) = candidates.pop().unwrap();
+            env.substitution = substitution;
+            Ok(return_type)
+        }
+        _ => {
+            // The type of an erroneous argument is reported, so that the error is only recorded if
+            // it is not caused by a previous error
+            let unknown = arg_types
+                .iter()
+                .find(|type_| env.substitution.is_erroneous(type_))
+                .or_else(|| arg_types.iter().find(|type_| !type_.free_vars().is_empty()))
+                .unwrap();
+            Err(TypeAnnotationNeededError::new(unknown.clone(), span).into())
+        }
+    }
+}
+
+// Tells if a given type parameter is used inside a given type
+fn mentions_type_param(type_: &Type, name: &str) -> bool {
+    match *type_ {
+        Type::Array(ref element_type) => mentions_type_param(element_type, name),
+        Type::Tuple(ref types) => types.iter().any(|type_| mentions_type_param(type_, name)),
+        Type::Function(ref args, ref return_type) => {
+            args.iter().any(|arg| mentions_type_param(arg, name)) ||
+            mentions_type_param(return_type, name)
+        }
+        Type::Param(ref param) => param == name,
+        Type::Void | Type::Integer | Type::Float | Type::Bool | Type::Str | Type::Named(_) |
+        Type::Var(_) => false,
+    }
+}
+
+// Write the inferred types inside a part of the AST, once the outermost type check is done
+//
+// The types which could not be inferred are added to the errors.
+trait ResolveInferred {
+    fn resolve_inferred(&mut self,
+                        substitution: &Substitution,
+                        errors: &mut Vec<TypeCheckError>);
+}
+
+impl ResolveInferred for Exprs {
+    fn resolve_inferred(&mut self,
+                        substitution: &Substitution,
+                        errors: &mut Vec<TypeCheckError>) {
+        for &mut (ref mut expr, _) in &mut self.exprs {
+            expr.resolve_inferred(substitution, errors);
+        }
+
+        // The unknown types (of jumps, for instance) are left as is
+        for type_ in &mut self.types {
+            *type_ = substitution.apply(type_);
+        }
+    }
+}
+
+impl ResolveInferred for Expr {
+    fn resolve_inferred(&mut self,
+                        substitution: &Substitution,
+                        errors: &mut Vec<TypeCheckError>) {
+        use ast::Expr::*;
+
+        match *self {
+            Grouping(ref mut exprs) => exprs.resolve_inferred(substitution, errors),
+            Let(_, ref mut bindings, ref mut function_decls, ref mut exprs) => {
+                for binding in bindings {
+                    binding.value.resolve_inferred(substitution, errors);
+                }
+                for function_decl in function_decls {
+                    function_decl.resolve_inferred(substitution, errors);
+                }
+                exprs.resolve_inferred(substitution, errors)
+            }
+            Assign { ref mut value, .. } |
+            Cast { expr: ref mut value, .. } |
+            TryCast { expr: ref mut value, .. } |
+            UnaryOp { expr: ref mut value, .. } |
+            Projection { expr: ref mut value, .. } |
+            Field { expr: ref mut value, .. } => value.resolve_inferred(substitution, errors),
+            ElementAssign {
+                ref mut target,
+                ref mut value,
+                ..
+            } => {
+                target.resolve_inferred(substitution, errors);
+                value.resolve_inferred(substitution, errors)
+            }
+            PatternMatch {
+                ref mut lhs,
+                ref mut rhs,
+                ref mut bindings,
+                ..
+            } => {
+                for binding in bindings {
+                    resolve_inferred_type(&mut binding.type_, binding.span, substitution, errors);
+                }
+                lhs.resolve_inferred(substitution, errors);
+                rhs.resolve_inferred(substitution, errors)
+            }
+            Function { ref mut args, .. } => {
+                for &mut (ref mut arg, _) in args {
+                    arg.resolve_inferred(substitution, errors);
+                }
+            }
+            If {
+                ref mut cond,
+                ref mut true_branch,
+                ref mut false_branch,
+                ref mut true_branch_type,
+                ..
+            } => {
+                cond.resolve_inferred(substitution, errors);
+                true_branch.resolve_inferred(substitution, errors);
+
+                if let Some(ref mut true_branch_type) = *true_branch_type {
+                    *true_branch_type = substitution.apply(true_branch_type);
+                }
+
+                if let Some((ref mut false_branch, _)) = *false_branch {
+                    false_branch.resolve_inferred(substitution, errors);
+                }
+            }
+            Case {
+                ref mut expr,
+                ref mut arms,
+                ..
+            } => {
+                expr.resolve_inferred(substitution, errors);
+
+                for arm in arms {
+                    for binding in &mut arm.bindings {
+                        resolve_inferred_type(&mut binding.type_,
+                                              binding.span,
+                                              substitution,
+                                              errors);
+                    }
+                    arm.pattern.resolve_inferred(substitution, errors);
+                    if let Some((ref mut guard, _)) = arm.guard {
+                        guard.resolve_inferred(substitution, errors);
+                    }
+                    arm.body.resolve_inferred(substitution, errors);
+                }
+            }
+            While {
+                ref mut cond,
+                ref mut expr,
+                ..
+            } => {
+                cond.resolve_inferred(substitution, errors);
+                expr.resolve_inferred(substitution, errors)
+            }
+            For {
+                ref mut binding,
+                ref mut goal,
+                ref mut step,
+                ref mut expr,
+                ..
+            } => {
+                binding.value.resolve_inferred(substitution, errors);
+                goal.resolve_inferred(substitution, errors);
+                if let Some((ref mut step, _)) = *step {
+                    step.resolve_inferred(substitution, errors);
+                }
+                expr.resolve_inferred(substitution, errors)
+            }
+            ForIn {
+                ref mut iterable,
+                ref mut expr,
+                ..
+            } => {
+                iterable.resolve_inferred(substitution, errors);
+                expr.resolve_inferred(substitution, errors)
+            }
+            BinaryOp {
+                ref mut lhs,
+                ref mut rhs,
+                ..
+            } |
+            LogicalOp {
+                ref mut lhs,
+                ref mut rhs,
+                ..
+            } |
+            Index {
+                expr: ref mut lhs,
+                index: ref mut rhs,
+                ..
+            } => {
+                lhs.resolve_inferred(substitution, errors);
+                rhs.resolve_inferred(substitution, errors)
+            }
+            Array {
+                ref mut values,
+                ref mut declared_type,
+                span,
+                ..
+            } => {
+                if let Some(ref mut declared_type) = *declared_type {
+                    resolve_inferred_type(declared_type, span, substitution, errors);
+                }
+                for &mut (ref mut value, _) in values {
+                    value.resolve_inferred(substitution, errors);
+                }
+            }
+            Tuple(ref mut exprs) => {
+                for expr in exprs {
+                    expr.resolve_inferred(substitution, errors);
+                }
+            }
+            Record { ref mut fields, .. } => {
+                for field in fields {
+                    field.value.resolve_inferred(substitution, errors);
+                }
+            }
+            Lambda(ref mut decl) => decl.resolve_inferred(substitution, errors),
+            Interpolation(ref mut parts) => {
+                for part in parts {
+                    if let InterpolationPart::Expr(ref mut expr, _) = *part {
+                        expr.resolve_inferred(substitution, errors);
+                    }
+                }
+            }
+            Return { ref mut value, .. } => value.resolve_inferred(substitution, errors),
+            Variable { .. } | Break(_) | Continue(_) | Wildcard(_) | Rest { .. } |
+            Value(_) => {}
+        }
+    }
+}
+
+impl ResolveInferred for FunctionDecl {
+    fn resolve_inferred(&mut self,
+                        substitution: &Substitution,
+                        errors: &mut Vec<TypeCheckError>) {
+        for arg in &mut self.args {
+            if let Some(ref mut type_) = arg.type_ {
+                resolve_inferred_type(type_, arg.span, substitution, errors);
+            }
+        }
+
+        if let Some(ref mut type_) = self.return_type {
+            resolve_inferred_type(type_, self.signature_span, substitution, errors);
+        }
+
+        self.body.resolve_inferred(substitution, errors)
+    }
+}
+
+// Replace the type variables inside a type by what was deduced for them
+//
+// An unknown type is reported once, where it is first met.
+fn resolve_inferred_type(type_: &mut Type,
+                         span: Span,
+                         substitution: &Substitution,
+                         errors: &mut Vec<TypeCheckError>) {
+    *type_ = substitution.apply(type_);
+
+    let reported = errors
+        .iter()
+        .flat_map(|err| match *err {
+                      TypeCheckError::TypeAnnotationNeeded(ref err) => err.type_.free_vars(),
+                      _ => Vec::new(),
+                  })
+        .collect::<Vec<_>>();
+
+    if type_.free_vars().iter().any(|var| !reported.contains(var)) {
+        errors.push(TypeAnnotationNeededError::new(type_.clone(), span).into());
     }
 }
 
@@ -388,7 +1747,9 @@ impl TypeCheck for FunctionDecl {
 mod tests {
     use super::TypeCheck;
 
+    use ast::{Expr, Span};
     use env::Environment;
+    use processing::Print;
     use error::*;
     use parser;
     use type_sys::Generic;
@@ -401,7 +1762,10 @@ mod tests {
                 .unwrap()
                 .type_check(&mut Environment::new());
             assert!(match res {
-                Err($err) => true,
+                Err(ref errors) if errors.len() == 1 => match errors[0] {
+                    $err => true,
+                    _ => false,
+                },
                 _ => false,
             });
         };
@@ -411,7 +1775,10 @@ mod tests {
                 .unwrap()
                 .type_check(&mut Environment::new());
             assert!(match res {
-                Err($err) if $guard => true,
+                Err(ref errors) if errors.len() == 1 => match errors[0] {
+                    $err if $guard => true,
+                    _ => false,
+                },
                 _ => false,
             });
         };
@@ -431,7 +1798,7 @@ mod tests {
     fn grouping() {
 
         // Report inner errors
-        assert_err!("(2, 3+4.)",
+        assert_err!("(2, 3+true)",
             TypeCheckError::NoSuchSignature(
                 NoSuchSignatureError { ref func_name, .. }
                 )
@@ -452,11 +1819,11 @@ mod tests {
         assert_type!("let in true, 2 end", Integer);
 
         // Report inner errors
-        assert_err!("let in 2 + 3.4 end",
+        assert_err!("let in 2 + true end",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
         assert_err!("let
-                        var x := 2 + 3.4
+                        var x := 2 + true
                     in
                     end",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
@@ -533,7 +1900,7 @@ mod tests {
     fn assign() {
         use ast::Declaration;
 
-        assert_err!("let var x := 0 in x := 2+3.4 end",
+        assert_err!("let var x := 0 in x := 2+true end",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
 
@@ -572,6 +1939,15 @@ mod tests {
     fn pattern_match() {
         assert_type!("match 1 := 1", Bool);
 
+        // The variables bound in a branch or a loop body are not declared after it
+        assert_err!(r#"let var x := "a" in while 0 do match x := 1, x + 1 end"#,
+                    TypeCheckError::NoSuchSignature(..));
+        assert_err!(r#"let var x := "a" in if x = "b" then match x := 1, x + 1 end"#,
+                    TypeCheckError::NoSuchSignature(..));
+        assert_err!(r#"let var x := "a" in x = "b" and (match x := 1), x + 1 end"#,
+                    TypeCheckError::NoSuchSignature(..));
+        assert_type!(r#"let var x := "a" in match x := 1, x + 1 end"#, Integer);
+
         // Real pattern match tests are in the src/processing/pattern_match.rs and
         // src/processing/pattern_match_check.rs files.
     }
@@ -585,7 +1961,7 @@ mod tests {
         assert_err!("let
                         function x(x: Bool): Bool := true
                     in
-                        x(2 + 3.4)
+                        x(2 + true)
                     end",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
@@ -593,7 +1969,7 @@ mod tests {
         assert_err!("let
                         function x(x: Bool, y: Bool): Bool := true
                     in
-                        x(true, 2 + 3.4)
+                        x(true, 2 + true)
                     end",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
@@ -643,29 +2019,281 @@ mod tests {
                         fact(5)
                      end",
                      Integer);
-    }
 
-    #[test]
-    fn if_block() {
-        assert_err!("if 2+3.4 then 1 else 2",
-                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
-                    if func_name == "+");
-        assert_err!("if true then 2+3.4 else 2",
-                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
-                    if func_name == "+");
-        assert_err!("if true then 1 else 2+3.4",
-                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
-                    if func_name == "+");
+        assert_type!("let
+                        function is_even(n: Integer): Bool := if n then is_odd(n - 1) else true
+                        function is_odd(n: Integer): Bool := if n then is_even(n - 1) else false
+                     in
+                        is_even(42)
+                     end",
+                     Bool);
 
-        assert_type!("if true then true else false", Bool);
-        assert_type!("if true then 1 else 2", Integer);
-        assert_type!("if 1 then 1 else 2", Integer);
-        assert_type!("if [42] then 1 else 2", Integer);
+        // Names are resolved where the function is declared, not where it is called
+        assert_err!("let
+                        function f(): Integer := x
+                     in
+                        let
+                           var x := 1
+                        in
+                           f()
+                        end
+                     end",
+                    TypeCheckError::UnboundedVar(UnboundedVarError { ref name, .. })
+                    if name == "x");
+    }
 
-        assert_err!("if true then true else 2",
-                    TypeCheckError::IncompatibleArmTypes(IncompatibleArmTypesError {
-                        expected: Bool,
-                        got: Integer,
+    #[test]
+    fn first_class_function() {
+        assert_type!("function(x: Integer): Integer := x",
+                     Function(vec![Integer], Box::new(Integer)));
+        assert_type!("let
+                        function double(x: Integer): Integer := x * 2
+                     in
+                        double
+                     end",
+                     Function(vec![Integer], Box::new(Integer)));
+        assert_type!("let
+                        var f := function(x: Integer, y: Bool): Bool := y
+                     in
+                        f(1, true)
+                     end",
+                     Bool);
+        assert_type!("let
+                        function apply(f: Function(Integer): Integer, x: Integer): Integer := f(x)
+                     in
+                        apply(function(x: Integer): Integer := x + 1, 41)
+                     end",
+                     Integer);
+
+        // Arguments shadow functions of the same name
+        assert_type!("let
+                        function f(): Bool := true
+                        function g(f: Function(): Integer): Integer := f()
+                     in
+                        g(function(): Integer := 42)
+                     end",
+                     Integer);
+
+        assert_err!("function(x: Integer): Bool := x",
+                    TypeCheckError::MismatchedTypes(MismatchedTypesError { .. }));
+        assert_err!("let
+                        var f := function(x: Integer): Integer := x
+                     in
+                        f(true)
+                     end",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "f");
+        assert_err!("let
+                        var f := 42
+                     in
+                        f(1)
+                     end",
+                    TypeCheckError::NotCallable(NotCallableError { ref name, type_: Integer, .. })
+                    if name == "f");
+        assert_err!("let
+                        function apply(f: Function(Integer): Integer, x: Integer): Integer := f(x)
+                     in
+                        apply(function(x: Bool): Integer := 1, 41)
+                     end",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "apply");
+    }
+
+    #[test]
+    fn generic_function() {
+        assert_type!("let
+                        function id<T>(x: T): T := x
+                     in
+                        {id(1), id(\"hello\"), id([true])}
+                     end",
+                     Tuple(vec![Integer, Str, Array(Box::new(Bool))]));
+        assert_type!("let
+                        function first<T, U>(pair: Tuple(T, U)): T := pair.0
+                        function apply<T>(f: Function(T): T, x: T): T := f(x)
+                        function double(x: Integer): Integer := x * 2
+                     in
+                        apply(double, first({1, 2.}))
+                     end",
+                     Integer);
+        assert_type!("let
+                        function singleton<T>(x: T): Array(T) := [x]
+                        function twice<T>(x: T): Array(T) := [x, x]
+                     in
+                        {singleton(1.), twice(true)}
+                     end",
+                     Tuple(vec![Array(Box::new(Float)), Array(Box::new(Bool))]));
+
+        // The same type parameter must be instantiated with the same type
+        assert_err!("let
+                        function pair<T>(x: T, y: T): Tuple(T, T) := {x, y}
+                     in
+                        pair(1, 2.)
+                     end",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "pair");
+
+        // Nothing is known about a type parameter inside the function
+        assert_err!("let
+                        function add<T>(x: T, y: T): T := x + y
+                     in
+                        add(1, 2)
+                     end",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "+");
+        assert_err!("let
+                        function to_int<T>(x: T): Integer := x
+                     in
+                        to_int(1)
+                     end",
+                    TypeCheckError::MismatchedTypes(MismatchedTypesError { ref got, .. })
+                    if *got == Param("T".to_string()));
+
+        assert_err!("let
+                        function make<T>(): T := make()
+                     in
+                        1
+                     end",
+                    TypeCheckError::UninferableTypeParam(UninferableTypeParamError { ref name, .. })
+                    if name == "T");
+    }
+
+    #[test]
+    fn inference() {
+        // The omitted types are deduced from the body...
+        assert_type!("let
+                        function fact(n) := if n = 0 then 1 else n * fact(n - 1)
+                     in
+                        fact(5)
+                     end",
+                     Integer);
+        assert_type!("let
+                        function pair(x, y: Str) := {x + 1., y}
+                     in
+                        pair
+                     end",
+                     Function(vec![Float, Str], Box::new(Tuple(vec![Float, Str]))));
+
+        // ... and, for lambdas, from their uses
+        assert_type!("let var f := function(x) := x in f(1.) end", Float);
+        assert_type!("let
+                        var xs := [1, 2]
+                        var f := function(i) := xs[i]
+                     in
+                        f
+                     end",
+                     Function(vec![Integer], Box::new(Integer)));
+
+        // What is not constrained becomes a type parameter of the function
+        assert_type!("let
+                        function id(x) := x
+                        function apply(f, x) := f(x)
+                     in
+                        {id(1), id(\"a\"), apply(id, true), apply(function(x) := [x], 2.)}
+                     end",
+                     Tuple(vec![Integer, Str, Bool, Array(Box::new(Float))]));
+
+        // Once printed, the deduced type parameters can be parsed again, without shadowing the
+        // declared types
+        let mut ast = parser::parse_Expression("let
+                                                   type A = {a: Integer}
+                                                   function pair(x, y) := {x, y}
+                                                   function get(a: A, x) := {a.a, x}
+                                                in
+                                                   pair(1, get(A {a = 2}, \"b\"))
+                                                end")
+                .unwrap();
+        ast.type_check(&mut Environment::new()).unwrap();
+        let printed = ast.pretty_print(0);
+        assert!(printed.contains("function pair<A_, B>(x: A_, y: B) : Tuple(A_, B) :="));
+        let mut printed_ast = parser::parse_Expression(&printed).unwrap();
+        assert_eq!(printed_ast.type_check(&mut Environment::new()),
+                   Ok(Tuple(vec![Integer, Tuple(vec![Integer, Str])])));
+
+        // Patterns tell the type of the matched value
+        assert_type!("let
+                        function head(xs, default) := case xs of [x, ..] => x | _ => default end
+                     in
+                        head([\"a\"], \"b\")
+                     end",
+                     Str);
+
+        // The inferred types are reported in the errors
+        assert_err!("let
+                        function twice(x) := x * 2
+                     in
+                        twice(1.)
+                     end",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError {
+                        function_type: Some((ref function_type, _)), ..
+                    })
+                    if *function_type == Function(vec![Integer], Box::new(Integer)));
+        assert_err!("let
+                        function f(x) := if x then x else 1.
+                     in
+                        1
+                     end",
+                    TypeCheckError::IncompatibleArmTypes(IncompatibleArmTypesError {
+                        ref expected, ..
+                    })
+                    if *expected == Bool);
+
+        // The overload of a builtin must be known
+        assert_err!("let
+                        function add(x, y) := x + y
+                     in
+                        add(1, 2)
+                     end",
+                    TypeCheckError::TypeAnnotationNeeded(_));
+        assert_err!("let
+                        function first(xs) := xs[0]
+                     in
+                        first([1])
+                     end",
+                    TypeCheckError::TypeAnnotationNeeded(_));
+        assert_err!("function(x) := x", TypeCheckError::TypeAnnotationNeeded(_));
+
+        // Every unknown type is reported
+        let errors = parser::parse_Expression("{function(x) := x, function(y) := {y}}")
+            .unwrap()
+            .type_check(&mut Environment::new())
+            .unwrap_err();
+        assert_eq!(errors
+                       .iter()
+                       .map(|err| match *err {
+                                TypeCheckError::TypeAnnotationNeeded(ref err) => err.span,
+                                _ => Span(0, 0),
+                            })
+                       .collect::<Vec<_>>(),
+                   vec![Span(10, 11), Span(28, 29)]);
+        assert_err!("let
+                        function loop(x: Integer) := loop(x)
+                     in
+                        1
+                     end",
+                    TypeCheckError::TypeAnnotationNeeded(_));
+    }
+
+    #[test]
+    fn if_block() {
+        assert_err!("if 2+true then 1 else 2",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "+");
+        assert_err!("if true then 2+true else 2",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "+");
+        assert_err!("if true then 1 else 2+true",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "+");
+
+        assert_type!("if true then true else false", Bool);
+        assert_type!("if true then 1 else 2", Integer);
+        assert_type!("if 1 then 1 else 2", Integer);
+        assert_type!("if [42] then 1 else 2", Integer);
+
+        assert_err!("if true then true else 2",
+                    TypeCheckError::IncompatibleArmTypes(IncompatibleArmTypesError {
+                        expected: Bool,
+                        got: Integer,
                         ..
                     }));
 
@@ -675,14 +2303,66 @@ mod tests {
                         to: Bool,
                         ..
                     }));
+
+        assert_type!("if true then 1", Void);
+        assert_type!("let var x := 1 in if true then x := 2 end", Void);
+        assert_err!("if true then 2+true",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "+");
+    }
+
+    #[test]
+    fn case_block() {
+        assert_type!("let var x := 0 in case 1 of 1 => true | x when x > 1 => false | x => true end end",
+                     Bool);
+        assert_type!("let
+                        type Shape = Circle(Float) | Rect(Float, Float)
+                        var a := 0.
+                        var b := 0.
+                     in
+                        case Rect(1., 2.) of Circle(a) => a | Rect(a, b) => a * b end
+                     end",
+                     Float);
+
+        assert_err!("case 1 of 1 => true | 2 => 3 end",
+                    TypeCheckError::IncompatibleCaseArmTypes(IncompatibleCaseArmTypesError {
+                        expected: Bool,
+                        got: Integer,
+                        ..
+                    }));
+        assert_err!("case 1 of 1. => true end",
+                    TypeCheckError::MismatchedTypes(MismatchedTypesError {
+                        expected: Generic::Builtin(Float),
+                        got: Integer,
+                        ..
+                    }));
+        assert_err!(r#"let var x := 0 in case 1 of x when "true" => 1 | x => 2 end end"#,
+                    TypeCheckError::Conversion(ConversionError { from: Str, to: Bool, .. }));
+
+        // The parser only builds valid patterns, but the AST may come from elsewhere
+        let mut ast = parser::parse_Expression("case 1 of x => 2 end").unwrap();
+        if let Expr::Case { ref mut arms, .. } = *ast {
+            arms[0].pattern = parser::parse_Expression("1 + 1").unwrap();
+        }
+        assert!(match ast.type_check(&mut Environment::new()) {
+                    Err(ref errors) => {
+                        match errors[..] {
+                            [TypeCheckError::InvalidPattern(InvalidPatternError { span })] => {
+                                span == Span(10, 11)
+                            }
+                            _ => false,
+                        }
+                    }
+                    _ => false,
+                });
     }
 
     #[test]
     fn while_block() {
-        assert_err!("while 2+3.4 do ()",
+        assert_err!("while 2+true do ()",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
-        assert_err!("while true do 2+3.4",
+        assert_err!("while true do 2+true",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
 
@@ -702,13 +2382,13 @@ mod tests {
 
     #[test]
     fn for_block() {
-        assert_err!("for var x := 2+3.4 to 10 do ()",
+        assert_err!("for var x := 2+true to 10 do ()",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
-        assert_err!("for var x := 1 to 2+3.4 do ()",
+        assert_err!("for var x := 1 to 2+true do ()",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
-        assert_err!("for var x := 1 to 10 do 2+3.4",
+        assert_err!("for var x := 1 to 10 do 2+true",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
 
@@ -728,6 +2408,130 @@ mod tests {
                         got: Float,
                         ..
                     }));
+
+        assert_type!("for var x := 10 downto 0 do x", Void);
+        assert_type!("for var x := 0 to 100 step 5 do x", Void);
+        assert_err!("for var x := 0 to 100 step 0.5 do x",
+                    TypeCheckError::MismatchedTypes(MismatchedTypesError {
+                        expected: Generic::Builtin(Integer),
+                        got: Float,
+                        ..
+                    }));
+        assert_err!("for var x := 0 to 100 step 1 + true do x",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "+");
+    }
+
+    #[test]
+    fn for_in_block() {
+        assert_type!("for x in [1, 2, 3] do x + 1", Void);
+        assert_type!(r#"for c in "abc" do c + "d""#, Void);
+        assert_type!("for xs in [[1], [2, 3]] do for x in xs do x + 1", Void);
+
+        // Like an indexed value, the iterated value must be known
+        assert_err!("let function f(xs) := for x in xs do x + 1 in f(Integer[]) end",
+                    TypeCheckError::TypeAnnotationNeeded(_));
+
+        // The variable is the element, not its index
+        assert_err!(r#"for x in ["a", "b"] do x + 1"#,
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "+");
+        assert_err!("for x in 42 do x",
+                    TypeCheckError::MismatchedTypes(MismatchedTypesError {
+                        expected: Generic::Named(ref name),
+                        got: Integer,
+                        ..
+                    })
+                    if name == "Indexable");
+
+        // Only lives in the loop
+        assert_err!("(for x in [1] do (), x)",
+                    TypeCheckError::UnboundedVar(UnboundedVarError { ref name, .. })
+                    if name == "x");
+    }
+
+    #[test]
+    fn loop_jumps() {
+        assert_type!("while true do break", Void);
+        assert_type!("for var x := 1 to 10 do (continue, 2)", Void);
+
+        // A jump is of any type
+        assert_type!("while true do let var x := if true then break else 1 in x + 1 end",
+                     Void);
+        assert_type!("while true do case 1 of 1 => continue | _ => \"a\" end", Void);
+
+        assert_err!("break",
+                    TypeCheckError::OutsideLoop(OutsideLoopError { ref keyword, .. })
+                    if keyword == "break");
+        assert_err!("while break do ()",
+                    TypeCheckError::OutsideLoop(OutsideLoopError { ref keyword, .. })
+                    if keyword == "break");
+        assert_err!("for var x := 1 to continue do ()",
+                    TypeCheckError::OutsideLoop(OutsideLoopError { ref keyword, .. })
+                    if keyword == "continue");
+
+        // The body of a function is outside of the loops around its declaration
+        assert_err!("while true do let function f() := break in f() end",
+                    TypeCheckError::OutsideLoop(OutsideLoopError { ref keyword, .. })
+                    if keyword == "break");
+        assert_err!("while true do let var f := function() := continue in f() end",
+                    TypeCheckError::OutsideLoop(OutsideLoopError { ref keyword, .. })
+                    if keyword == "continue");
+        assert_type!("let function f() := while true do break in f() end", Void);
+    }
+
+    #[test]
+    fn return_expr() {
+        assert_type!("let function f(x: Integer): Integer := (if x < 0 then return 0 else (), x) \
+                      in f(1) end",
+                     Integer);
+        assert_type!("let function f(x: Integer): Str := \
+                          (for var i := 1 to x do if i = 5 then return \"five\" else (), \"no\") \
+                      in f(10) end",
+                     Str);
+
+        // The return type is deduced from the returned values
+        assert_type!("let function f(x: Integer) := if x = 0 then return true else false in f(1) end",
+                     Bool);
+        assert_type!("let var f := function() := return 2 in f() end", Integer);
+
+        // A `return` leaves the innermost function
+        assert_type!("let function f(): Integer := \
+                          let var g := function(): Str := return \"a\" in (g(), 1) end \
+                      in f() end",
+                     Integer);
+
+        assert_err!("let function f(): Integer := return \"a\" in f() end",
+                    TypeCheckError::MismatchedTypes(MismatchedTypesError {
+                        expected: Generic::Builtin(Integer),
+                        got: Str,
+                        ..
+                    }));
+        assert_err!("return 1", TypeCheckError::OutsideFunction(_));
+        assert_err!("let var x := return 1 in x end", TypeCheckError::OutsideFunction(_));
+    }
+
+    #[test]
+    fn numeric_promotion() {
+        assert_type!("2+3.4", Float);
+        assert_type!("2.5*2", Float);
+        assert_type!("2 ** 0.5", Float);
+        assert_type!("2 < 2.5", Bool);
+        assert_type!("let var x := 1 in x = 1. end", Bool);
+        assert_type!("let function f(x: Integer): Float := x / 2. in f(1) end", Float);
+
+        // The Integer operand is casted
+        let mut ast = parser::parse_Expression("1 + 2. = 3").unwrap();
+        ast.type_check(&mut Environment::new()).unwrap();
+        assert_eq!(ast.pretty_print(0), "(((1 as Float) + 2.) = (3 as Float))");
+
+        // Comparisons need operands of the same type
+        assert_err!(r#"1 = "1""#,
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "=");
+        assert_err!("[1] < [1.]",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "<");
     }
 
     #[test]
@@ -742,9 +2546,12 @@ mod tests {
         assert_type!("2.*2.", Float);
         assert_type!("2./2.", Float);
 
-        assert_err!("2+3.4",
+        assert_err!("2+true",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
+        assert_err!("2 div 3.4",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "div");
         // This is not JavaScript here, please
         assert_err!(r#"2-"3""#,
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
@@ -758,6 +2565,24 @@ mod tests {
         assert_type!("2>=2", Bool);
         assert_type!("2<2", Bool);
         assert_type!("2<=2", Bool);
+
+        assert_type!("7 mod 2", Integer);
+        assert_type!("7. mod 2.", Float);
+        assert_type!("2 ** 3", Integer);
+        assert_type!("2. ** 0.5", Float);
+        assert_type!("7 div 2", Integer);
+        assert_type!("1 & 2 | 3 ^ 4 << 5 >> 6", Integer);
+
+        // Only for Integers
+        assert_err!("7. div 2.",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "div");
+        assert_err!("1 | 2.",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "|");
+        assert_err!("true << 1",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "<<");
     }
 
     #[test]
@@ -772,6 +2597,25 @@ mod tests {
                     if func_name == "un-");
     }
 
+    #[test]
+    fn logical_ops() {
+        assert_type!("true and false", Bool);
+        assert_type!("1 or 2.5", Bool);
+        assert_type!("not [1]", Bool);
+        assert_type!("not 1 = 2 and 3 < 4 or false", Bool);
+
+        // The operands are conditions
+        assert_type!("let function f(x, y) := x and not y in f(true, false) end", Bool);
+        assert_err!(r#""a" or true"#,
+                    TypeCheckError::Conversion(ConversionError { from: Str, to: Bool, span })
+                    if span == Span(0, 3));
+        assert_err!(r#"true and "a""#,
+                    TypeCheckError::Conversion(ConversionError { from: Str, to: Bool, span })
+                    if span == Span(9, 12));
+        assert_err!(r#"not "a""#,
+                    TypeCheckError::Conversion(ConversionError { from: Str, to: Bool, .. }));
+    }
+
     #[test]
     fn cast() {
         assert_type!("2 as Float", Float);
@@ -779,6 +2623,12 @@ mod tests {
         assert_type!("[1, 2, 3] as Array(Float)", Array(Box::new(Float)));
         assert_type!("{1, 2, 3} as Tuple(Integer, Float, Bool)", Tuple(vec![Integer, Float, Bool]));
         assert_type!("{1, 2, 3} as Array(Float)", Array(Box::new(Float)));
+        assert_type!(r#""42" as Integer"#, Integer);
+        assert_type!(r#""4.2" as Float"#, Float);
+        assert_type!(r#""true" as Bool"#, Bool);
+
+        assert_type!(r#""42" as? Integer"#, Tuple(vec![Bool, Integer]));
+        assert_type!("42 as? Str", Tuple(vec![Bool, Str]));
 
         assert_err!("[1, 2, 3] as Integer",
                     TypeCheckError::Conversion(ConversionError {
@@ -795,6 +2645,53 @@ mod tests {
                         ..
                     })
                     if *from == Tuple(vec![Integer, Integer, Integer]));
+
+        assert_err!(r#""[1, 2]" as Array(Integer)"#,
+                    TypeCheckError::Conversion(ConversionError {
+                        from: Str,
+                        ref to,
+                        ..
+                    })
+                    if *to == Array(Box::new(Integer)));
+
+        assert_err!("[1, 2, 3] as? Integer",
+                    TypeCheckError::Conversion(ConversionError {
+                        ref from,
+                        to: Integer,
+                        ..
+                    })
+                    if *from == Array(Box::new(Integer)));
+    }
+
+    #[test]
+    fn interpolation() {
+        assert_type!(r#""x = ${1}, y = ${2.5 + 1}, ${true}, ${"a"}""#, Str);
+        assert_type!(r#"let var x := 1 in "${"${x}" as Integer + x}" end"#, Str);
+
+        assert_err!(r#""a ${[1, 2]} b""#,
+                    TypeCheckError::Conversion(ConversionError { ref from, to: Str, span })
+                    if *from == Array(Box::new(Integer)) && span == Span(5, 11));
+        assert_err!(r#""a ${1} ${{1, "b"}}""#,
+                    TypeCheckError::Conversion(ConversionError { to: Str, span, .. })
+                    if span == Span(10, 18));
+        assert_err!(r#"let function f(x) := "${x}" in f(1) end"#,
+                    TypeCheckError::TypeAnnotationNeeded(..));
+
+        // Every part is checked
+        let errors = parser::parse_Expression(r#""${[1]} ${undefined} ${{1}} ${1 + true}""#)
+            .unwrap()
+            .type_check(&mut Environment::new())
+            .unwrap_err();
+        assert_eq!(errors.len(), 4);
+        assert!(match errors[..] {
+                    [TypeCheckError::Conversion(ConversionError { span: first, .. }),
+                     TypeCheckError::UnboundedVar(_),
+                     TypeCheckError::Conversion(ConversionError { span: second, .. }),
+                     TypeCheckError::NoSuchSignature(_)] => {
+                        first == Span(3, 6) && second == Span(23, 26)
+                    }
+                    _ => false,
+                });
     }
 
     #[test]
@@ -819,10 +2716,10 @@ mod tests {
 
     #[test]
     fn array() {
-        assert_err!("[2+3.4]",
+        assert_err!("[2+true]",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
-        assert_err!("[1, 2+3.4]",
+        assert_err!("[1, 2+true]",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
 
@@ -848,12 +2745,283 @@ mod tests {
                     }));
     }
 
+    #[test]
+    fn index() {
+        assert_type!("[1, 2, 3][0]", Integer);
+        assert_type!("[[1], [2, 3]][1]", Array(Box::new(Integer)));
+        assert_type!("[[1], [2, 3]][1][0]", Integer);
+        assert_type!(r#""hello"[1]"#, Str);
+        assert_type!("let var x := [true] in x[0] := false end", Bool);
+        assert_type!(r#"let var x := ["hello"] in x[0][0] := "j" end"#, Str);
+
+        assert_err!("[1, 2, 3][true]",
+                    TypeCheckError::MismatchedTypes(MismatchedTypesError {
+                        expected: Generic::Builtin(Integer),
+                        got: Bool,
+                        ..
+                    }));
+        assert_err!("42[0]",
+                    TypeCheckError::MismatchedTypes(MismatchedTypesError {
+                        expected: Generic::Named(ref name),
+                        got: Integer,
+                        ..
+                    })
+                    if name == "Indexable");
+        assert_err!("let var x := [1] in x[0] := true end",
+                    TypeCheckError::MismatchedTypes(MismatchedTypesError {
+                        expected: Generic::Builtin(Integer),
+                        got: Bool,
+                        ..
+                    }));
+        assert_err!("x[0] := 1",
+                    TypeCheckError::UnboundedVar(UnboundedVarError { ref name, .. })
+                    if name == "x");
+    }
+
+    #[test]
+    fn projection() {
+        assert_type!("{1, true}.0", Integer);
+        assert_type!("{1, true}.1", Bool);
+        assert_type!("{1, {2., true}}.1.0", Float);
+        assert_type!("let var t := {1, [true]} in t.1[0] := false end", Bool);
+
+        assert_err!("{1, true}.2",
+                    TypeCheckError::TupleIndexOutOfRange(TupleIndexOutOfRangeError {
+                        index: 2,
+                        ref tuple_type,
+                        ..
+                    })
+                    if *tuple_type == Tuple(vec![Integer, Bool]));
+        assert_err!("{}.0",
+                    TypeCheckError::TupleIndexOutOfRange(TupleIndexOutOfRangeError {
+                        index: 0,
+                        ..
+                    }));
+        assert_err!("[1, 2].0",
+                    TypeCheckError::MismatchedTypes(MismatchedTypesError { ref got, .. })
+                    if *got == Array(Box::new(Integer)));
+        assert_err!("let var t := {1} in t.0 := true end",
+                    TypeCheckError::MismatchedTypes(MismatchedTypesError {
+                        expected: Generic::Builtin(Integer),
+                        got: Bool,
+                        ..
+                    }));
+    }
+
+    #[test]
+    fn record() {
+        assert_type!("let
+                        type Point = {x: Float, y: Float}
+                     in
+                        Point {y = 2., x = 1.}
+                     end",
+                     Named("Point".to_string()));
+        assert_type!("let
+                        type Point = {x: Float, y: Float}
+                        var p := Point {x = 1., y = 2.}
+                     in
+                        p.x := p.y
+                     end",
+                     Float);
+        assert_type!("let
+                        type Point = {x: Float, y: Float}
+                        type Segment = {a: Point, b: Point}
+                        function length(s: Segment): Float := s.b.x - s.a.x
+                     in
+                        length(Segment {a = Point {x = 0., y = 0.}, b = Point {x = 1., y = 0.}})
+                     end",
+                     Float);
+        assert_type!("let
+                        type Node = {value: Integer, next: Array(Node)}
+                        function value(node: Node): Integer := node.value
+                     in
+                     end",
+                     Void);
+
+        assert_err!("Point {x = 1., y = 2.}",
+                    TypeCheckError::UndefinedType(UndefinedTypeError { ref name, .. })
+                    if name == "Point");
+        assert_err!("let
+                        function f(p: Point): Integer := 1
+                     in
+                     end",
+                    TypeCheckError::UndefinedType(UndefinedTypeError { ref name, .. })
+                    if name == "Point");
+        assert_err!("let
+                        type Segment = {a: Point, b: Float}
+                     in
+                     end",
+                    TypeCheckError::UndefinedType(UndefinedTypeError { ref name, .. })
+                    if name == "Point");
+        assert_err!("let
+                        type Point = {x: Float}
+                        type Point = {y: Float}
+                     in
+                     end",
+                    TypeCheckError::AlreadyDeclared(AlreadyDeclaredError { ref name, .. })
+                    if name == "Point");
+
+        // The values of a type may outlive its scope, so a type name is unique in the program
+        assert_err!("let
+                        type P = {x: Integer}
+                        var p := P {x = 1}
+                     in
+                        let
+                           type P = {y: Integer}
+                        in
+                           p.x
+                        end
+                     end",
+                    TypeCheckError::AlreadyDeclared(AlreadyDeclaredError { ref name, .. })
+                    if name == "P");
+        assert_err!("let
+                        function f(): Integer := let type P = {x: Integer} in 1 end
+                     in
+                        let type P = {y: Integer} in 2 end
+                     end",
+                    TypeCheckError::AlreadyDeclared(AlreadyDeclaredError { ref name, .. })
+                    if name == "P");
+
+        assert_err!("let
+                        type R = {r: R}
+                     in
+                     end",
+                    TypeCheckError::UninhabitedType(UninhabitedTypeError { ref name, .. })
+                    if name == "R");
+        let errors = parser::parse_Expression("let
+                                                 type A = {b: B, n: Integer}
+                                                 type B = {a: Tuple(A, Integer)}
+                                               in
+                                               end")
+            .unwrap()
+            .type_check(&mut Environment::new())
+            .unwrap_err();
+        assert_eq!(errors
+                       .iter()
+                       .map(|err| match *err {
+                                TypeCheckError::UninhabitedType(ref err) => err.name.as_str(),
+                                _ => "",
+                            })
+                       .collect::<Vec<_>>(),
+                   vec!["A", "B"]);
+        assert_type!("let
+                        type Stream = {head: Integer, tail: Function(): Stream}
+                     in
+                     end",
+                     Void);
+        assert_err!("let
+                        type Point = {x: Float, y: Float}
+                     in
+                        Point {x = 1., y = 2., z = 3.}
+                     end",
+                    TypeCheckError::NoSuchField(NoSuchFieldError { ref name, .. })
+                    if name == "z");
+        assert_err!("let
+                        type Point = {x: Float, y: Float}
+                     in
+                        Point {x = 1.}
+                     end",
+                    TypeCheckError::MissingField(MissingFieldError { ref name, .. })
+                    if name == "y");
+        assert_err!("let
+                        type Point = {x: Float, y: Float}
+                     in
+                        Point {x = 1., y = 2}
+                     end",
+                    TypeCheckError::MismatchedTypes(MismatchedTypesError {
+                        expected: Generic::Builtin(Float),
+                        got: Integer,
+                        ..
+                    }));
+        assert_err!("let
+                        type Point = {x: Float, y: Float}
+                        var p := Point {x = 1., y = 2.}
+                     in
+                        p.z
+                     end",
+                    TypeCheckError::NoSuchField(NoSuchFieldError { ref name, .. })
+                    if name == "z");
+        assert_err!("{1, 2}.x",
+                    TypeCheckError::NoSuchField(NoSuchFieldError { ref type_, .. })
+                    if *type_ == Tuple(vec![Integer, Integer]));
+    }
+
+    #[test]
+    fn sum_type() {
+        assert_type!("let
+                        type Shape = Circle(Float) | Rect(Float, Float) | Empty
+                     in
+                        Rect(1., 2.)
+                     end",
+                     Named("Shape".to_string()));
+        assert_type!("let
+                        type Shape = Circle(Float) | Rect(Float, Float) | Empty
+                     in
+                        Empty
+                     end",
+                     Named("Shape".to_string()));
+        assert_type!("let
+                        type List = Cons(Integer, List) | Nil
+                        var r := 0
+                     in
+                        match Cons(r, Nil) := Cons(1, Nil)
+                     end",
+                     Bool);
+
+        assert_err!("let
+                        type Shape = Circle(Float) | Rect(Float, Float)
+                     in
+                        Circle(1)
+                     end",
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
+                    if func_name == "Circle");
+        assert_err!("let
+                        type Shape = Circle(Float) | Rect(Float, Float)
+                     in
+                        Circle
+                     end",
+                    TypeCheckError::UnboundedVar(UnboundedVarError { ref name, .. })
+                    if name == "Circle");
+        assert_err!("let
+                        type Shape = Circle(Float) | Rect(Float, Float)
+                        type Figure = Circle(Integer)
+                     in
+                     end",
+                    TypeCheckError::AlreadyDeclared(AlreadyDeclaredError { ref name, .. })
+                    if name == "Circle");
+        assert_err!("let
+                        type Shape = Circle(Point)
+                     in
+                     end",
+                    TypeCheckError::UndefinedType(UndefinedTypeError { ref name, .. })
+                    if name == "Point");
+        assert_err!("let
+                        type Shape = Circle(Float) | Empty
+                     in
+                        Shape {}
+                     end",
+                    TypeCheckError::NotARecord(NotARecordError { ref name, .. })
+                    if name == "Shape");
+        assert_err!("let
+                        type Loop = Again(Loop)
+                     in
+                     end",
+                    TypeCheckError::UninhabitedType(UninhabitedTypeError { ref name, .. })
+                    if name == "Loop");
+        assert_err!("let
+                        type Expr = Sum(Expr, Expr) | Neg(Expr)
+                     in
+                     end",
+                    TypeCheckError::UninhabitedType(UninhabitedTypeError { ref name, .. })
+                    if name == "Expr");
+    }
+
     #[test]
     fn tuple() {
-        assert_err!("{2+3.4}",
+        assert_err!("{2+true}",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
-        assert_err!("{1, 2+3.4}",
+        assert_err!("{1, 2+true}",
                     TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. })
                     if func_name == "+");
 
@@ -868,4 +3036,107 @@ mod tests {
         assert_type!("true", Bool);
         assert_type!(r#""2""#, Str);
     }
+
+    #[test]
+    fn several_errors() {
+        let res = parser::parse_Expression("let
+                                              var x := 1 + \"a\"
+                                              var y := -x * 2
+                                              function f(n) := -(undefined + n)
+                                            in
+                                              {f(\"s\"), y + 1, z}
+                                            end")
+                .unwrap()
+                .type_check(&mut Environment::new());
+
+        // Every error is reported, in order, but not the ones caused by a previous error
+        let errors = res.unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert!(match errors[0] {
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. }) => {
+                        func_name == "+"
+                    }
+                    _ => false,
+                });
+        assert!(match errors[1] {
+                    TypeCheckError::UnboundedVar(UnboundedVarError { ref name, .. }) => {
+                        name == "undefined"
+                    }
+                    _ => false,
+                });
+        assert!(match errors[2] {
+                    TypeCheckError::UnboundedVar(UnboundedVarError { ref name, .. }) => name == "z",
+                    _ => false,
+                });
+
+        // An erroneous type declaration does not stop the check of the rest of the let
+        let errors = parser::parse_Expression("let
+                                                 type P = {x: Intger}
+                                                 type Loop = Again(Loop)
+                                                 type P = {y: Float}
+                                                 var x := 1 + \"a\"
+                                               in
+                                                 z
+                                               end")
+            .unwrap()
+            .type_check(&mut Environment::new())
+            .unwrap_err();
+        assert_eq!(errors.len(), 5);
+        assert!(match errors[0] {
+                    TypeCheckError::AlreadyDeclared(AlreadyDeclaredError { ref name, .. }) => {
+                        name == "P"
+                    }
+                    _ => false,
+                });
+        assert!(match errors[1] {
+                    TypeCheckError::UndefinedType(UndefinedTypeError { ref name, .. }) => {
+                        name == "Intger"
+                    }
+                    _ => false,
+                });
+        assert!(match errors[2] {
+                    TypeCheckError::UninhabitedType(UninhabitedTypeError { ref name, .. }) => {
+                        name == "Loop"
+                    }
+                    _ => false,
+                });
+        assert!(match errors[3] {
+                    TypeCheckError::NoSuchSignature(NoSuchSignatureError { ref func_name, .. }) => {
+                        func_name == "+"
+                    }
+                    _ => false,
+                });
+        assert!(match errors[4] {
+                    TypeCheckError::UnboundedVar(UnboundedVarError { ref name, .. }) => name == "z",
+                    _ => false,
+                });
+
+        // The scopes of an erroneous expression are left
+        let errors = parser::parse_Expression("{let var x := 1 in x + \"a\" end, x}")
+            .unwrap()
+            .type_check(&mut Environment::new())
+            .unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(match errors[1] {
+                    TypeCheckError::UnboundedVar(UnboundedVarError { ref name, .. }) => name == "x",
+                    _ => false,
+                });
+
+        // Whatever the order of the operands, and however an erroneous variable is used
+        assert_err!("let function f(n) := n + undefined in f(1) end",
+                    TypeCheckError::UnboundedVar(UnboundedVarError { ref name, .. })
+                    if name == "undefined");
+        assert_err!("let function f(n) := undefined + n in f(1) end",
+                    TypeCheckError::UnboundedVar(UnboundedVarError { ref name, .. })
+                    if name == "undefined");
+        assert_err!("let var x := undefined in x + 1, x + \"a\", x[0], x.y end",
+                    TypeCheckError::UnboundedVar(UnboundedVarError { ref name, .. })
+                    if name == "undefined");
+
+        // The variables of an erroneous pattern are declared anyway
+        assert_err!("let var t := {1, 2} in match {a, b, c} := t, a + b + c end",
+                    TypeCheckError::MismatchedTypes(MismatchedTypesError { .. }));
+        assert_err!("case 1 of {a, b} => a | _ => 2 end",
+                    TypeCheckError::MismatchedTypes(MismatchedTypesError { .. }));
+    }
 }
@@ -4,7 +4,7 @@ use super::{do_the_thing, parse_expressions};
 
 use ast;
 use env::Environment;
-use error::{print_error, REPLError, ParseError};
+use error::{print_error, print_errors, REPLError, ParseError};
 use parser;
 
 use lalrpop_util::ParseError as PopParseError;
@@ -42,17 +42,23 @@ pub fn start() {
                 match parse_expressions(line.as_str()) {
                     Ok(exprs) => {
                         rl.add_history_entry(&line);
-                        if let Err(err) = do_the_thing(exprs, &mut bindings) {
-                            print_error("<command-line>", line.as_str(), &err);
+                        if let Err(errs) = do_the_thing(exprs,
+                                                        &mut bindings,
+                                                        "<command-line>",
+                                                        &line) {
+                            print_errors("<command-line>", line.as_str(), &errs);
                         }
                     }
 
-                    Err(ParseError::UnrecognizedToken { token: None, .. }) => {
+                    Err(ref errs) if is_incomplete(errs) => {
                         let mut partial_input = line.clone();
                         match multiline_loop(&mut rl, &mut partial_input) {
                             (input, Ok(exprs)) => {
-                                if let Err(err) = do_the_thing(exprs, &mut bindings) {
-                                    print_error("<command-line>", input.as_str(), &err);
+                                if let Err(errs) = do_the_thing(exprs,
+                                                                &mut bindings,
+                                                                "<command-line>",
+                                                                input) {
+                                    print_errors("<command-line>", input.as_str(), &errs);
                                 }
                                 // Restore the default completer.
                                 rl.set_completer(Some(ParseCompleter::default()));
@@ -62,8 +68,8 @@ pub fn start() {
                         }
                     }
 
-                    Err(err) => {
-                        print_error("<command-line>", line.as_str(), &err);
+                    Err(errs) => {
+                        print_errors("<command-line>", line.as_str(), &errs);
                     }
                 }
 
@@ -79,6 +85,15 @@ pub fn start() {
     }
 }
 
+// The input only lacks its end: every error is about its end (with an error in the middle of it,
+// there is no point in asking for more)
+fn is_incomplete(errs: &[ParseError]) -> bool {
+    errs.iter().all(|err| match *err {
+                        ParseError::UnrecognizedEof { .. } => true,
+                        _ => false,
+                    })
+}
+
 /// The loop that keep asking for input until the expression is completed
 ///
 /// This is done by looping while the parser returns an error of type "I needed something more"
@@ -96,12 +111,12 @@ fn multiline_loop<'a>(mut rl: &mut Editor<ParseCompleter>,
 
                 match parse_expressions(partial_input) {
                     Ok(expr) => return (partial_input, Ok(expr)),
-                    Err(ParseError::UnrecognizedToken { token: None, .. }) => continue,
-                    Err(err) => {
+                    Err(ref errs) if is_incomplete(errs) => continue,
+                    Err(errs) => {
                         // See: https://github.com/rust-lang/rust/issues/40307
                         //return Err(REPLError::Parse(err));
 
-                        print_error("<command-line>", partial_input, &err);
+                        print_errors("<command-line>", partial_input, &errs);
                     }
                 }
 
@@ -174,11 +189,16 @@ impl Completer for ParseCompleter {
 
         let partial_input = self.context.clone() + &line[0..start];
 
-        match parser::parse_Expressions(partial_input.as_str()) {
-            Err(PopParseError::UnrecognizedToken {
-                    token: None,
-                    expected: candidates,
-                }) => {
+        // The end of the input is the last place where the parser can fail
+        let last_error = parser::parse_Expressions(partial_input.as_str())
+            .err()
+            .and_then(|errs| errs.into_iter().last());
+
+        match last_error {
+            Some(PopParseError::UnrecognizedToken {
+                     token: None,
+                     expected: candidates,
+                 }) => {
                 let candidates = candidates
                     .into_iter()
                     .filter_map(|mut candidate| {
@@ -3,12 +3,17 @@
 //! This module enumerates the available types, the values ("runtime typed" containers) and
 //! functions that manipulate these types / values.
 
+use ast::{FunctionDecl, Span, TypeDefinition};
+use env::{Environment, Scope, ValueInfo};
+use error::{ConversionError, IndexOutOfBoundsError, InvalidCharacterError, RuntimeError,
+            UnparsableStrError};
+
 use itertools::Itertools;
 
-use std::char;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, LinkedList};
 use std::fmt;
+use std::str::FromStr;
 
 /// A Type (really?!)
 #[derive(Clone,PartialEq,Eq,Hash)]
@@ -27,6 +32,30 @@ pub enum Type {
     Array(Box<Type>),
     /// The Tuple type
     Tuple(Vec<Type>),
+    /// The Function type
+    ///
+    /// Composed of the types of the arguments, and the return type
+    Function(Vec<Type>, Box<Type>),
+    /// A user-declared type (see the [`ast::TypeDecl`] struct), designated by its name
+    ///
+    /// The definition of the type is stored in the scope where it was declared (see the
+    /// [`env`](../env/index.html) module).
+    ///
+    /// [`ast::TypeDecl`]: ../ast/struct.TypeDecl.html
+    Named(String),
+    /// A type parameter of a generic function (like `T` in `function id<T>(x: T): T`)
+    ///
+    /// Inside the function, it stands for an unknown type. It is replaced by a concrete type at
+    /// each call of the function (see [`ast::FunctionDecl::instantiate`]).
+    ///
+    /// [`ast::FunctionDecl::instantiate`]: ../ast/struct.FunctionDecl.html#method.instantiate
+    Param(String),
+    /// A type to be inferred by the type checker (see the [`Substitution`] struct)
+    ///
+    /// Never appears in a type checked AST.
+    ///
+    /// [`Substitution`]: struct.Substitution.html
+    Var(usize),
 }
 
 impl Type {
@@ -54,27 +83,29 @@ impl Type {
             Integer | Float => {
                 match *dest {
                     Void | Integer | Float | Bool | Str => true,
-                    Array(_) | Tuple(_) => false,
+                    Array(_) | Tuple(_) | Function(..) | Named(_) | Param(_) | Var(_) => false,
                 }
             }
             Bool => {
                 match *dest {
                     Void | Bool | Str => true,
-                    Integer | Float | Array(_) | Tuple(_) => false,
+                    Integer | Float | Array(_) | Tuple(_) | Function(..) | Named(_) | Param(_) |
+                    Var(_) => false,
                 }
             }
             Str => {
                 match *dest {
-                    Void | Str => true,
-                    // TODO
-                    Integer | Float | Bool | Array(_) | Tuple(_) => false,
+                    // Converting to a Integer, a Float or a Bool may fail at runtime
+                    Void | Str | Integer | Float | Bool => true,
+                    Array(_) | Tuple(_) | Function(..) | Named(_) | Param(_) | Var(_) => false,
                 }
             }
             Array(ref my_type) => {
                 match *dest {
                     Void => true,
                     Array(ref type_) => my_type.is_convertible_to(type_),
-                    Integer | Float | Bool | Str | Tuple(_) => false,
+                    Integer | Float | Bool | Str | Tuple(_) | Function(..) | Named(_) |
+                    Param(_) | Var(_) => false,
                 }
             }
             Tuple(ref my_types) => {
@@ -92,11 +123,88 @@ impl Type {
                             .zip(types)
                             .all(|(my_type, type_)| my_type.is_convertible_to(type_))
                     }
-                    Integer | Float | Bool | Str => false,
+                    Integer | Float | Bool | Str | Function(..) | Named(_) | Param(_) |
+                    Var(_) => false,
+                }
+            }
+            Function(..) => {
+                match *dest {
+                    Void => true,
+                    Function(..) => self == dest,
+                    Integer | Float | Bool | Str | Array(_) | Tuple(_) | Named(_) | Param(_) |
+                    Var(_) => false,
                 }
             }
+            Named(_) => {
+                match *dest {
+                    Void => true,
+                    Named(_) => self == dest,
+                    Integer | Float | Bool | Str | Array(_) | Tuple(_) | Function(..) |
+                    Param(_) | Var(_) => false,
+                }
+            }
+            // Nothing is known about a type parameter
+            Param(_) => {
+                match *dest {
+                    Void => true,
+                    Param(_) => self == dest,
+                    Integer | Float | Bool | Str | Array(_) | Tuple(_) | Function(..) |
+                    Named(_) | Var(_) => false,
+                }
+            }
+            // The type checker never asks for the conversion of a type still to infer
+            Var(_) => false,
         }
     }
+
+    /// Replace the type parameters inside the current type
+    ///
+    /// `lookup` gives the type a type parameter stands for, or None if it must be left as is.
+    pub fn substitute<F>(&self, lookup: &F) -> Type
+        where F: Fn(&str) -> Option<Type>
+    {
+        use self::Type::*;
+
+        match *self {
+            Array(ref type_) => Array(Box::new(type_.substitute(lookup))),
+            Tuple(ref types) => Tuple(types.iter().map(|type_| type_.substitute(lookup)).collect()),
+            Function(ref args, ref return_type) => {
+                Function(args.iter().map(|type_| type_.substitute(lookup)).collect(),
+                         Box::new(return_type.substitute(lookup)))
+            }
+            Param(ref name) => lookup(name).unwrap_or_else(|| self.clone()),
+            Void | Integer | Float | Bool | Str | Named(_) | Var(_) => self.clone(),
+        }
+    }
+
+    /// Returns the ids of the type variables used inside the current type (in order of
+    /// appearance, without duplicates)
+    pub fn free_vars(&self) -> Vec<usize> {
+        fn collect(type_: &Type, vars: &mut Vec<usize>) {
+            use self::Type::*;
+
+            match *type_ {
+                Var(id) if !vars.contains(&id) => vars.push(id),
+                Array(ref type_) => collect(type_, vars),
+                Tuple(ref types) => {
+                    for type_ in types {
+                        collect(type_, vars);
+                    }
+                }
+                Function(ref args, ref return_type) => {
+                    for type_ in args {
+                        collect(type_, vars);
+                    }
+                    collect(return_type, vars);
+                }
+                Void | Integer | Float | Bool | Str | Named(_) | Param(_) | Var(_) => {}
+            }
+        }
+
+        let mut vars = Vec::new();
+        collect(self, &mut vars);
+        vars
+    }
 }
 
 impl fmt::Debug for Type {
@@ -124,10 +232,176 @@ impl fmt::Debug for Type {
 
                 write!(f, ")")
             }
+            Function(ref args, ref return_type) => {
+                write!(f, "Function(")?;
+
+                if !args.is_empty() {
+
+                    write!(f, "{:?}", args[0])?;
+
+                    for type_ in args.iter().skip(1) {
+                        write!(f, ", {:?}", type_)?;
+                    }
+                }
+
+                write!(f, "): {:?}", return_type)
+            }
+            Named(ref name) |
+            Param(ref name) => write!(f, "{}", name),
+            Var(id) => write!(f, "?{}", var_name(id)),
         }
     }
 }
 
+/// The types deduced for the type variables (see the [`Type::Var`] variant) during the type
+/// checking
+///
+/// Implements the unification of Hindley-Milner type inference: two types containing type
+/// variables are made equal by recording what each variable stands for.
+///
+/// [`Type::Var`]: enum.Type.html#variant.Var
+#[derive(Debug,Clone,PartialEq,Default)]
+pub struct Substitution {
+    /// What each type variable stands for, indexed by the variable id (None if still unknown)
+    vars: Vec<Option<Type>>,
+    /// The type variables standing for the type of an erroneous expression
+    erroneous: Vec<usize>,
+}
+
+impl Substitution {
+    /// Create a new empty substitution
+    pub fn new() -> Self {
+        Substitution {
+            vars: Vec::new(),
+            erroneous: Vec::new(),
+        }
+    }
+
+    /// Create a new type variable, standing for an unknown type
+    pub fn fresh(&mut self) -> Type {
+        self.vars.push(None);
+        Type::Var(self.vars.len() - 1)
+    }
+
+    /// Create a new type variable, standing for the type of an erroneous expression
+    ///
+    /// Unlike the other variables, it is never bound: it can be unified with anything, so that
+    /// the type checking can go on after an error without reporting its consequences.
+    pub fn fresh_erroneous(&mut self) -> Type {
+        let type_ = self.fresh();
+        if let Type::Var(id) = type_ {
+            self.erroneous.push(id);
+        }
+        type_
+    }
+
+    /// Tells if the type of an erroneous expression is still unknown inside a given type
+    pub fn is_erroneous(&self, type_: &Type) -> bool {
+        self.apply(type_)
+            .free_vars()
+            .iter()
+            .any(|id| self.erroneous.contains(id))
+    }
+
+    /// Replace every known type variable inside a given type by the type it stands for
+    pub fn apply(&self, type_: &Type) -> Type {
+        use self::Type::*;
+
+        match *type_ {
+            Var(id) => {
+                match self.vars[id] {
+                    Some(ref bound) => self.apply(bound),
+                    None => type_.clone(),
+                }
+            }
+            Array(ref type_) => Array(Box::new(self.apply(type_))),
+            Tuple(ref types) => Tuple(types.iter().map(|type_| self.apply(type_)).collect()),
+            Function(ref args, ref return_type) => {
+                Function(args.iter().map(|type_| self.apply(type_)).collect(),
+                         Box::new(self.apply(return_type)))
+            }
+            Void | Integer | Float | Bool | Str | Named(_) | Param(_) => type_.clone(),
+        }
+    }
+
+    /// Make two types equal, by binding the type variables they contain
+    ///
+    /// Returns false if the types cannot be made equal. In this case, some variables may have
+    /// been bound anyway: see the [`snapshot`](#method.snapshot) method.
+    pub fn unify(&mut self, lhs: &Type, rhs: &Type) -> bool {
+        use self::Type::*;
+
+        match (self.apply(lhs), self.apply(rhs)) {
+            (Var(lhs), Var(rhs)) if lhs == rhs => true,
+            // Keep track of the erroneous expressions
+            (Var(id), Var(erroneous)) |
+            (Var(erroneous), Var(id)) if self.erroneous.contains(&erroneous) => {
+                if !self.erroneous.contains(&id) {
+                    self.vars[id] = Some(Var(erroneous));
+                }
+                true
+            }
+            (Var(erroneous), _) |
+            (_, Var(erroneous)) if self.erroneous.contains(&erroneous) => true,
+            (Var(id), other) | (other, Var(id)) => {
+                // A type cannot contain itself
+                if other.free_vars().contains(&id) {
+                    return false;
+                }
+
+                self.vars[id] = Some(other);
+                true
+            }
+            (Array(lhs), Array(rhs)) => self.unify(&lhs, &rhs),
+            (Tuple(lhs), Tuple(rhs)) => {
+                lhs.len() == rhs.len() &&
+                lhs.iter().zip(&rhs).all(|(lhs, rhs)| self.unify(lhs, rhs))
+            }
+            (Function(lhs_args, lhs_return), Function(rhs_args, rhs_return)) => {
+                lhs_args.len() == rhs_args.len() &&
+                lhs_args
+                    .iter()
+                    .zip(&rhs_args)
+                    .all(|(lhs, rhs)| self.unify(lhs, rhs)) &&
+                self.unify(&lhs_return, &rhs_return)
+            }
+            (lhs, rhs) => lhs == rhs,
+        }
+    }
+
+    /// Bind a type variable without any check
+    ///
+    /// Used to generalize the type variables of a function into type parameters.
+    pub fn bind(&mut self, id: usize, type_: Type) {
+        self.vars[id] = Some(type_);
+    }
+
+    /// Save the current state of the substitution, to try an unification without committing to
+    /// it
+    pub fn snapshot(&self) -> Substitution {
+        self.clone()
+    }
+
+    /// Restore a state saved by the [`snapshot`](#method.snapshot) method
+    pub fn rollback(&mut self, snapshot: Substitution) {
+        *self = snapshot;
+    }
+}
+
+/// Returns the name of a type variable: `a` to `z`, then `a1`, `b1`, etc.
+///
+/// The type parameters deduced for a function are named after the variables they come from, in
+/// uppercase (like `A`), so that the printed program can be parsed again.
+pub fn var_name(id: usize) -> String {
+    let letter = (b'a' + (id % 26) as u8) as char;
+
+    if id < 26 {
+        letter.to_string()
+    } else {
+        format!("{}{}", letter, id / 26)
+    }
+}
+
 /// A Value (...)
 #[derive(Debug,Clone,PartialEq)]
 pub enum Value {
@@ -155,6 +429,32 @@ pub enum Value {
         /// The value of this tuple's elements
         values: Vec<Value>,
     },
+    /// The Closure value (a function used as a value)
+    Closure {
+        /// The declaration of the function (boxed, to keep the values small)
+        decl: Box<FunctionDecl>,
+        /// The scopes captured when the closure was created
+        ///
+        /// This is a copy: assigning a captured variable inside the closure does not change the
+        /// original variable, and vice versa.
+        scopes: LinkedList<Scope<ValueInfo>>,
+    },
+    /// The Record value
+    Record {
+        /// The name of the record type
+        type_name: String,
+        /// The names and values of the fields (in the order of the type declaration)
+        fields: Vec<(String, Value)>,
+    },
+    /// The Variant value (a value of a user sum type)
+    Variant {
+        /// The name of the sum type
+        type_name: String,
+        /// The name of the variant (its tag)
+        tag: String,
+        /// The values carried by the variant
+        values: Vec<Value>,
+    },
 }
 
 impl Value {
@@ -182,57 +482,315 @@ impl Value {
             Str(_) => Type::Str,
             Array { ref element_type, .. } => Type::Array(Box::new(element_type.clone())),
             Tuple { ref element_types, .. } => Type::Tuple(element_types.clone()),
+            Closure { ref decl, .. } => decl.get_type(),
+            Record { ref type_name, .. } |
+            Variant { ref type_name, .. } => Type::Named(type_name.clone()),
+        }
+    }
+
+    /// Get the element at the given index of an Array, or the character at the given index of a
+    /// Str (as a Str)
+    ///
+    /// Returns an [`IndexOutOfBoundsError`] if the index is negative or too big. Since values do
+    /// not know where they come from, the location of the error must be set by the caller.
+    ///
+    /// [`IndexOutOfBoundsError`]: ../error/struct.IndexOutOfBoundsError.html
+    pub fn get_element(&self, index: i64) -> Result<Value, RuntimeError> {
+        use self::Value::*;
+
+        match *self {
+            Array { ref values, .. } => {
+                check_index(index, values.len()).map(|index| values[index].clone())
+            }
+            Str(ref value) => {
+                let index = check_index(index, value.chars().count())?;
+                Ok(Str(value.chars().nth(index).unwrap().to_string()))
+            }
+            _ => panic!("Invalid value indexed"),
+        }
+    }
+
+    /// Get the elements of an Array, or the characters of a Str (as Strs)
+    pub fn into_elements(self) -> Vec<Value> {
+        use self::Value::*;
+
+        match self {
+            Array { values, .. } => values,
+            Str(value) => value.chars().map(|c| Str(c.to_string())).collect(),
+            _ => panic!("Invalid value iterated over"),
+        }
+    }
+
+    /// Replace the element at the given index of an Array, or the character at the given index of
+    /// a Str (by a Str of one character)
+    ///
+    /// Returns an [`IndexOutOfBoundsError`] if the index is negative or too big, or an
+    /// [`InvalidCharacterError`] if a character is replaced by a Str which is not exactly one
+    /// character long. Since values do not know where they come from, the location of the error
+    /// must be set by the caller.
+    ///
+    /// [`IndexOutOfBoundsError`]: ../error/struct.IndexOutOfBoundsError.html
+    /// [`InvalidCharacterError`]: ../error/struct.InvalidCharacterError.html
+    pub fn set_element(&mut self, index: i64, element: Value) -> Result<(), RuntimeError> {
+        use self::Value::*;
+
+        match (self, element) {
+            (&mut Array { ref mut values, .. }, element) => {
+                let index = check_index(index, values.len())?;
+                values[index] = element;
+            }
+            (&mut Str(ref mut value), Str(element)) => {
+                let index = check_index(index, value.chars().count())?;
+                if element.chars().count() != 1 {
+                    return Err(InvalidCharacterError::new(element, Span(0, 0)).into());
+                }
+                *value = value
+                    .chars()
+                    .take(index)
+                    .chain(element.chars())
+                    .chain(value.chars().skip(index + 1))
+                    .collect();
+            }
+            _ => panic!("Invalid value indexed"),
+        }
+
+        Ok(())
+    }
+
+    /// Get the default value of a given type
+    ///
+    /// Used as a placeholder when a conversion fails (see the `as?` operator). The environment is
+    /// used to lookup the declarations of user-declared types, which the type checker ensures have
+    /// a finite value.
+    pub fn default_of(type_: &Type, env: &Environment<ValueInfo>) -> Self {
+        Value::finite_default_of(type_, env, &mut Vec::new())
+            .unwrap_or_else(|| panic!("No finite default value of type {:?}", type_))
+    }
+
+    // Get a default value of a given type without going through the user types being built (the
+    // ones in `building`), so that the default value of a recursive type is finite
+    //
+    // The default value of a function type is a closure computing the default value of its
+    // return type when called, so it does not go through the return type.
+    fn finite_default_of(type_: &Type,
+                         env: &Environment<ValueInfo>,
+                         building: &mut Vec<String>)
+                         -> Option<Self> {
+        use self::Value::*;
+
+        match *type_ {
+            Type::Void => Some(Void),
+            Type::Integer => Some(Integer(0)),
+            Type::Float => Some(Float(0f64)),
+            Type::Bool => Some(Bool(false)),
+            Type::Str => Some(Str(String::new())),
+            Type::Array(ref element_type) => {
+                Some(Array {
+                         element_type: (**element_type).clone(),
+                         values: vec![],
+                     })
+            }
+            Type::Tuple(ref element_types) => {
+                let values = element_types
+                    .iter()
+                    .map(|type_| Value::finite_default_of(type_, env, building))
+                    .collect::<Option<Vec<_>>>()?;
+
+                Some(Tuple {
+                         element_types: element_types.clone(),
+                         values,
+                     })
+            }
+            Type::Function(ref args, ref return_type) => {
+                use ast::{ArgumentDecl, Expr};
+
+                // The body is `(() as? R).1`
+                let default = Expr::TryCast {
+                    expr: Box::new(Expr::Value(Void)),
+                    expr_span: Span(0, 0),
+                    dest: (**return_type).clone(),
+                };
+
+                Some(Closure {
+                         decl: Box::new(FunctionDecl {
+                             name: "lambda".to_string(),
+                             type_params: Vec::new(),
+                             args: args.iter()
+                                 .enumerate()
+                                 .map(|(ind, type_)| {
+                                          ArgumentDecl {
+                                              name: format!("arg{}", ind),
+                                              type_: Some(type_.clone()),
+                                              span: Span(0, 0),
+                                          }
+                                      })
+                                 .collect(),
+                             return_type: Some((**return_type).clone()),
+                             signature_span: Span(0, 0),
+                             body: Box::new(Expr::Projection {
+                                                expr: Box::new(default),
+                                                expr_span: Span(0, 0),
+                                                index: 1,
+                                                index_span: Span(0, 0),
+                                            }),
+                             body_span: Span(0, 0),
+                         }),
+                         scopes: env.scopes.clone(),
+                     })
+            }
+            Type::Named(ref name) if building.contains(name) => None,
+            Type::Named(ref name) => {
+                let decl = env.get_type_decl(name)
+                    .unwrap_or_else(|| panic!("Undeclared type {} in default value", name));
+
+                building.push(name.clone());
+
+                let value = match decl.definition {
+                    TypeDefinition::Record(ref fields) => {
+                        fields
+                            .iter()
+                            .map(|field| {
+                                     Value::finite_default_of(&field.type_, env, building)
+                                         .map(|value| (field.name.clone(), value))
+                                 })
+                            .collect::<Option<Vec<_>>>()
+                            .map(|fields| {
+                                     Record {
+                                         type_name: name.clone(),
+                                         fields,
+                                     }
+                                 })
+                    }
+                    TypeDefinition::Sum(ref variants) => {
+                        // Prefer a variant without values
+                        variants
+                            .iter()
+                            .filter(|variant| variant.types.is_empty())
+                            .chain(variants.iter())
+                            .filter_map(|variant| {
+                                variant
+                                    .types
+                                    .iter()
+                                    .map(|type_| Value::finite_default_of(type_, env, building))
+                                    .collect::<Option<Vec<_>>>()
+                                    .map(|values| {
+                                             Variant {
+                                                 type_name: name.clone(),
+                                                 tag: variant.name.clone(),
+                                                 values,
+                                             }
+                                         })
+                            })
+                            .next()
+                    }
+                };
+
+                building.pop();
+                value
+            }
+            Type::Param(ref name) => {
+                let type_ = env.get_type_param(name)
+                    .unwrap_or_else(|| panic!("Unbound type parameter {} in default value", name))
+                    .clone();
+
+                Value::finite_default_of(&type_, env, building)
+            }
+            Type::Var(_) => panic!("Type variable in default value"),
         }
     }
 
     /// Convert the current value to another type
     ///
     /// Consumes the value.
-    pub fn into(self, dest: &Type) -> Self {
+    ///
+    /// Returns a [`ConversionError`] if the value cannot be converted, or an
+    /// [`UnparsableStrError`] if the value is a string that does not represent a value of the
+    /// given type. Since values do not know where they come from, the location of the error must
+    /// be set by the caller.
+    ///
+    /// [`ConversionError`]: ../error/struct.ConversionError.html
+    /// [`UnparsableStrError`]: ../error/struct.UnparsableStrError.html
+    pub fn into(self, dest: &Type) -> Result<Self, RuntimeError> {
         use self::Value::*;
 
+        let unnatural = |from: Type| {
+            Err(ConversionError::new(from, dest.clone(), Span(0, 0)).into())
+        };
+
         match self {
             Void => {
                 match *dest {
-                    Type::Void => Void,
-                    _ => panic!("Unnatural conversion at runtime"),
+                    Type::Void => Ok(Void),
+                    _ => unnatural(Type::Void),
                 }
             }
             Integer(val) => {
                 match *dest {
-                    Type::Void => Void,
-                    Type::Integer => Integer(val),
-                    Type::Float => Float(val as f64),
-                    Type::Bool => Bool(val != 0),
-                    Type::Str => Str(val.to_string()),
-                    Type::Array(_) | Type::Tuple(_) => panic!("Unnatural conversion at runtime"),
+                    Type::Void => Ok(Void),
+                    Type::Integer => Ok(Integer(val)),
+                    Type::Float => Ok(Float(val as f64)),
+                    Type::Bool => Ok(Bool(val != 0)),
+                    Type::Str => Ok(Str(val.to_string())),
+                    Type::Array(_) | Type::Tuple(_) | Type::Function(..) | Type::Named(_) |
+                    Type::Param(_) | Type::Var(_) => {
+                        unnatural(Type::Integer)
+                    }
                 }
             }
             Float(val) => {
                 match *dest {
-                    Type::Void => Void,
-                    Type::Integer => Integer(val as i64),
-                    Type::Float => Float(val),
-                    Type::Bool => Bool(val != 0f64),
-                    Type::Str => Str(val.to_string()),
-                    Type::Array(_) | Type::Tuple(_) => panic!("Unnatural conversion at runtime"),
+                    Type::Void => Ok(Void),
+                    Type::Integer => Ok(Integer(val as i64)),
+                    Type::Float => Ok(Float(val)),
+                    Type::Bool => Ok(Bool(val != 0f64)),
+                    Type::Str => Ok(Str(val.to_string())),
+                    Type::Array(_) | Type::Tuple(_) | Type::Function(..) | Type::Named(_) |
+                    Type::Param(_) | Type::Var(_) => {
+                        unnatural(Type::Float)
+                    }
                 }
             }
             Bool(val) => {
                 match *dest {
-                    Type::Void => Void,
-                    Type::Bool => Bool(val),
-                    Type::Str => Str(val.to_string()),
-                    Type::Integer | Type::Float | Type::Array(_) | Type::Tuple(_) => panic!("Unnatural conversion at runtime"),
+                    Type::Void => Ok(Void),
+                    Type::Bool => Ok(Bool(val)),
+                    Type::Str => Ok(Str(val.to_string())),
+                    Type::Integer | Type::Float | Type::Array(_) | Type::Tuple(_) |
+                    Type::Function(..) | Type::Named(_) | Type::Param(_) | Type::Var(_) => {
+                        unnatural(Type::Bool)
+                    }
                 }
             }
             Str(val) => {
+                let unparsable = |val: String| {
+                    Err(UnparsableStrError::new(val, dest.clone(), Span(0, 0)).into())
+                };
+
                 match *dest {
-                    Type::Void => Value::Void,
-                    Type::Str => Str(val),
-                    // TODO
-                    Type::Integer | Type::Float | Type::Bool | Type::Array(_) | Type::Tuple(_) => {
-                        panic!("Unnatural conversion at runtime")
+                    Type::Void => Ok(Value::Void),
+                    Type::Str => Ok(Str(val)),
+                    Type::Integer => {
+                        match i64::from_str(&val) {
+                            Ok(val) => Ok(Integer(val)),
+                            Err(_) => unparsable(val),
+                        }
+                    }
+                    Type::Float => {
+                        match f64::from_str(&val) {
+                            Ok(val) => Ok(Float(val)),
+                            Err(_) => unparsable(val),
+                        }
+                    }
+                    Type::Bool => {
+                        match val.as_str() {
+                            "true" => Ok(Bool(true)),
+                            "false" => Ok(Bool(false)),
+                            _ => unparsable(val),
+                        }
+                    }
+                    Type::Array(_) | Type::Tuple(_) | Type::Function(..) | Type::Named(_) |
+                    Type::Param(_) | Type::Var(_) => {
+                        unnatural(Type::Str)
                     }
                 }
             }
@@ -241,24 +799,25 @@ impl Value {
                 values,
             } => {
                 match *dest {
-                    Type::Void => Value::Void,
+                    Type::Void => Ok(Value::Void),
                     Type::Array(ref new_element_type) if **new_element_type == element_type => {
-                        Value::Array {
-                            element_type,
-                            values,
-                        }
+                        Ok(Value::Array {
+                               element_type,
+                               values,
+                           })
                     }
                     Type::Array(ref new_element_type) => {
-                        Value::Array {
-                            element_type: *new_element_type.clone(),
-                            values: values
-                                .into_iter()
-                                .map(|value| value.into(new_element_type))
-                                .collect(),
-                        }
+                        Ok(Value::Array {
+                               element_type: *new_element_type.clone(),
+                               values: values
+                                   .into_iter()
+                                   .map(|value| value.into(new_element_type))
+                                   .collect::<Result<_, _>>()?,
+                           })
                     }
-                    Type::Integer | Type::Float | Type::Bool | Type::Str | Type::Tuple(_) => {
-                        panic!("Unnatural conversion at runtime")
+                    Type::Integer | Type::Float | Type::Bool | Type::Str | Type::Tuple(_) |
+                    Type::Function(..) | Type::Named(_) | Type::Param(_) | Type::Var(_) => {
+                        unnatural(Type::Array(Box::new(element_type)))
                     }
                 }
             }
@@ -267,39 +826,96 @@ impl Value {
                 values,
             } => {
                 match *dest {
-                    Type::Void => Value::Void,
+                    Type::Void => Ok(Value::Void),
                     Type::Tuple(ref new_element_types) if *new_element_types == element_types => {
-                        Value::Tuple {
-                            element_types,
-                            values,
-                        }
+                        Ok(Value::Tuple {
+                               element_types,
+                               values,
+                           })
                     }
                     Type::Tuple(ref new_element_types) => {
-                        Value::Tuple {
-                            element_types: new_element_types.clone(),
-                            values: values
-                                .into_iter()
-                                .zip(new_element_types)
-                                .map(|(value, new_element_type)| value.into(new_element_type))
-                                .collect(),
-                        }
+                        Ok(Value::Tuple {
+                               element_types: new_element_types.clone(),
+                               values: values
+                                   .into_iter()
+                                   .zip(new_element_types)
+                                   .map(|(value, new_element_type)| value.into(new_element_type))
+                                   .collect::<Result<_, _>>()?,
+                           })
                     }
                     Type::Array(ref new_element_type) => {
-                        Value::Array {
-                            element_type: (**new_element_type).clone(),
-                            values: values
-                                .into_iter()
-                                .map(|value| value.into(new_element_type))
-                                .collect(),
-                        }
+                        Ok(Value::Array {
+                               element_type: (**new_element_type).clone(),
+                               values: values
+                                   .into_iter()
+                                   .map(|value| value.into(new_element_type))
+                                   .collect::<Result<_, _>>()?,
+                           })
+                    }
+                    Type::Integer | Type::Float | Type::Bool | Type::Str | Type::Function(..) |
+                    Type::Named(_) | Type::Param(_) | Type::Var(_) => {
+                        unnatural(Type::Tuple(element_types))
+                    }
+                }
+            }
+            Closure { decl, scopes } => {
+                match *dest {
+                    Type::Void => Ok(Value::Void),
+                    Type::Function(..) if *dest == decl.get_type() => {
+                        Ok(Closure { decl, scopes })
+                    }
+                    Type::Function(..) | Type::Integer | Type::Float | Type::Bool | Type::Str |
+                    Type::Array(_) | Type::Tuple(_) | Type::Named(_) | Type::Param(_) |
+                    Type::Var(_) => {
+                        unnatural(decl.get_type())
+                    }
+                }
+            }
+            Record { type_name, fields } => {
+                match *dest {
+                    Type::Void => Ok(Value::Void),
+                    Type::Named(ref name) if *name == type_name => Ok(Record { type_name, fields }),
+                    Type::Named(_) | Type::Param(_) | Type::Var(_) | Type::Integer | Type::Float |
+                    Type::Bool | Type::Str |
+                    Type::Array(_) | Type::Tuple(_) | Type::Function(..) => {
+                        unnatural(Type::Named(type_name))
+                    }
+                }
+            }
+            Variant {
+                type_name,
+                tag,
+                values,
+            } => {
+                match *dest {
+                    Type::Void => Ok(Value::Void),
+                    Type::Named(ref name) if *name == type_name => {
+                        Ok(Variant {
+                               type_name,
+                               tag,
+                               values,
+                           })
+                    }
+                    Type::Named(_) | Type::Param(_) | Type::Var(_) | Type::Integer | Type::Float |
+                    Type::Bool | Type::Str |
+                    Type::Array(_) | Type::Tuple(_) | Type::Function(..) => {
+                        unnatural(Type::Named(type_name))
                     }
-                    Type::Integer | Type::Float | Type::Bool | Type::Str => panic!("Unnatural conversion at runtime"),
                 }
             }
         }
     }
 }
 
+// Check that an index is in the bounds of a container of the given length
+fn check_index(index: i64, length: usize) -> Result<usize, RuntimeError> {
+    if index < 0 || index as usize >= length {
+        Err(IndexOutOfBoundsError::new(index, length, Span(0, 0)).into())
+    } else {
+        Ok(index as usize)
+    }
+}
+
 impl PartialOrd for Value {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         use self::Value::*;
@@ -334,6 +950,30 @@ impl fmt::Display for Value {
             Str(ref value) => write!(f, "{}", value),
             Array { ref values, .. } => write!(f, "[{}]", values.iter().join(", ")),
             Tuple { ref values, .. } => write!(f, "{{{}}}", values.iter().join(", ")),
+            Closure { ref decl, .. } => write!(f, "<{:?}>", decl.get_type()),
+            Record {
+                ref type_name,
+                ref fields,
+            } => {
+                write!(f,
+                       "{} {{{}}}",
+                       type_name,
+                       fields
+                           .iter()
+                           .map(|&(ref name, ref value)| format!("{} = {}", name, value))
+                           .join(", "))
+            }
+            Variant {
+                ref tag,
+                ref values,
+                ..
+            } => {
+                if values.is_empty() {
+                    write!(f, "{}", tag)
+                } else {
+                    write!(f, "{}({})", tag, values.iter().join(", "))
+                }
+            }
             Void => write!(f, "nil"),
         }
     }
@@ -431,54 +1071,3 @@ impl Match for SumType {
             .any(|candidate| candidate.match_with(given_type, types))
     }
 }
-
-/// Unescape a string
-///
-/// This will evaluate escape sequence and return the given string, unescaped.
-///
-/// If an escape sequence is misused, this function will return an error with the name of the
-/// misused escape sequence.
-pub fn unescape_str(input: &str) -> Result<String, char> {
-    let mut res = String::with_capacity(input.len());
-
-    let mut chars = input.chars();
-
-    while let Some(ch) = chars.next() {
-        res.push(if ch != '\\' {
-                     ch
-                 } else {
-                     match chars.next() {
-                         Some('x') => {
-                             if chars.clone().by_ref().count() < 2 {
-                                 return Err('x');
-                             }
-
-                             chars
-                                 .by_ref()
-                                 .take(2)
-                                 .fold(0u8,
-                                       |acc, c| acc * 16 + c.to_digit(16).unwrap() as u8) as
-                                 char
-                         }
-                         Some('u') => {
-                             if chars.clone().by_ref().count() < 4 {
-                                 return Err('u');
-                             }
-
-                             let val = chars
-                                 .by_ref()
-                                 .take(4)
-                                 .fold(0, |acc, c| acc * 16 + c.to_digit(16).unwrap());
-                             char::from_u32(val).unwrap()
-                         }
-                         Some('n') => '\n',
-                         Some('r') => '\r',
-                         Some('t') => '\t',
-                         Some(ch) => ch,
-                         None => panic!("Un-ended string"),
-                     }
-                 });
-    }
-
-    Ok(res)
-}